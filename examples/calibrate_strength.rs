@@ -0,0 +1,151 @@
+//! Measure the playing strength of each skill level against the full-strength engine.
+//!
+//! Usage: cargo run --release --example calibrate_strength -- <millis per move> <games per level> [levels...]
+//!
+//! Each level plays the requested number of games against the unrestricted engine, alternating
+//! colours, starting from the initial position after a few random plies. The Elo difference is
+//! estimated from the score percentage, so the results can be used to tune `elo_to_skill_level`.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rusty_rival::evaluate::insufficient_material;
+use rusty_rival::fen::get_position;
+use rusty_rival::make_move::make_move;
+use rusty_rival::move_constants::START_POS;
+use rusty_rival::moves::{generate_moves, is_check};
+use rusty_rival::strength::{search_with_strength, MAX_SKILL_LEVEL};
+use rusty_rival::types::{default_search_state, Move, Position, SearchState, BLACK, WHITE};
+use std::env;
+use std::ops::Add;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RANDOM_OPENING_PLIES: usize = 6;
+const MAX_GAME_PLIES: usize = 400;
+
+#[derive(PartialEq)]
+enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+fn legal_moves(position: &Position) -> Vec<Move> {
+    generate_moves(position)
+        .into_iter()
+        .filter(|m| {
+            let mut new_position = *position;
+            make_move(position, *m, &mut new_position);
+            !is_check(&new_position, position.mover)
+        })
+        .collect()
+}
+
+fn is_game_drawn(position: &Position, history: &[u128]) -> bool {
+    let piece_count = (position.pieces[WHITE as usize].all_pieces_bitboard.count_ones()
+        + position.pieces[BLACK as usize].all_pieces_bitboard.count_ones()) as u8;
+    position.half_moves >= 100
+        || history.iter().filter(|lock| **lock == position.zobrist_lock).count() >= 3
+        || insufficient_material(position, piece_count, false)
+}
+
+fn play_game(engines: &mut [SearchState; 2], white: usize, millis: u64, rng: &mut StdRng) -> GameResult {
+    let mut position = get_position(START_POS);
+    let mut history = vec![position.zobrist_lock];
+
+    for ply in 0..MAX_GAME_PLIES {
+        let moves = legal_moves(&position);
+        if moves.is_empty() {
+            return if !is_check(&position, position.mover) {
+                GameResult::Draw
+            } else if position.mover == WHITE {
+                GameResult::BlackWins
+            } else {
+                GameResult::WhiteWins
+            };
+        }
+        if is_game_drawn(&position, &history) {
+            return GameResult::Draw;
+        }
+
+        let mv = if ply < RANDOM_OPENING_PLIES {
+            *moves.choose(rng).unwrap()
+        } else {
+            let engine = if position.mover == WHITE { white } else { 1 - white };
+            let search_state = &mut engines[engine];
+            search_state.history = history.clone();
+            search_state.nodes_limit = u64::MAX;
+            search_state.end_time = Instant::now().add(Duration::from_millis(millis));
+            search_with_strength(&mut position, 100, search_state)
+        };
+
+        let mut new_position = position;
+        make_move(&position, mv, &mut new_position);
+        position = new_position;
+        history.push(position.zobrist_lock);
+    }
+    GameResult::Draw
+}
+
+fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn main() {
+    // Search states are large, so run on a thread with a bigger stack than the main thread
+    thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(calibrate)
+        .expect("Failed to spawn calibration thread")
+        .join()
+        .unwrap();
+}
+
+fn calibrate() {
+    let args: Vec<String> = env::args().collect();
+    let millis: u64 = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(100);
+    let games: u32 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(20);
+    let levels: Vec<u8> = if args.len() > 3 {
+        args[3..].iter().filter_map(|a| a.parse().ok()).collect()
+    } else {
+        (0..MAX_SKILL_LEVEL).step_by(2).collect()
+    };
+
+    let mut rng = StdRng::seed_from_u64(28);
+
+    println!("level  wins draws losses  score   elo");
+    for level in levels {
+        let mut engines = [default_search_state(), default_search_state()];
+        for engine in engines.iter_mut() {
+            engine.show_info = false;
+        }
+        engines[0].strength.skill_level = level;
+
+        let (mut wins, mut draws, mut losses) = (0, 0, 0);
+        for game in 0..games {
+            let white = (game % 2) as usize;
+            let result = play_game(&mut engines, white, millis, &mut rng);
+            let weak_engine_won = (white == 0 && result == GameResult::WhiteWins) || (white == 1 && result == GameResult::BlackWins);
+            if result == GameResult::Draw {
+                draws += 1;
+            } else if weak_engine_won {
+                wins += 1;
+            } else {
+                losses += 1;
+            }
+        }
+
+        let score = (wins as f64 + draws as f64 / 2.0) / games as f64;
+        println!(
+            "{:>5} {:>5} {:>5} {:>6} {:>6.1}% {:>+5.0}",
+            level,
+            wins,
+            draws,
+            losses,
+            score * 100.0,
+            elo_difference(score)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Fit the coefficients of the win/draw/loss model used by `UCI_ShowWDL`.
+//!
+//! Usage: cargo run --release --example fit_wdl -- <data file> [search depth]
+//!
+//! Each line of the data file holds a position and the result of the game it came from, from
+//! white's point of view, in either of the common formats:
+//!
+//!   rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1 [0.5]
+//!   rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c9 "1/2-1/2";
+//!
+//! Positions from self-play games or from PGN files converted to this format can both be used.
+//! Each position is scored with a shallow search and the model is fitted to the results, starting
+//! from the current defaults. The fitted coefficients are printed ready to paste into `wdl.rs`.
+
+use rusty_rival::fen::try_get_position;
+use rusty_rival::search::{iterative_deepening, total_material_value};
+use rusty_rival::types::{default_search_state, WHITE};
+use rusty_rival::wdl::{fit_wdl_model, parse_result_label, WdlSample, DEFAULT_WDL_MODEL};
+use std::env;
+use std::fs;
+use std::ops::Add;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn main() {
+    // Search states are large, so run on a thread with a bigger stack than the main thread
+    thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(fit)
+        .expect("Failed to spawn fitting thread")
+        .join()
+        .unwrap();
+}
+
+/// Split a data line into its FEN, with or without the move counters, and the rest of the line
+fn split_fen(line: &str) -> (String, &str) {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let mut fen_tokens = tokens.len().min(4);
+    while fen_tokens < tokens.len().min(6) && tokens[fen_tokens].parse::<u32>().is_ok() {
+        fen_tokens += 1;
+    }
+    let fen = tokens[..fen_tokens].join(" ");
+    let rest = line.splitn(fen_tokens + 1, char::is_whitespace).nth(fen_tokens).unwrap_or("");
+    (fen, rest)
+}
+
+fn fit() {
+    let args: Vec<String> = env::args().collect();
+    let path = args.get(1).expect("usage: fit_wdl <data file> [search depth]");
+    let depth: u8 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(4);
+    let data = fs::read_to_string(path).expect("Failed to read data file");
+
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+
+    let mut samples = vec![];
+    let mut skipped = 0;
+    for line in data.lines().filter(|l| !l.trim().is_empty()) {
+        let (fen, label) = split_fen(line);
+        let (mut position, result) = match (try_get_position(&fen), parse_result_label(label)) {
+            (Ok(position), Some(result)) => (position, result),
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        search_state.end_time = Instant::now().add(Duration::from_secs(60));
+        if iterative_deepening(&mut position, depth, &mut search_state) == 0 {
+            skipped += 1;
+            continue;
+        }
+        samples.push(WdlSample {
+            score: search_state.current_best.1,
+            material: total_material_value(&position),
+            result: if position.mover == WHITE { result } else { 1.0 - result },
+        });
+    }
+    println!("{} positions scored, {} skipped", samples.len(), skipped);
+
+    let model = fit_wdl_model(&samples, DEFAULT_WDL_MODEL);
+    println!(
+        "loss before {:.5} after {:.5}",
+        DEFAULT_WDL_MODEL.loss(&samples),
+        model.loss(&samples)
+    );
+    println!(
+        "pub const DEFAULT_WDL_MODEL: WdlModel = WdlModel {{\n    a: ({:.1}, {:.1}),\n    b: ({:.1}, {:.1}),\n}};",
+        model.a.0, model.a.1, model.b.0, model.b.1
+    );
+}
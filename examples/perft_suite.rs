@@ -0,0 +1,42 @@
+//! Run a perft suite in the standard perftsuite.epd format, where each line holds a FEN followed
+//! by the expected node counts, as in `<fen> ;D1 20 ;D2 400 ;D3 8902`.
+//!
+//! Usage: cargo run --release --example perft_suite -- <suite file> [max depth]
+//!
+//! Counts deeper than the maximum depth, 6 by default, are skipped.
+
+use rusty_rival::perft::{run_perft_suite, PerftHashTable, PERFT_HASH_ENTRIES};
+use std::env;
+use std::fs;
+use std::process::exit;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let path = args.get(1).expect("usage: perft_suite <suite file> [max depth]");
+    let max_depth: u8 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(6);
+    let suite = fs::read_to_string(path).expect("Failed to read suite file");
+
+    let start = Instant::now();
+    let hash_table = PerftHashTable::new(PERFT_HASH_ENTRIES);
+    match run_perft_suite(&suite, max_depth, &hash_table) {
+        Ok((checked, failures)) => {
+            for failure in &failures {
+                println!("FAIL {}", failure);
+            }
+            println!(
+                "{} of {} counts correct in {:?}",
+                checked - failures.len(),
+                checked,
+                start.elapsed()
+            );
+            if !failures.is_empty() {
+                exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    }
+}
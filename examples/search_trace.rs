@@ -0,0 +1,50 @@
+//! Search a position to a fixed depth and show the nodes searched, with the pruning rules and
+//! extensions that applied at each one.
+//!
+//! Usage: cargo run --release --features search-trace --example search_trace -- <depth> <max ply> <fen> [--json] [move ...]
+//!
+//! The text log shows only the nodes on the way to and below the given moves, so
+//! `search_trace 6 4 "<bl2 fen>" d3f3` shows what happened in the search of d3f3. With `--json`
+//! the whole tree is written out instead.
+
+use rusty_rival::fen::{algebraic_move_from_move, get_position};
+use rusty_rival::search::iterative_deepening;
+use rusty_rival::search_trace::{finish_trace, start_trace, trace_to_json, trace_to_text};
+use rusty_rival::types::default_search_state;
+use std::env;
+use std::ops::Add;
+use std::process::exit;
+use std::time::{Duration, Instant};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (depth, max_ply, fen) = match (
+        args.get(1).and_then(|a| a.parse::<u8>().ok()),
+        args.get(2).and_then(|a| a.parse::<u8>().ok()),
+        args.get(3),
+    ) {
+        (Some(depth), Some(max_ply), Some(fen)) if depth > 0 => (depth, max_ply, fen),
+        _ => {
+            eprintln!("usage: search_trace <depth> <max ply> <fen> [--json] [move ...]");
+            exit(2);
+        }
+    };
+    let json = args.iter().any(|a| a == "--json");
+    let filter: Vec<&str> = args[4..].iter().filter(|a| *a != "--json").map(|a| a.as_str()).collect();
+
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.end_time = Instant::now().add(Duration::from_secs(24 * 60 * 60));
+    let mut position = get_position(fen);
+
+    start_trace(max_ply);
+    let mv = iterative_deepening(&mut position, depth, &mut search_state);
+    let trace = finish_trace();
+
+    if json {
+        println!("{}", trace_to_json(&trace));
+    } else {
+        print!("{}", trace_to_text(&trace, &filter));
+        println!("bestmove {}", algebraic_move_from_move(mv));
+    }
+}
@@ -0,0 +1,44 @@
+//! Check perft against shakmaty and report the first position where the move lists differ.
+//!
+//! Usage: cargo run --release --example verify_perft -- <depth> [fen ...]
+//!
+//! With no FENs, a built-in set of positions with en passant, promotion, castling and pin
+//! subtleties is checked.
+
+use rusty_rival::perft_verify::{verify_perft, PerftError, PERFT_VERIFY_FENS};
+use std::env;
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let depth: u8 = match args.get(1).and_then(|a| a.parse().ok()) {
+        Some(depth) if depth > 0 => depth,
+        _ => {
+            eprintln!("usage: verify_perft <depth> [fen ...]");
+            exit(2);
+        }
+    };
+    let fens: Vec<&str> = if args.len() > 2 {
+        args[2..].iter().map(|f| f.as_str()).collect()
+    } else {
+        PERFT_VERIFY_FENS.to_vec()
+    };
+
+    let mut failures = 0;
+    for fen in fens {
+        match verify_perft(fen, depth) {
+            Ok(nodes) => println!("ok    {} nodes  {}", nodes, fen),
+            Err(PerftError::Divergence(divergence)) => {
+                failures += 1;
+                println!("FAIL  {}\n      {}", fen, divergence)
+            }
+            Err(PerftError::InvalidFen(e)) => {
+                failures += 1;
+                println!("ERROR {}  {}", e, fen)
+            }
+        }
+    }
+    if failures > 0 {
+        exit(1);
+    }
+}
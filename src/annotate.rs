@@ -0,0 +1,187 @@
+//! Annotating the games of a PGN with the engine's view of every move
+//!
+//! Each move of the main line is searched twice with the same limits: once for the best move,
+//! and once restricted to the move played with `search_moves`. The comment after the move gives
+//! the played move's score in pawns from white's point of view and the depth reached, as
+//! `{+0.35/18}`. A move that loses at least the inaccuracy, mistake or blunder threshold against
+//! the best move is marked `?!`, `?` or `??`, and the best line follows it as a variation.
+
+use std::cmp::max;
+
+use crate::fen::{algebraic_move_from_move, get_fen, try_get_position};
+use crate::make_move::make_move;
+use crate::pgn::{uci_to_san, PgnGame};
+use crate::search::{iterative_deepening, mate_in_moves};
+use crate::search_limits::SearchLimits;
+use crate::types::{is_stopped, Move, PathScore, Position, Score, SearchState, WHITE};
+use crate::uci_command::{AnnotateParams, DEFAULT_ANNOTATE_DEPTH};
+use crate::utils::hydrate_move_from_algebraic_move;
+
+/// Movetext lines are wrapped at this width
+pub const PGN_LINE_WIDTH: usize = 79;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveAnnotation {
+    pub san: String,
+    /// Score of the move played, from white's point of view
+    pub score: Score,
+    pub depth: u8,
+    /// Centipawns lost against the best move, never negative
+    pub loss: Score,
+    pub best_score: Score,
+    /// The best line in SAN, starting with the best move
+    pub best_line: Vec<String>,
+}
+
+pub fn annotate_limits(params: &AnnotateParams) -> SearchLimits {
+    let default_depth = if params.move_time.is_some() {
+        None
+    } else {
+        Some(DEFAULT_ANNOTATE_DEPTH)
+    };
+    SearchLimits {
+        depth: params.depth.or(default_depth).unwrap_or(SearchLimits::default().depth),
+        hard_millis: params.move_time,
+        ..SearchLimits::default()
+    }
+}
+
+/// The suffix for a move that lost `loss` centipawns against the best move
+pub fn move_suffix(loss: Score, params: &AnnotateParams) -> &'static str {
+    if loss >= params.blunder {
+        "??"
+    } else if loss >= params.mistake {
+        "?"
+    } else if loss >= params.inaccuracy {
+        "?!"
+    } else {
+        ""
+    }
+}
+
+/// A score from white's point of view in pawns, or as a mate in moves
+pub fn format_score(score: Score) -> String {
+    match mate_in_moves(score) {
+        Some(moves) if moves > 0 => format!("+M{}", moves),
+        Some(moves) => format!("-M{}", -moves),
+        None => format!("{:+.2}", score as f64 / 100.0),
+    }
+}
+
+/// Search the position, only considering `search_moves` if given, and return the principal
+/// variation with the depth of the last completed iteration
+pub fn search_position(
+    position: &mut Position,
+    limits: &SearchLimits,
+    search_moves: Option<Vec<Move>>,
+    search_state: &mut SearchState,
+) -> (PathScore, u8) {
+    search_state.nodes = 0;
+    search_state.search_moves = search_moves;
+    limits.apply(search_state);
+    iterative_deepening(position, limits.depth, search_state);
+    search_state.search_moves = None;
+    let depth = if is_stopped(&search_state.stop) {
+        search_state.iterative_depth - 1
+    } else {
+        search_state.iterative_depth
+    };
+    (search_state.current_best.clone(), max(1, depth))
+}
+
+pub fn annotate_game(game: &PgnGame, params: &AnnotateParams, search_state: &mut SearchState) -> Result<Vec<MoveAnnotation>, String> {
+    let mut position = try_get_position(game.start_fen()).map_err(|e| format!("Invalid FEN: {}", e))?;
+    let uci_moves = game.uci_moves()?;
+    let limits = annotate_limits(params);
+    search_state.history = vec![position.zobrist_lock];
+
+    let mut annotations = vec![];
+    for (uci, san) in uci_moves.iter().zip(&game.moves) {
+        let played = hydrate_move_from_algebraic_move(&position, uci.clone());
+        let ((best_pv, best_score), best_depth) = search_position(&mut position, &limits, None, search_state);
+        let (score, depth) = if best_pv[0] == played {
+            (best_score, best_depth)
+        } else {
+            let ((_, score), depth) = search_position(&mut position, &limits, Some(vec![played]), search_state);
+            (score, depth)
+        };
+        let white = if position.mover == WHITE { 1 } else { -1 };
+        let best_line: Vec<String> = best_pv.iter().filter(|m| **m != 0).map(|m| algebraic_move_from_move(*m)).collect();
+
+        annotations.push(MoveAnnotation {
+            san: san.clone(),
+            score: score * white,
+            depth,
+            loss: max(0, best_score - score),
+            best_score: best_score * white,
+            best_line: uci_to_san(&get_fen(&position), &best_line),
+        });
+
+        let mut new_position = position;
+        make_move(&position, played, &mut new_position);
+        position = new_position;
+        search_state.history.push(position.zobrist_lock);
+    }
+    Ok(annotations)
+}
+
+/// The move number to write before the move `ply` plies from the start of the game. Black moves
+/// only get one when `black_numbered`, as the first move of a line or after a comment.
+fn numbering(start: &Position, ply: usize, black_numbered: bool) -> String {
+    let plies_from_white = ply + usize::from(start.mover != WHITE);
+    let number = start.move_number as usize + plies_from_white / 2;
+    match (plies_from_white.is_multiple_of(2), black_numbered) {
+        (true, _) => format!("{}. ", number),
+        (false, true) => format!("{}... ", number),
+        (false, false) => String::new(),
+    }
+}
+
+/// Write the game as PGN with the annotations of its moves
+pub fn annotated_pgn(game: &PgnGame, annotations: &[MoveAnnotation], params: &AnnotateParams) -> Result<String, String> {
+    let start = try_get_position(game.start_fen()).map_err(|e| format!("Invalid FEN: {}", e))?;
+    let mut pgn = String::new();
+    for (name, value) in game.tags.iter().filter(|(name, _)| name != "Annotator") {
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    pgn.push_str(&format!("[Annotator \"Rusty Rival {}\"]\n\n", env!("CARGO_PKG_VERSION")));
+
+    // Move numbers stay on the same line as their moves
+    let mut tokens = vec![];
+    for (ply, annotation) in annotations.iter().enumerate() {
+        let suffix = move_suffix(annotation.loss, params);
+        // A black move follows a comment, so it always gets its move number
+        tokens.push(format!("{}{}{}", numbering(&start, ply, true), annotation.san, suffix));
+        tokens.push(format!("{{{}/{}}}", format_score(annotation.score), annotation.depth));
+
+        if !suffix.is_empty() && !annotation.best_line.is_empty() {
+            let mut variation = vec![];
+            for (i, san) in annotation.best_line.iter().enumerate() {
+                variation.push(format!("{}{}", numbering(&start, ply + i, i == 0), san));
+                if i == 0 {
+                    variation.push(format!("{{{}}}", format_score(annotation.best_score)));
+                }
+            }
+            variation[0].insert(0, '(');
+            variation.last_mut().unwrap().push(')');
+            tokens.extend(variation);
+        }
+    }
+    tokens.push(game.result.clone());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > PGN_LINE_WIDTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    Ok(pgn)
+}
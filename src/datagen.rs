@@ -0,0 +1,202 @@
+//! Training data for tuning the evaluation, from fast self-play games
+//!
+//! Each game starts with a few random legal plies from the start position and is then played out
+//! by fixed node searches in deterministic mode. Every quiet position reached, one that is not in
+//! check where the best move is not a capture or promotion and the score is not a mate, is written
+//! as a line `<fen> | <score> | <result>`. The score is in centipawns and the result is 1.0, 0.5 or
+//! 0.0, both from white's point of view.
+//!
+//! Game n is played from `seed + n` alone, so the same seed gives the same file whatever the
+//! number of threads. Games are written in order, each as soon as the games before it are done.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::engine_constants::MAX_DEPTH;
+use crate::evaluate::insufficient_material;
+use crate::fen::{get_fen, get_position};
+use crate::make_move::make_move;
+use crate::move_constants::{PROMOTION_FULL_MOVE_MASK, START_POS};
+use crate::moves::{generate_legal_moves, is_check};
+use crate::search::{iterative_deepening, MATE_START};
+use crate::search_limits::SearchLimits;
+use crate::types::{MaterialHashTable, PawnHashTable, Position, Score, SearchState, SharedHashTable, WHITE};
+use crate::uci_command::DatagenParams;
+use crate::utils::is_capture;
+
+/// Each thread has its own hash table, which deterministic mode clears before every move
+pub const DATAGEN_HASH_MB: usize = 4;
+
+/// Plies the score must stay beyond the adjudication threshold before a game is called
+pub const ADJUDICATION_PLIES: usize = 4;
+
+/// Games still going after this many plies are scored as draws
+pub const MAX_GAME_PLIES: usize = 400;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatagenGame {
+    /// The FEN and white's search score of each quiet position
+    pub positions: Vec<(String, Score)>,
+    /// White's points from the game
+    pub result: f32,
+}
+
+impl DatagenGame {
+    pub fn lines(&self) -> String {
+        self.positions
+            .iter()
+            .map(|(fen, score)| format!("{} | {} | {:.1}\n", fen, score, self.result))
+            .collect()
+    }
+}
+
+/// White's points if the game is over, from checkmate, stalemate, the fifty move rule, threefold
+/// repetition or insufficient material. `history` holds the zobrist locks of the game so far.
+pub fn game_result(position: &Position, history: &[u128]) -> Option<f32> {
+    if generate_legal_moves(position).is_empty() {
+        return Some(match (is_check(position, position.mover), position.mover == WHITE) {
+            (true, true) => 0.0,
+            (true, false) => 1.0,
+            (false, _) => 0.5,
+        });
+    }
+    let piece_count = (position.pieces[0].all_pieces_bitboard | position.pieces[1].all_pieces_bitboard).count_ones() as u8;
+    let repetitions = history.iter().filter(|lock| **lock == position.zobrist_lock).count();
+    if position.half_moves >= 100 || repetitions >= 3 || insufficient_material(position, piece_count, false) {
+        return Some(0.5);
+    }
+    None
+}
+
+/// The start position after `plies` random legal moves, trying again whenever the game ends early
+fn random_opening<R: Rng>(rng: &mut R, plies: u8) -> Position {
+    'attempt: loop {
+        let mut position = get_position(START_POS);
+        for _ in 0..plies {
+            let moves = generate_legal_moves(&position);
+            if moves.is_empty() {
+                continue 'attempt;
+            }
+            let mut new_position = position;
+            make_move(&position, moves[rng.gen_range(0..moves.len())], &mut new_position);
+            position = new_position;
+        }
+        if !generate_legal_moves(&position).is_empty() {
+            return position;
+        }
+    }
+}
+
+/// Play game number `game` of a datagen run with the given search state, which should be in
+/// deterministic mode for the game to depend only on the seed
+pub fn play_game(params: &DatagenParams, game: u32, search_state: &mut SearchState) -> DatagenGame {
+    let mut rng = StdRng::seed_from_u64(params.seed.wrapping_add(game as u64));
+    let mut position = random_opening(&mut rng, params.random_plies);
+    let limits = SearchLimits {
+        nodes: params.nodes,
+        ..SearchLimits::default()
+    };
+    search_state.history = vec![position.zobrist_lock];
+
+    let mut positions = vec![];
+    let mut winning_side = 0;
+    let mut winning_plies = 0;
+    for _ in 0..MAX_GAME_PLIES {
+        if let Some(result) = game_result(&position, &search_state.history) {
+            return DatagenGame { positions, result };
+        }
+
+        search_state.nodes = 0;
+        limits.apply(search_state);
+        let mv = iterative_deepening(&mut position, MAX_DEPTH, search_state);
+        let score = search_state.current_best.1;
+        let white_score = if position.mover == WHITE { score } else { -score };
+
+        let quiet = !is_check(&position, position.mover) && !is_capture(&position, mv) && mv & PROMOTION_FULL_MOVE_MASK == 0;
+        if quiet && score.abs() < MATE_START {
+            positions.push((get_fen(&position), white_score));
+        }
+
+        let side = if white_score >= params.adjudicate {
+            1
+        } else if white_score <= -params.adjudicate {
+            -1
+        } else {
+            0
+        };
+        winning_plies = if side != 0 && side == winning_side { winning_plies + 1 } else { 1 };
+        winning_side = side;
+        if winning_side != 0 && winning_plies >= ADJUDICATION_PLIES {
+            let result = if winning_side > 0 { 1.0 } else { 0.0 };
+            return DatagenGame { positions, result };
+        }
+
+        let mut new_position = position;
+        make_move(&position, mv, &mut new_position);
+        position = new_position;
+        search_state.history.push(position.zobrist_lock);
+    }
+    DatagenGame { positions, result: 0.5 }
+}
+
+/// A copy of the engine's search state for one datagen thread, with its own hash tables and stop flag
+fn worker_search_state(search_state: &SearchState) -> SearchState {
+    let mut worker = search_state.clone();
+    worker.hash_table = Arc::new(SharedHashTable::new_with_mb(DATAGEN_HASH_MB));
+    worker.pawn_hash_table = Arc::new(PawnHashTable::new());
+    worker.material_hash_table = Arc::new(MaterialHashTable::new());
+    worker.stop = Arc::new(AtomicBool::new(false));
+    worker.deterministic = true;
+    worker.show_info = false;
+    worker.multi_pv = 1;
+    worker.ignore_root_move = 0;
+    worker.search_moves = None;
+    worker.mate_search = None;
+    worker
+}
+
+/// Play the games of a datagen run on `params.threads` threads and write their positions to `out`.
+/// Returns the number of positions written.
+pub fn run_datagen<W: Write>(params: &DatagenParams, search_state: &SearchState, out: &mut W) -> io::Result<usize> {
+    let next_game = AtomicU32::new(0);
+    let (sender, receiver) = mpsc::channel::<(u32, DatagenGame)>();
+
+    thread::scope(|scope| {
+        for _ in 0..params.threads {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            let mut worker = worker_search_state(search_state);
+            thread::Builder::new()
+                .stack_size(64 * 1024 * 1024)
+                .spawn_scoped(scope, move || loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    // The receiver is gone if writing failed
+                    if game >= params.games || sender.send((game, play_game(params, game, &mut worker))).is_err() {
+                        break;
+                    }
+                })
+                .expect("Failed to spawn datagen thread");
+        }
+        drop(sender);
+
+        let mut finished = BTreeMap::new();
+        let mut next_to_write = 0;
+        let mut positions = 0;
+        for (game, result) in receiver {
+            finished.insert(game, result);
+            while let Some(result) = finished.remove(&next_to_write) {
+                out.write_all(result.lines().as_bytes())?;
+                positions += result.positions.len();
+                next_to_write += 1;
+            }
+        }
+        Ok(positions)
+    })
+}
@@ -0,0 +1,277 @@
+//! Specialised evaluation of basic endgames, found by material signature
+//!
+//! The material key packs the number of pawns, knights, bishops, rooks and queens of each side
+//! into four bits apiece, so it can be kept up to date as pieces are captured and promoted, and
+//! compared directly. Each endgame in `ENDGAMES` is written for the stronger side as white, like
+//! "KBNK", and registered for both colours. An endgame either replaces the evaluation with its own
+//! score, or gives a scale factor out of `SCALE_FACTOR_NORMAL` for the normal one. Endgames that
+//! hold whatever pawns are on the board, like opposite-coloured bishops, are looked up with the
+//! pawns left out.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::bitboards::DARK_SQUARES_BITS;
+use crate::engine_constants::{QUEEN_VALUE_PAIR, ROOK_VALUE_PAIR};
+use crate::opponent;
+use crate::types::{Bitboard, MaterialKey, Mover, Position, Score, Square, BLACK, WHITE};
+
+/// The normal evaluation is multiplied by the scale factor and divided by this
+pub const SCALE_FACTOR_NORMAL: Score = 64;
+
+/// Added to the score of endgames the strong side is known to win
+pub const KNOWN_WIN: Score = 4000;
+
+/// The pieces counted by the material key, in the order of their bits
+pub const MATERIAL_KEY_PIECES: [char; 5] = ['P', 'N', 'B', 'R', 'Q'];
+
+const PAWN_KEY_MASK: MaterialKey = 0xf | 0xf << 20;
+
+#[derive(Clone, Copy)]
+pub enum EndgameRule {
+    /// The score from the strong side's point of view
+    Evaluate(fn(&Position, Mover) -> Score),
+    /// The scale factor for the normal evaluation
+    Scale(fn(&Position, Mover) -> Score),
+}
+
+pub struct Endgame {
+    pub code: &'static str,
+    pub rule: EndgameRule,
+    /// Found whatever the pawns on the board, which are left out of the code
+    pub any_pawns: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndgameEvaluation {
+    /// The score from the side to move's point of view
+    Exact(Score),
+    Scaled(Score),
+}
+
+impl Endgame {
+    pub fn evaluate(&self, position: &Position, strong: Mover) -> EndgameEvaluation {
+        match self.rule {
+            EndgameRule::Evaluate(evaluate) => {
+                let score = evaluate(position, strong);
+                EndgameEvaluation::Exact(if position.mover == strong { score } else { -score })
+            }
+            EndgameRule::Scale(scale) => EndgameEvaluation::Scaled(scale(position, strong)),
+        }
+    }
+}
+
+pub const ENDGAMES: [Endgame; 6] = [
+    Endgame {
+        code: "KBNK",
+        rule: EndgameRule::Evaluate(evaluate_kbnk),
+        any_pawns: false,
+    },
+    Endgame {
+        code: "KQKR",
+        rule: EndgameRule::Evaluate(evaluate_kqkr),
+        any_pawns: false,
+    },
+    Endgame {
+        code: "KRKP",
+        rule: EndgameRule::Evaluate(evaluate_krkp),
+        any_pawns: false,
+    },
+    Endgame {
+        code: "KRKB",
+        rule: EndgameRule::Evaluate(evaluate_krkb),
+        any_pawns: false,
+    },
+    Endgame {
+        code: "KRKN",
+        rule: EndgameRule::Evaluate(evaluate_krkn),
+        any_pawns: false,
+    },
+    Endgame {
+        code: "KBKB",
+        rule: EndgameRule::Scale(scale_opposite_bishops),
+        any_pawns: true,
+    },
+];
+
+#[inline(always)]
+fn key_shift(colour: Mover, piece_index: usize) -> u32 {
+    (colour as u32 * 5 + piece_index as u32) * 4
+}
+
+/// What one piece adds to the material key, with `piece_index` in the order of `MATERIAL_KEY_PIECES`
+#[inline(always)]
+pub fn material_key_unit(colour: Mover, piece_index: usize) -> MaterialKey {
+    1 << key_shift(colour, piece_index)
+}
+
+/// The material key computed from scratch; positions keep theirs up to date as moves are made
+pub fn material_key(position: &Position) -> MaterialKey {
+    let mut key = 0;
+    for colour in [WHITE, BLACK] {
+        let pieces = &position.pieces[colour as usize];
+        let counts = [
+            pieces.pawn_bitboard,
+            pieces.knight_bitboard,
+            pieces.bishop_bitboard,
+            pieces.rook_bitboard,
+            pieces.queen_bitboard,
+        ];
+        for (index, bitboard) in counts.iter().enumerate() {
+            key |= (bitboard.count_ones() as MaterialKey).min(15) << key_shift(colour, index);
+        }
+    }
+    key
+}
+
+/// The material key of an endgame code such as "KRKP", with the pieces of white up to the second
+/// king and black's after it
+pub fn material_key_from_code(code: &str) -> MaterialKey {
+    let (white, black) = code[1..].split_at(code[1..].find('K').unwrap_or(code.len() - 1));
+    let mut key = 0;
+    for (colour, pieces) in [(WHITE, white), (BLACK, black)] {
+        for piece in pieces.chars() {
+            if let Some(index) = MATERIAL_KEY_PIECES.iter().position(|p| *p == piece) {
+                key += material_key_unit(colour, index);
+            }
+        }
+    }
+    key
+}
+
+/// The same material with the colours swapped
+pub fn flip_material_key(key: MaterialKey) -> MaterialKey {
+    (key & 0xfffff) << 20 | key >> 20
+}
+
+static ENDGAME_REGISTRY: OnceLock<HashMap<MaterialKey, (usize, Mover)>> = OnceLock::new();
+
+fn endgame_registry() -> &'static HashMap<MaterialKey, (usize, Mover)> {
+    ENDGAME_REGISTRY.get_or_init(|| {
+        let mut registry = HashMap::new();
+        for (index, endgame) in ENDGAMES.iter().enumerate() {
+            let key = material_key_from_code(endgame.code);
+            registry.insert(flip_material_key(key), (index, BLACK));
+            registry.insert(key, (index, WHITE));
+        }
+        registry
+    })
+}
+
+/// The endgame with this material and the colour of its strong side, if there is one
+pub fn find_endgame(key: MaterialKey) -> Option<(&'static Endgame, Mover)> {
+    let registry = endgame_registry();
+    registry
+        .get(&key)
+        .or_else(|| {
+            registry
+                .get(&(key & !PAWN_KEY_MASK))
+                .filter(|(index, _)| ENDGAMES[*index].any_pawns)
+        })
+        .map(|(index, strong)| (&ENDGAMES[*index], *strong))
+}
+
+#[inline(always)]
+fn file_of(square: Square) -> i32 {
+    7 - (square % 8) as i32
+}
+
+#[inline(always)]
+fn rank_of(square: Square) -> i32 {
+    (square / 8) as i32
+}
+
+#[inline(always)]
+fn distance(a: Square, b: Square) -> i32 {
+    (file_of(a) - file_of(b)).abs().max((rank_of(a) - rank_of(b)).abs())
+}
+
+/// From 0 in the centre to 90 in the corners
+fn push_to_edge(square: Square) -> Score {
+    let file_from_centre = (2 * file_of(square) - 7).abs() / 2;
+    let rank_from_centre = (2 * rank_of(square) - 7).abs() / 2;
+    15 * (file_from_centre + rank_from_centre)
+}
+
+/// Larger the closer the two squares are
+fn push_close(a: Square, b: Square) -> Score {
+    140 - 20 * distance(a, b)
+}
+
+fn kings(position: &Position, strong: Mover) -> (Square, Square) {
+    (
+        position.pieces[strong as usize].king_square,
+        position.pieces[opponent!(strong) as usize].king_square,
+    )
+}
+
+fn first_square(bitboard: Bitboard) -> Square {
+    bitboard.trailing_zeros() as Square
+}
+
+/// Mate can only be forced in a corner the bishop controls, so the weak king is driven there
+fn evaluate_kbnk(position: &Position, strong: Mover) -> Score {
+    let (strong_king, weak_king) = kings(position, strong);
+    let bishop = position.pieces[strong as usize].bishop_bitboard;
+    // h1 is square 0 and a light square, a1 is square 7 and dark
+    let corners: [Square; 2] = if bishop & DARK_SQUARES_BITS != 0 { [7, 56] } else { [0, 63] };
+    let corner_distance = corners.iter().map(|corner| distance(weak_king, *corner)).min().unwrap_or(0);
+    KNOWN_WIN + push_close(strong_king, weak_king) + 40 * (7 - corner_distance) + push_to_edge(weak_king)
+}
+
+fn evaluate_kqkr(position: &Position, strong: Mover) -> Score {
+    let (strong_king, weak_king) = kings(position, strong);
+    QUEEN_VALUE_PAIR.1 - ROOK_VALUE_PAIR.1 + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+}
+
+/// Won when the rook side's king is in front of the pawn or the weak king is far from both pawn
+/// and rook, otherwise drawish, and more so the further the pawn has gone
+fn evaluate_krkp(position: &Position, strong: Mover) -> Score {
+    let weak = opponent!(strong);
+    // Seen with the rook side as white, so the pawn runs down the board
+    let flip = if strong == WHITE { 0 } else { 56 };
+    let (strong_king, weak_king) = kings(position, strong);
+    let (strong_king, weak_king) = (strong_king ^ flip, weak_king ^ flip);
+    let rook = first_square(position.pieces[strong as usize].rook_bitboard) ^ flip;
+    let pawn = first_square(position.pieces[weak as usize].pawn_bitboard) ^ flip;
+    let queening = pawn % 8;
+    let weak_to_move = i32::from(position.mover == weak);
+
+    let king_in_front = file_of(strong_king) == file_of(pawn) && rank_of(strong_king) < rank_of(pawn);
+    let weak_king_too_far = distance(weak_king, pawn) >= 3 + weak_to_move && distance(weak_king, rook) >= 3;
+    if king_in_front || weak_king_too_far {
+        ROOK_VALUE_PAIR.1 - distance(strong_king, pawn)
+    } else if rank_of(weak_king) <= 2
+        && distance(weak_king, pawn) == 1
+        && rank_of(strong_king) >= 3
+        && distance(strong_king, pawn) > 3 - weak_to_move
+    {
+        80 - 8 * distance(strong_king, pawn)
+    } else {
+        200 - 8 * (distance(strong_king, pawn - 8) - distance(weak_king, pawn - 8) - distance(pawn, queening))
+    }
+}
+
+/// A draw unless the defending king is caught on the edge
+fn evaluate_krkb(position: &Position, strong: Mover) -> Score {
+    let (_, weak_king) = kings(position, strong);
+    push_to_edge(weak_king)
+}
+
+/// A draw unless the defending king is caught on the edge or the knight strays from it
+fn evaluate_krkn(position: &Position, strong: Mover) -> Score {
+    let (_, weak_king) = kings(position, strong);
+    let knight = first_square(position.pieces[opponent!(strong) as usize].knight_bitboard);
+    push_to_edge(weak_king) + 15 * distance(weak_king, knight)
+}
+
+/// Bishops on opposite colours draw unless one side is well ahead on pawns
+fn scale_opposite_bishops(position: &Position, _strong: Mover) -> Score {
+    let white = &position.pieces[WHITE as usize];
+    let black = &position.pieces[BLACK as usize];
+    if (white.bishop_bitboard & DARK_SQUARES_BITS != 0) == (black.bishop_bitboard & DARK_SQUARES_BITS != 0) {
+        return SCALE_FACTOR_NORMAL;
+    }
+    let pawn_difference = (white.pawn_bitboard.count_ones() as Score - black.pawn_bitboard.count_ones() as Score).abs();
+    (16 + 12 * pawn_difference).min(SCALE_FACTOR_NORMAL)
+}
@@ -19,7 +19,9 @@ pub const STARTING_MATERIAL: Score =
 
 pub const HISTORY_MAX_SCORE: Score = (HistoryScore::MAX / 2) as Score;
 
-pub const UCI_MILLIS_REDUCTION: u128 = 5;
+// Default for the Move Overhead option: milliseconds kept back from each move for GUI and network lag
+pub const DEFAULT_MOVE_OVERHEAD_MILLIS: u64 = 10;
+pub const MAX_MOVE_OVERHEAD_MILLIS: u64 = 5000;
 
 pub const BETA_PRUNE_MARGIN_PER_DEPTH: Score = 200;
 pub const BETA_PRUNE_MAX_DEPTH: u8 = 4;
@@ -74,13 +76,19 @@ pub const MAX_QUIESCE_DEPTH: u8 = 100;
 pub const HASH_ENTRY_BYTES: u64 = 22;
 pub const HASH_SIZE_MB: u64 = 128;
 pub const NUM_HASH_ENTRIES: u64 = (1024 * 1024 * HASH_SIZE_MB) / HASH_ENTRY_BYTES;
+// Hash entries sampled to estimate hashfull, as in other engines
+pub const HASHFULL_SAMPLE_SIZE: usize = 1000;
 
 // Pawn hash table: 16K entries, each entry is 20 bytes (16 byte key + 4 byte score)
 pub const NUM_PAWN_HASH_ENTRIES: usize = 16384;
+// Material hash table: 8K entries, far more than the material configurations a search reaches
+pub const NUM_MATERIAL_HASH_ENTRIES: usize = 8192;
 // SPSA tuned: base=130, per_depth=63
 pub const ALPHA_PRUNE_MARGINS: [Score; 8] = [130, 193, 256, 319, 382, 445, 508, 571];
 
 pub const TICKER_MILLIS: u16 = 500;
+// Root moves are only announced with currmove once the search has run this long
+pub const CURRMOVE_INFO_MILLIS: u128 = 3000;
 
 pub const IID_MIN_DEPTH: u8 = 3;
 pub const IID_SEARCH_DEPTH: u8 = 2;
@@ -5,6 +5,7 @@ use crate::bitboards::{
     KING_MOVES_BITBOARDS, KNIGHT_MOVES_BITBOARDS, LIGHT_SQUARES_BITS, RANK_1_BITS, RANK_2_BITS, RANK_3_BITS, RANK_4_BITS, RANK_5_BITS,
     RANK_6_BITS, RANK_7_BITS, RANK_8_BITS, ROOK_RAYS,
 };
+use crate::endgame::{find_endgame, EndgameEvaluation, SCALE_FACTOR_NORMAL};
 use crate::engine_constants::{
     BAD_BISHOP_PAWN_THRESHOLD, BAD_BISHOP_PENALTY_PER_PAWN, BISHOP_KNIGHT_IMBALANCE_BONUS, BISHOP_VALUE_AVERAGE, BISHOP_VALUE_PAIR,
     BLOCKED_PASSED_PAWN_PENALTY, DOUBLED_PAWN_PENALTY, ENDGAME_MATERIAL_THRESHOLD, FIANCHETTO_BISHOP_BONUS, ISOLATED_PAWN_PENALTY,
@@ -19,11 +20,14 @@ use crate::engine_constants::{
     VALUE_KING_ENDGAME_CENTRALIZATION, VALUE_KING_MOBILITY, VALUE_KING_SUPPORTS_PASSED_PAWN, VALUE_KNIGHT_OUTPOST, VALUE_PASSED_PAWN_BONUS,
     VALUE_QUEENSIDE_PAWN_MAJORITY, VALUE_QUEEN_MOBILITY, VALUE_ROOKS_ON_SAME_FILE, VALUE_ROOK_BEHIND_PASSED_PAWN,
 };
-use crate::hash::pawn_zobrist_key;
+use crate::kpk::probe_kpk;
 use crate::magic_bitboards::{magic_moves_bishop, magic_moves_rook};
-use crate::material_imbalance::material_imbalance_score;
+use crate::material_imbalance::{knight_placement_score, material_imbalance_count_score, material_imbalance_score, queen_vs_knight_side};
 use crate::piece_square_tables::piece_square_values;
-use crate::types::{default_evaluate_cache, Bitboard, EvaluateCache, Mover, PawnHashTable, Pieces, Position, Score, Square, BLACK, WHITE};
+use crate::types::{
+    default_evaluate_cache, Bitboard, EvaluateCache, MaterialHashEntry, MaterialHashTable, Mover, PawnHashTable, Pieces, Position, Score,
+    Square, BLACK, WHITE,
+};
 use crate::utils::linear_scale;
 use crate::{get_and_unset_lsb, opponent};
 use std::cmp::{max, min};
@@ -46,6 +50,12 @@ pub fn evaluate(position: &Position) -> Score {
         return 0;
     }
 
+    let scale_factor = match find_endgame(position.material_key).map(|(endgame, strong)| endgame.evaluate(position, strong)) {
+        Some(EndgameEvaluation::Exact(score)) => return 10 + score,
+        Some(EndgameEvaluation::Scaled(scale_factor)) => scale_factor,
+        None => SCALE_FACTOR_NORMAL,
+    };
+
     let score = material_score(position)
         + piece_square_values(position)
         + king_score(position, &cache)
@@ -82,13 +92,15 @@ pub fn evaluate(position: &Position) -> Score {
         + rook_7th_king_8th_bonus(position)
         + knight_on_rim_penalty(position)
         + fianchetto_bishop_bonus(position);
+    let score = score * scale_factor / SCALE_FACTOR_NORMAL;
 
     10 + if position.mover == WHITE { score } else { -score }
 }
 
-/// Evaluate with pawn hash table support for caching pawn structure evaluation
+/// Evaluate with pawn hash table support for caching pawn structure evaluation, and the material
+/// hash table for the terms that depend only on the piece counts
 #[inline(always)]
-pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable) -> Score {
+pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable, material_hash: &MaterialHashTable) -> Score {
     let mut cache = default_evaluate_cache();
 
     cache_piece_count(position, &mut cache);
@@ -105,8 +117,15 @@ pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable) -
         return 0;
     }
 
+    let material = material_hash_entry(position, material_hash);
+    let scale_factor = match material.endgame.map(|(endgame, strong)| endgame.evaluate(position, strong)) {
+        Some(EndgameEvaluation::Exact(score)) => return 10 + score,
+        Some(EndgameEvaluation::Scaled(scale_factor)) => scale_factor,
+        None => SCALE_FACTOR_NORMAL,
+    };
+
     // Try to get pawn structure score from hash table
-    let pawn_key = pawn_zobrist_key(position);
+    let pawn_key = position.pawn_zobrist_key;
     let pawn_structure = match pawn_hash.get(pawn_key) {
         Some(score) => score,
         None => {
@@ -118,7 +137,7 @@ pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable) -
     };
 
     let score =
-        material_score(position)
+        material_score_at_phase(position, material.phase)
         + piece_square_values(position)
         + king_score(position, &cache)
         + king_threat_score(position)
@@ -140,9 +159,10 @@ pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable) -
             + endgame_king_centralization_bonus(position)
             + king_activity_score(position)
             + king_mobility_score(position)
-            + trade_bonus(position)
-            + bishop_knight_imbalance_score(position)
-            + material_imbalance_score(position)
+            + material.imbalance
+            + material
+                .queen_vs_knight
+                .map_or(0, |knight_side| knight_placement_score(position, knight_side))
             + trapped_piece_penalty(position)
             + space_score(position)
             + queenside_pawn_majority_score(position)
@@ -151,6 +171,7 @@ pub fn evaluate_with_pawn_hash(position: &Position, pawn_hash: &PawnHashTable) -
             + rook_7th_king_8th_bonus(position)
             + knight_on_rim_penalty(position)
             + fianchetto_bishop_bonus(position);
+    let score = score * scale_factor / SCALE_FACTOR_NORMAL;
 
     10 + if position.mover == WHITE { score } else { -score }
 }
@@ -162,6 +183,23 @@ fn pawn_structure_score(position: &Position, cache: &mut EvaluateCache) -> Score
     doubled_and_isolated_pawn_score(position, cache) + backward_pawn_score(position)
 }
 
+/// The terms that depend only on the piece counts, from the material hash table or computed and stored there
+#[inline(always)]
+pub fn material_hash_entry(position: &Position, material_hash: &MaterialHashTable) -> MaterialHashEntry {
+    if let Some(entry) = material_hash.get(position.material_key) {
+        return entry;
+    }
+    let entry = MaterialHashEntry {
+        key: position.material_key,
+        imbalance: trade_bonus(position) + bishop_knight_imbalance_score(position) + material_imbalance_count_score(position),
+        phase: game_phase(position),
+        endgame: find_endgame(position.material_key),
+        queen_vs_knight: queen_vs_knight_side(position),
+    };
+    material_hash.set(entry);
+    entry
+}
+
 #[inline(always)]
 pub fn insufficient_material(position: &Position, piece_count: u8, include_helpmates: bool) -> bool {
     if piece_count > 4 {
@@ -181,11 +219,13 @@ pub fn insufficient_material(position: &Position, piece_count: u8, include_helpm
         return false;
     }
 
-    let knight_count = (w.knight_bitboard | b.knight_bitboard).count_ones();
-    let minor_count = (w.bishop_bitboard | b.bishop_bitboard).count_ones();
-
     if include_helpmates {
-        return minor_count <= 2 || (minor_count == 3 && knight_count == 0);
+        // Bishop and knight, or bishops on both colours, can force mate; anything else needs help
+        let can_force_mate = |pieces: &Pieces| {
+            (pieces.bishop_bitboard != 0 && pieces.knight_bitboard != 0)
+                || (pieces.bishop_bitboard & DARK_SQUARES_BITS != 0 && pieces.bishop_bitboard & LIGHT_SQUARES_BITS != 0)
+        };
+        return !can_force_mate(&w) && !can_force_mate(&b);
     }
 
     if (w.bishop_bitboard | w.knight_bitboard | b.bishop_bitboard | b.knight_bitboard).count_ones() == 1 {
@@ -283,12 +323,7 @@ fn is_wrong_color_for_pawn(bishop_bb: Bitboard, pawn_bb: Bitboard, is_white: boo
     }
 }
 
-/// Detects drawn KP vs K positions where the defending king has opposition
-/// or can block the pawn's promotion.
-///
-/// Key concepts:
-/// - Rook pawns (a/h file): drawn if defending king can reach the corner
-/// - Other pawns: drawn if defending king has opposition and is in front of the pawn
+/// Detects drawn KP vs K positions with the KPK bitbase, which is exact
 #[inline(always)]
 pub fn is_kpk_draw(position: &Position, piece_count: u8) -> bool {
     // Must be exactly 3 pieces: K + K + P
@@ -300,201 +335,23 @@ pub fn is_kpk_draw(position: &Position, piece_count: u8) -> bool {
     let b = position.pieces[BLACK as usize];
 
     // Determine which side has the pawn
-    let (pawn_bb, attacking_king_sq, defending_king_sq, pawn_is_white) = if w.pawn_bitboard.count_ones() == 1 && b.pawn_bitboard == 0 {
-        // White has K+P, Black has K
-        (w.pawn_bitboard, w.king_square, b.king_square, true)
-    } else if b.pawn_bitboard.count_ones() == 1 && w.pawn_bitboard == 0 {
-        // Black has K+P, White has K
-        (b.pawn_bitboard, b.king_square, w.king_square, false)
+    let (strong, pawn_bb, strong_king_sq, weak_king_sq) = if w.pawn_bitboard != 0 {
+        (WHITE, w.pawn_bitboard, w.king_square, b.king_square)
+    } else if b.pawn_bitboard != 0 {
+        (BLACK, b.pawn_bitboard, b.king_square, w.king_square)
     } else {
         return false;
     };
 
-    // Ensure no other pieces
-    if pawn_is_white {
-        if w.knight_bitboard != 0
-            || w.bishop_bitboard != 0
-            || w.rook_bitboard != 0
-            || w.queen_bitboard != 0
-            || b.knight_bitboard != 0
-            || b.bishop_bitboard != 0
-            || b.rook_bitboard != 0
-            || b.queen_bitboard != 0
-        {
-            return false;
-        }
-    } else if b.knight_bitboard != 0
-        || b.bishop_bitboard != 0
-        || b.rook_bitboard != 0
-        || b.queen_bitboard != 0
-        || w.knight_bitboard != 0
-        || w.bishop_bitboard != 0
-        || w.rook_bitboard != 0
-        || w.queen_bitboard != 0
-    {
-        return false;
-    }
-
-    let pawn_sq = pawn_bb.trailing_zeros() as Square;
-    let pawn_file = 7 - (pawn_sq % 8); // 0=a, 7=h
-    let pawn_rank = pawn_sq / 8; // 0=rank1, 7=rank8
-
-    let def_file = 7 - (defending_king_sq % 8);
-    let def_rank = defending_king_sq / 8;
-
-    let atk_file = 7 - (attacking_king_sq % 8);
-    let atk_rank = attacking_king_sq / 8;
-
-    // Handle rook pawns (a-file or h-file) specially
-    if pawn_file == 0 || pawn_file == 7 {
-        return is_rook_pawn_draw(pawn_file, pawn_rank, def_file, def_rank, atk_rank, pawn_is_white, position.mover);
-    }
-
-    // For non-rook pawns, check if defending king has opposition and is blocking
-    is_opposition_draw(
-        pawn_file,
-        pawn_rank,
-        def_file,
-        def_rank,
-        atk_file,
-        atk_rank,
-        pawn_is_white,
+    !probe_kpk(
+        strong,
+        strong_king_sq,
+        weak_king_sq,
+        pawn_bb.trailing_zeros() as Square,
         position.mover,
     )
 }
 
-/// Check if a rook pawn position is drawn.
-/// Rook pawns are drawn if the defending king can reach the corner.
-#[inline(always)]
-fn is_rook_pawn_draw(
-    pawn_file: Square,
-    pawn_rank: Square,
-    def_file: Square,
-    def_rank: Square,
-    atk_rank: Square,
-    pawn_is_white: bool,
-    mover: Mover,
-) -> bool {
-    // Promotion square corner: a8 for a-pawn (white) or a1 for a-pawn (black)
-    // h8 for h-pawn (white) or h1 for h-pawn (black)
-    let promo_rank = if pawn_is_white { 7 } else { 0 };
-
-    // If defending king is on the promotion file (same as pawn) and ahead of/at promotion rank
-    // or can reach the corner, it's drawn
-    if def_file == pawn_file {
-        if pawn_is_white {
-            // Defending king on promotion file and at or ahead of pawn
-            if def_rank >= pawn_rank {
-                return true;
-            }
-        } else {
-            // Black pawn: defending king on file and at or below pawn
-            if def_rank <= pawn_rank {
-                return true;
-            }
-        }
-    }
-
-    // If defending king is in the corner or can reach it before pawn promotes
-    // Simple heuristic: if defending king is within reach of corner
-    let corner_rank = promo_rank;
-    let corner_file = pawn_file;
-
-    let def_dist_to_corner = max((def_file - corner_file).abs(), (def_rank - corner_rank).abs());
-
-    // Distance for pawn to promote
-    let pawn_dist_to_promo = if pawn_is_white { 7 - pawn_rank } else { pawn_rank };
-
-    // Account for who moves - defender gets -1 if they move first
-    let def_moves = if (pawn_is_white && mover == BLACK) || (!pawn_is_white && mover == WHITE) {
-        def_dist_to_corner.saturating_sub(1)
-    } else {
-        def_dist_to_corner
-    };
-
-    // If defender can reach corner in time, it's drawn
-    if def_moves <= pawn_dist_to_promo {
-        // Also check that attacking king isn't cutting off the path
-        // Simple: if attacking king is not between defender and corner, likely draw
-        if pawn_is_white {
-            if atk_rank <= def_rank || atk_rank < pawn_rank {
-                return true;
-            }
-        } else if atk_rank >= def_rank || atk_rank > pawn_rank {
-            return true;
-        }
-    }
-
-    false
-}
-
-/// Check if a non-rook pawn position is drawn due to opposition.
-/// The defending king has opposition if it's on the same file as the pawn,
-/// in front of the pawn, and the attacker cannot outflank.
-#[inline(always)]
-#[allow(clippy::too_many_arguments)]
-fn is_opposition_draw(
-    pawn_file: Square,
-    pawn_rank: Square,
-    def_file: Square,
-    def_rank: Square,
-    atk_file: Square,
-    atk_rank: Square,
-    pawn_is_white: bool,
-    mover: Mover,
-) -> bool {
-    // Defending king must be on the pawn's file or adjacent files
-    let file_diff = (def_file - pawn_file).abs();
-    if file_diff > 1 {
-        return false;
-    }
-
-    // Defending king must be in front of the pawn
-    if pawn_is_white {
-        if def_rank <= pawn_rank {
-            return false;
-        }
-    } else if def_rank >= pawn_rank {
-        return false;
-    }
-
-    // Check for direct opposition: kings on same file, odd number of ranks apart
-    if def_file == atk_file {
-        let rank_diff = (def_rank - atk_rank).abs();
-        if rank_diff % 2 == 0 {
-            // Even distance - side to move has opposition
-            // If it's the attacker's move and they have opposition, not drawn
-            // If it's the defender's move and they have opposition, drawn
-            let attacker_to_move = (pawn_is_white && mover == WHITE) || (!pawn_is_white && mover == BLACK);
-            if !attacker_to_move {
-                // Defender to move with even distance = defender has opposition = NOT drawn
-                // (defender loses opposition by moving)
-                return false;
-            }
-            // Attacker to move with even distance = drawn (defender will get opposition)
-            return def_file == pawn_file && rank_diff == 2;
-        } else {
-            // Odd distance - side NOT to move has opposition
-            let attacker_to_move = (pawn_is_white && mover == WHITE) || (!pawn_is_white && mover == BLACK);
-            if attacker_to_move {
-                // Attacker to move, defender has opposition
-                // If defending king is directly in front of pawn, likely drawn
-                if def_file == pawn_file {
-                    // Check if attacking king is far enough that it can't outflank
-                    let atk_file_diff = (atk_file - pawn_file).abs();
-                    if atk_file_diff <= 1 {
-                        // Attacking king is close but defender has opposition
-                        // This is the classic drawn position
-                        return true;
-                    }
-                }
-            }
-        }
-    }
-
-    false
-}
-
 fn cache_piece_count(position: &Position, cache: &mut EvaluateCache) {
     if cache.piece_count == 0 {
         cache.piece_count = (position.pieces[WHITE as usize].all_pieces_bitboard.count_ones()
@@ -765,11 +622,20 @@ pub fn black_king_early_safety(position: &Position) -> Score {
     score
 }
 
+/// The material of both sides without their kings, from the starting material down to zero
+#[inline(always)]
+pub fn game_phase(position: &Position) -> Score {
+    pawn_material(position, WHITE) + pawn_material(position, BLACK) + piece_material(position, WHITE) + piece_material(position, BLACK)
+}
+
 #[inline(always)]
 pub fn material_score(position: &Position) -> Score {
-    let game_stage =
-        pawn_material(position, WHITE) + pawn_material(position, BLACK) + piece_material(position, WHITE) + piece_material(position, BLACK);
+    material_score_at_phase(position, game_phase(position))
+}
 
+/// Piece values interpolated between middlegame and endgame by `game_stage`, which is `game_phase`
+#[inline(always)]
+pub fn material_score_at_phase(position: &Position, game_stage: Score) -> Score {
     let pawn_balance = position.pieces[WHITE as usize].pawn_bitboard.count_ones() as Score
         - position.pieces[BLACK as usize].pawn_bitboard.count_ones() as Score;
     let pawn_score = pawn_balance
@@ -1,12 +1,16 @@
-use crate::bitboards::bit;
-use crate::hash::zobrist_lock;
+use crate::bitboards::{bit, A1_BIT, A8_BIT, E1_BIT, E8_BIT, H1_BIT, H8_BIT, RANK_1_BITS, RANK_3_BITS, RANK_6_BITS, RANK_8_BITS};
+use crate::endgame::material_key;
+use crate::hash::{pawn_zobrist_key, zobrist_lock};
 use crate::move_constants::{
     BK_CASTLE, BQ_CASTLE, PROMOTION_BISHOP_MOVE_MASK, PROMOTION_FULL_MOVE_MASK, PROMOTION_KNIGHT_MOVE_MASK, PROMOTION_QUEEN_MOVE_MASK,
     PROMOTION_ROOK_MOVE_MASK, WK_CASTLE, WQ_CASTLE,
 };
+use crate::moves::is_check;
+use crate::opponent;
 use crate::types::{Bitboard, Move, Mover, Pieces, Position, Square, BLACK, WHITE};
 use crate::utils::from_square_mask;
 use std::collections::HashMap;
+use std::fmt;
 
 const EN_PASSANT_UNAVAILABLE: i8 = -1;
 
@@ -216,14 +220,225 @@ pub fn get_position(fen: &str) -> Position {
         half_moves: fen_part(fen, 4).parse::<u16>().unwrap(),
         move_number: fen_part(fen, 5).parse::<u16>().unwrap(),
         zobrist_lock: 0,
+        pawn_zobrist_key: 0,
+        material_key: 0,
     };
 
     let lock = zobrist_lock(&position);
 
     position.zobrist_lock = lock;
+    position.pawn_zobrist_key = pawn_zobrist_key(&position);
+    position.material_key = material_key(&position);
     position
 }
 
+/// Reasons a FEN string can be rejected by `try_get_position`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    BadRank(String),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfMoveClock(String),
+    InvalidMoveNumber(String),
+    WrongKingCount(Mover, u32),
+    TooManyPieces(Mover),
+    TooManyPawns(Mover),
+    PawnOnBackRank,
+    ImpossibleEnPassant(String),
+    SideNotToMoveInCheck,
+}
+
+fn colour_name(colour: Mover) -> &'static str {
+    if colour == WHITE {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(n) => write!(f, "expected 4 or 6 fields, found {}", n),
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::BadRank(r) => write!(f, "rank '{}' does not describe 8 squares", r),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::InvalidSideToMove(s) => write!(f, "invalid side to move '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling field '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant field '{}'", s),
+            FenError::InvalidHalfMoveClock(s) => write!(f, "invalid half move clock '{}'", s),
+            FenError::InvalidMoveNumber(s) => write!(f, "invalid move number '{}'", s),
+            FenError::WrongKingCount(c, n) => write!(f, "{} has {} kings", colour_name(*c), n),
+            FenError::TooManyPieces(c) => write!(f, "{} has more than 16 pieces", colour_name(*c)),
+            FenError::TooManyPawns(c) => write!(f, "{} has more than 8 pawns", colour_name(*c)),
+            FenError::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            FenError::ImpossibleEnPassant(s) => write!(f, "en passant square {} is not possible", s),
+            FenError::SideNotToMoveInCheck => write!(f, "side not to move is in check"),
+        }
+    }
+}
+
+fn validate_board_part(board: &str) -> Result<(), FenError> {
+    let ranks: Vec<&str> = board.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+    for rank in ranks {
+        let mut squares = 0;
+        let mut previous_was_digit = false;
+        for c in rank.chars() {
+            if ('1'..='8').contains(&c) {
+                if previous_was_digit {
+                    return Err(FenError::BadRank(rank.to_string()));
+                }
+                squares += char_as_num(c);
+                previous_was_digit = true;
+            } else if "PNBRQKpnbrqk".contains(c) {
+                squares += 1;
+                previous_was_digit = false;
+            } else {
+                return Err(FenError::InvalidPiece(c));
+            }
+        }
+        if squares != 8 {
+            return Err(FenError::BadRank(rank.to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn is_algebraic_square(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 2 && (b'a'..=b'h').contains(&b[0]) && (b'1'..=b'8').contains(&b[1])
+}
+
+fn validate_fields(fields: &[&str]) -> Result<(), FenError> {
+    validate_board_part(fields[0])?;
+
+    if fields[1] != "w" && fields[1] != "b" {
+        return Err(FenError::InvalidSideToMove(fields[1].to_string()));
+    }
+
+    let castling = fields[2];
+    let valid_castling = castling == "-"
+        || (!castling.is_empty()
+            && castling.chars().all(|c| "KQkq".contains(c))
+            && castling.chars().enumerate().all(|(i, c)| !castling[i + 1..].contains(c)));
+    if !valid_castling {
+        return Err(FenError::InvalidCastling(castling.to_string()));
+    }
+
+    if fields[3] != "-" && !is_algebraic_square(fields[3]) {
+        return Err(FenError::InvalidEnPassant(fields[3].to_string()));
+    }
+
+    if fields[4].parse::<u16>().is_err() {
+        return Err(FenError::InvalidHalfMoveClock(fields[4].to_string()));
+    }
+    if fields[5].parse::<u16>().is_err() {
+        return Err(FenError::InvalidMoveNumber(fields[5].to_string()));
+    }
+
+    Ok(())
+}
+
+/// Exactly one king per side is checked before building the position, because the king
+/// square is taken from the king bitboard and is meaningless otherwise
+fn validate_king_counts(board: &str) -> Result<(), FenError> {
+    for (colour, king_char) in [(WHITE, 'K'), (BLACK, 'k')] {
+        let kings = board.chars().filter(|c| *c == king_char).count() as u32;
+        if kings != 1 {
+            return Err(FenError::WrongKingCount(colour, kings));
+        }
+    }
+    Ok(())
+}
+
+fn validate_position(position: &Position) -> Result<(), FenError> {
+    for colour in [WHITE, BLACK] {
+        let pieces = &position.pieces[colour as usize];
+        if pieces.all_pieces_bitboard.count_ones() > 16 {
+            return Err(FenError::TooManyPieces(colour));
+        }
+        if pieces.pawn_bitboard.count_ones() > 8 {
+            return Err(FenError::TooManyPawns(colour));
+        }
+    }
+
+    let pawns = position.pieces[WHITE as usize].pawn_bitboard | position.pieces[BLACK as usize].pawn_bitboard;
+    if pawns & (RANK_1_BITS | RANK_8_BITS) != 0 {
+        return Err(FenError::PawnOnBackRank);
+    }
+
+    if position.en_passant_square != EN_PASSANT_UNAVAILABLE {
+        let ep = position.en_passant_square;
+        // The pawn that has just moved two squares sits in front of the en passant square, and
+        // both the en passant square and the square the pawn came from must be empty
+        let (expected_rank_bits, pawn_square, origin_square) = if position.mover == WHITE {
+            (RANK_6_BITS, ep - 8, ep + 8)
+        } else {
+            (RANK_3_BITS, ep + 8, ep - 8)
+        };
+        let all_pieces = position.pieces[WHITE as usize].all_pieces_bitboard | position.pieces[BLACK as usize].all_pieces_bitboard;
+        let possible = bit(ep) & expected_rank_bits != 0
+            && position.pieces[opponent!(position.mover) as usize].pawn_bitboard & bit(pawn_square) != 0
+            && all_pieces & (bit(ep) | bit(origin_square)) == 0;
+        if !possible {
+            return Err(FenError::ImpossibleEnPassant(algebraic_squareref_from_bitref(ep)));
+        }
+    }
+
+    if is_check(position, opponent!(position.mover)) {
+        return Err(FenError::SideNotToMoveInCheck);
+    }
+
+    Ok(())
+}
+
+/// Castling rights are dropped, rather than rejected, when the king or rook is not on its
+/// original square. Test suites in the wild often carry stale castling fields.
+fn remove_unavailable_castle_rights(position: &mut Position) {
+    let castle_requirements = [
+        (WK_CASTLE, WHITE, E1_BIT, H1_BIT),
+        (WQ_CASTLE, WHITE, E1_BIT, A1_BIT),
+        (BK_CASTLE, BLACK, E8_BIT, H8_BIT),
+        (BQ_CASTLE, BLACK, E8_BIT, A8_BIT),
+    ];
+    let original_flags = position.castle_flags;
+    for (flag, colour, king_square, rook_square) in castle_requirements {
+        let pieces = &position.pieces[colour as usize];
+        if pieces.king_square != king_square || pieces.rook_bitboard & bit(rook_square) == 0 {
+            position.castle_flags &= !flag;
+        }
+    }
+    if position.castle_flags != original_flags {
+        position.zobrist_lock = zobrist_lock(position);
+    }
+}
+
+/// Parse and validate a FEN. Unlike `get_position`, which trusts its input, this checks the
+/// syntax of every field and that the position could arise in a game. Four-field FENs, as found
+/// in EPD files, are accepted with a half move clock of 0 and a move number of 1.
+pub fn try_get_position(fen: &str) -> Result<Position, FenError> {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    match fields.len() {
+        4 => fields.extend(["0", "1"]),
+        6 => {}
+        n => return Err(FenError::WrongFieldCount(n)),
+    }
+    validate_fields(&fields)?;
+    validate_king_counts(fields[0])?;
+
+    let mut position = get_position(&fields.join(" "));
+    remove_unavailable_castle_rights(&mut position);
+    validate_position(&position)?;
+    Ok(position)
+}
+
 pub fn get_piece_on_square(position: &Position, sq: Square) -> char {
     let bb = bit(sq);
 
@@ -0,0 +1,158 @@
+//! An exact win/draw bitbase for king and pawn against king
+//!
+//! The bitbase is built by retrograde analysis the first time it is needed, or at startup with
+//! `init_kpk`. Positions are seen from the side with the pawn, as if it were white, and mirrored so
+//! the pawn is on files a to d. That leaves 24 pawn squares, 64 squares for each king and two sides
+//! to move, one bit each for 24 KB in all.
+//!
+//! Every position starts as invalid, a draw, a win or unknown. A win is a pawn on the seventh that
+//! promotes safely; a draw is a stalemate or a pawn the weak king takes for free. Unknown positions
+//! are then resolved from their successors until nothing changes: the strong side wins if any move
+//! wins, the weak side draws if any move draws. Whatever is still unknown at the end is a draw.
+
+use std::sync::OnceLock;
+
+use crate::bitboards::{bit, KING_MOVES_BITBOARDS, WHITE_PAWN_MOVES_CAPTURE};
+use crate::get_and_unset_lsb;
+use crate::types::{Bitboard, Mover, Square, WHITE};
+
+pub const KPK_POSITIONS: usize = 2 * 24 * 64 * 64;
+
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+static KPK_BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+/// Build the bitbase now, rather than in the middle of the first search that reaches KPK
+pub fn init_kpk() {
+    kpk_bitbase();
+}
+
+fn kpk_bitbase() -> &'static [u64] {
+    KPK_BITBASE.get_or_init(generate_kpk_bitbase)
+}
+
+/// The index of a position with the pawn on files a to d and ranks 2 to 7
+fn kpk_index(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> usize {
+    let pawn_file = 7 - pawn % 8;
+    let pawn_rank = pawn / 8;
+    let pawn_index = ((pawn_rank - 1) * 4 + pawn_file) as usize;
+    usize::from(!strong_to_move) | (weak_king as usize) << 1 | (strong_king as usize) << 7 | pawn_index << 13
+}
+
+fn kpk_squares(index: usize) -> (bool, Square, Square, Square) {
+    let pawn_index = (index >> 13) as Square;
+    let pawn = (pawn_index / 4 + 1) * 8 + 7 - pawn_index % 4;
+    (index & 1 == 0, ((index >> 7) & 63) as Square, ((index >> 1) & 63) as Square, pawn)
+}
+
+#[inline(always)]
+fn king_moves(square: Square) -> Bitboard {
+    KING_MOVES_BITBOARDS[square as usize]
+}
+
+fn initial_classification(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> u8 {
+    let pawn_attacks = WHITE_PAWN_MOVES_CAPTURE[pawn as usize];
+    if strong_king == weak_king || king_moves(strong_king) & bit(weak_king) != 0 || pawn == strong_king || pawn == weak_king {
+        return INVALID;
+    }
+    if strong_to_move {
+        let queening = pawn + 8;
+        if pawn_attacks & bit(weak_king) != 0 {
+            return INVALID;
+        }
+        if pawn / 8 == 6
+            && strong_king != queening
+            && weak_king != queening
+            && (king_moves(weak_king) & bit(queening) == 0 || king_moves(strong_king) & bit(queening) != 0)
+        {
+            return WIN;
+        }
+        return UNKNOWN;
+    }
+
+    let escapes = king_moves(weak_king) & !(king_moves(strong_king) | pawn_attacks);
+    if escapes == 0 {
+        return if pawn_attacks & bit(weak_king) != 0 { WIN } else { DRAW };
+    }
+    if escapes & bit(pawn) != 0 {
+        return DRAW;
+    }
+    UNKNOWN
+}
+
+fn classify_from_successors(db: &[u8], strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> u8 {
+    let mut results = INVALID;
+    if strong_to_move {
+        let mut moves = king_moves(strong_king);
+        while moves != 0 {
+            results |= db[kpk_index(false, get_and_unset_lsb!(moves), weak_king, pawn)];
+        }
+        let push = pawn + 8;
+        if pawn / 8 < 6 {
+            results |= db[kpk_index(false, strong_king, weak_king, push)];
+        }
+        if pawn / 8 == 1 && push != strong_king && push != weak_king {
+            results |= db[kpk_index(false, strong_king, weak_king, push + 8)];
+        }
+    } else {
+        let mut moves = king_moves(weak_king);
+        while moves != 0 {
+            results |= db[kpk_index(true, strong_king, get_and_unset_lsb!(moves), pawn)];
+        }
+    }
+
+    let (good, bad) = if strong_to_move { (WIN, DRAW) } else { (DRAW, WIN) };
+    if results & good != 0 {
+        good
+    } else if results & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+fn generate_kpk_bitbase() -> Vec<u64> {
+    let mut db: Vec<u8> = (0..KPK_POSITIONS)
+        .map(|index| {
+            let (strong_to_move, strong_king, weak_king, pawn) = kpk_squares(index);
+            initial_classification(strong_to_move, strong_king, weak_king, pawn)
+        })
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..KPK_POSITIONS {
+            if db[index] == UNKNOWN {
+                let (strong_to_move, strong_king, weak_king, pawn) = kpk_squares(index);
+                db[index] = classify_from_successors(&db, strong_to_move, strong_king, weak_king, pawn);
+                changed |= db[index] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bits = vec![0; KPK_POSITIONS / 64];
+    for (index, result) in db.iter().enumerate() {
+        if *result == WIN {
+            bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+    bits
+}
+
+/// True if the side with the pawn wins. `strong` is the colour of the side with the pawn.
+pub fn probe_kpk(strong: Mover, strong_king: Square, weak_king: Square, pawn: Square, mover: Mover) -> bool {
+    // Seen from white, then mirrored onto files a to d, which are the top half of each rank since h1 is square 0
+    let flip = if strong == WHITE { 0 } else { 56 };
+    let mirror = if (pawn ^ flip) % 8 < 4 { 7 } else { 0 };
+    let index = kpk_index(
+        mover == strong,
+        strong_king ^ flip ^ mirror,
+        weak_king ^ flip ^ mirror,
+        pawn ^ flip ^ mirror,
+    );
+    kpk_bitbase()[index / 64] & (1 << (index % 64)) != 0
+}
@@ -1,24 +1,42 @@
+pub mod annotate;
 pub mod bitboards;
+pub mod datagen;
+pub mod endgame;
 pub mod engine_constants;
 pub mod evaluate;
 pub mod fen;
 pub mod hash;
+pub mod kpk;
 pub mod magic_bitboards;
 pub mod magic_moves_bishop;
 pub mod magic_moves_rook;
 pub mod make_move;
+#[cfg(feature = "move-validation")]
+pub mod make_move_validation;
 pub mod material_imbalance;
 pub mod move_constants;
+pub mod move_picker;
 pub mod move_scores;
 pub mod moves;
 pub mod mvm_test_fens;
 pub mod perft;
+pub mod perft_verify;
+pub mod pgn;
 pub mod piece_square_tables;
+pub mod puzzles;
 pub mod quiesce;
 pub mod search;
+pub mod search_features;
+pub mod search_limits;
+#[cfg(feature = "search-trace")]
+pub mod search_trace;
 pub mod see;
+pub mod strength;
 pub mod tablebase;
+pub mod time_management;
 pub mod types;
 pub mod uci;
 pub mod uci_bench;
+pub mod uci_command;
 pub mod utils;
+pub mod wdl;
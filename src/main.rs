@@ -1,10 +1,12 @@
 use either::{Left, Right};
+use rusty_rival::kpk::init_kpk;
 use rusty_rival::types::{default_search_state, default_uci_state, SearchHandle, SearchState, UciState};
 use rusty_rival::uci::run_command;
 use rustyline::error::ReadlineError;
 use rustyline::{DefaultEditor, Result};
 
 fn main() {
+    init_kpk();
     repl().unwrap();
 }
 
@@ -41,7 +43,7 @@ fn handle_cmd_line(uci_state: &mut UciState, search_state: &mut SearchState, sea
             }
         }
         Left(error) => {
-            println!("Error: {}", error);
+            println!("info string {}", error);
         }
     }
 }
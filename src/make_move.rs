@@ -1,12 +1,15 @@
 use crate::bitboards::{bit, test_bit, A1_BIT, A8_BIT, H1_BIT, H8_BIT};
+use crate::endgame::material_key_unit;
 use crate::hash::{
     en_passant_zobrist_key_index, ZOBRIST_KEYS_CASTLE, ZOBRIST_KEYS_EN_PASSANT, ZOBRIST_KEYS_PIECES, ZOBRIST_KEY_MOVER_SWITCH,
     ZOBRIST_PIECE_INDEX_BISHOP, ZOBRIST_PIECE_INDEX_KING, ZOBRIST_PIECE_INDEX_KNIGHT, ZOBRIST_PIECE_INDEX_PAWN, ZOBRIST_PIECE_INDEX_QUEEN,
     ZOBRIST_PIECE_INDEX_ROOK,
 };
+#[cfg(feature = "move-validation")]
+use crate::make_move_validation::{is_move_validation_enabled, validate_make, validate_unmake};
 use crate::move_constants::*;
 use crate::opponent;
-use crate::types::{Bitboard, Move, Position, Square, UnmakeInfo, BLACK, WHITE};
+use crate::types::{Bitboard, MaterialKey, Move, Mover, Position, Square, UnmakeInfo, BLACK, WHITE};
 use crate::utils::{from_square_part, to_square_part};
 
 // Captured piece encoding for UnmakeInfo
@@ -18,6 +21,17 @@ pub const CAPTURED_ROOK: u8 = 4;
 pub const CAPTURED_QUEEN: u8 = 5;
 pub const CAPTURED_EP_PAWN: u8 = 6; // En passant capture (pawn on different square)
 
+/// What a captured piece of this colour took away from the material key
+#[inline(always)]
+fn captured_material_key(colour: usize, captured: u8) -> MaterialKey {
+    match captured {
+        CAPTURED_NONE => 0,
+        CAPTURED_EP_PAWN => material_key_unit(colour as Mover, 0),
+        // Pawn to queen are in the order of the material key
+        _ => material_key_unit(colour as Mover, captured as usize - 1),
+    }
+}
+
 #[inline(always)]
 pub fn make_move(position: &Position, mv: Move, new_position: &mut Position) {
     let from = from_square_part(mv);
@@ -105,8 +119,10 @@ pub fn perform_castle(position: &mut Position, index: usize) {
 fn make_simple_pawn_move(position: &mut Position, from: Square, to: Square) {
     let switch = bit(from) | bit(to);
     position.pieces[position.mover as usize].pawn_bitboard ^= switch;
-    position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize]
+    let pawn_keys = ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize]
         ^ ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
+    position.zobrist_lock ^= pawn_keys;
+    position.pawn_zobrist_key ^= pawn_keys;
 
     position.pieces[position.mover as usize].all_pieces_bitboard ^= switch;
 
@@ -159,31 +175,39 @@ pub fn make_move_with_promotion(position: &mut Position, from: Square, to: Squar
     let bit_to = bit(to);
     let bit_from = bit(from);
 
-    match promotion_mask {
+    let promoted_index = match promotion_mask {
         PROMOTION_KNIGHT_MOVE_MASK => {
             position.pieces[position.mover as usize].knight_bitboard |= bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_KNIGHT][to as usize];
+            1
         }
         PROMOTION_BISHOP_MOVE_MASK => {
             position.pieces[position.mover as usize].bishop_bitboard |= bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_BISHOP][to as usize];
+            2
         }
         PROMOTION_ROOK_MOVE_MASK => {
             position.pieces[position.mover as usize].rook_bitboard |= bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_ROOK][to as usize];
+            3
         }
         PROMOTION_QUEEN_MOVE_MASK => {
             position.pieces[position.mover as usize].queen_bitboard |= bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_QUEEN][to as usize];
+            4
         }
         _ => panic!("Invalid promotion piece"),
-    }
+    };
+    // The promoted piece comes after the pawn in the material key, so this never underflows
+    position.material_key += material_key_unit(position.mover, promoted_index) - material_key_unit(position.mover, 0);
 
     position.pieces[position.mover as usize].pawn_bitboard ^= bit_from;
 
     let opponent = opponent!(position.mover) as usize;
 
-    position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize];
+    let pawn_key = ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize];
+    position.zobrist_lock ^= pawn_key;
+    position.pawn_zobrist_key ^= pawn_key;
 
     position.pieces[position.mover as usize].all_pieces_bitboard ^= bit_from | bit_to;
 
@@ -214,6 +238,7 @@ pub fn make_move_with_promotion(position: &mut Position, from: Square, to: Squar
     } else {
         CAPTURED_NONE
     };
+    position.material_key -= captured_material_key(opponent, captured);
 
     update_castle_flags_if_square(position, to);
 
@@ -289,6 +314,7 @@ pub fn make_pawn_capture_move(position: &mut Position, from: Square, to: Square)
 
         let epcps = en_passant_captured_piece_square(position.en_passant_square) as usize;
         position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][epcps];
+        position.pawn_zobrist_key ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][epcps];
 
         enemy.pawn_bitboard &= pawn_off;
         enemy.all_pieces_bitboard &= pawn_off;
@@ -297,6 +323,7 @@ pub fn make_pawn_capture_move(position: &mut Position, from: Square, to: Square)
         let cap = if enemy.pawn_bitboard & bit_to != 0 {
             enemy.pawn_bitboard &= !bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
+            position.pawn_zobrist_key ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
             CAPTURED_PAWN
         } else if enemy.knight_bitboard & bit_to != 0 {
             enemy.knight_bitboard &= !bit_to;
@@ -320,13 +347,16 @@ pub fn make_pawn_capture_move(position: &mut Position, from: Square, to: Square)
         enemy.all_pieces_bitboard &= !bit_to;
         cap
     };
+    position.material_key -= captured_material_key(opponent, captured);
 
     let switch = bit(from) | bit_to;
     position.pieces[position.mover as usize].all_pieces_bitboard ^= switch;
     position.pieces[position.mover as usize].pawn_bitboard ^= switch;
 
-    position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize]
+    let pawn_keys = ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][from as usize]
         ^ ZOBRIST_KEYS_PIECES[position.mover as usize][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
+    position.zobrist_lock ^= pawn_keys;
+    position.pawn_zobrist_key ^= pawn_keys;
 
     position.en_passant_square = EN_PASSANT_NOT_AVAILABLE;
 
@@ -369,6 +399,7 @@ fn remove_captured_piece(position: &mut Position, to: Square) -> u8 {
         let captured = if enemy.pawn_bitboard & bit_to != 0 {
             enemy.pawn_bitboard &= !bit_to;
             position.zobrist_lock ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
+            position.pawn_zobrist_key ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
             CAPTURED_PAWN
         } else if enemy.knight_bitboard & bit_to != 0 {
             enemy.knight_bitboard &= !bit_to;
@@ -391,6 +422,7 @@ fn remove_captured_piece(position: &mut Position, to: Square) -> u8 {
         };
 
         enemy.all_pieces_bitboard &= !bit_to;
+        position.material_key -= captured_material_key(opponent, captured);
         captured
     } else {
         position.half_moves += 1;
@@ -411,11 +443,25 @@ pub fn all_pieces(position: &Position) -> Bitboard {
 /// Make a move in-place and return info needed to unmake it
 #[inline(always)]
 pub fn make_move_in_place(position: &mut Position, mv: Move) -> UnmakeInfo {
+    #[cfg(feature = "move-validation")]
+    if is_move_validation_enabled() {
+        let before = *position;
+        let unmake = make_move_in_place_unvalidated(position, mv);
+        validate_make(&before, position, mv, &unmake);
+        return unmake;
+    }
+    make_move_in_place_unvalidated(position, mv)
+}
+
+#[inline(always)]
+fn make_move_in_place_unvalidated(position: &mut Position, mv: Move) -> UnmakeInfo {
     // Save state for unmake (captured_piece will be set by the make functions)
     let castle_flags = position.castle_flags;
     let en_passant_square = position.en_passant_square;
     let half_moves = position.half_moves;
     let zobrist_lock = position.zobrist_lock;
+    let pawn_zobrist_key = position.pawn_zobrist_key;
+    let material_key = position.material_key;
 
     let from = from_square_part(mv);
     let to = to_square_part(mv);
@@ -474,6 +520,8 @@ pub fn make_move_in_place(position: &mut Position, mv: Move) -> UnmakeInfo {
         en_passant_square,
         half_moves,
         zobrist_lock,
+        pawn_zobrist_key,
+        material_key,
         captured_piece,
     }
 }
@@ -481,6 +529,18 @@ pub fn make_move_in_place(position: &mut Position, mv: Move) -> UnmakeInfo {
 /// Unmake a move, restoring the position to its previous state
 #[inline(always)]
 pub fn unmake_move(position: &mut Position, mv: Move, unmake: &UnmakeInfo) {
+    #[cfg(feature = "move-validation")]
+    if is_move_validation_enabled() {
+        let before_unmake = *position;
+        unmake_move_unvalidated(position, mv, unmake);
+        validate_unmake(&before_unmake, position, mv);
+        return;
+    }
+    unmake_move_unvalidated(position, mv, unmake)
+}
+
+#[inline(always)]
+fn unmake_move_unvalidated(position: &mut Position, mv: Move, unmake: &UnmakeInfo) {
     // Flip mover back (the move was made by the opponent of current mover)
     position.mover ^= 1;
 
@@ -549,6 +609,8 @@ pub fn unmake_move(position: &mut Position, mv: Move, unmake: &UnmakeInfo) {
     position.en_passant_square = unmake.en_passant_square;
     position.half_moves = unmake.half_moves;
     position.zobrist_lock = unmake.zobrist_lock;
+    position.pawn_zobrist_key = unmake.pawn_zobrist_key;
+    position.material_key = unmake.material_key;
 }
 
 #[inline(always)]
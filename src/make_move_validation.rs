@@ -0,0 +1,184 @@
+//! Checks of the incrementally updated position state, built with the `move-validation` feature
+//! and switched on with `debug on`
+//!
+//! While enabled, every `make_move_in_place` and `unmake_move` is followed by a check that the
+//! zobrist lock, pawn key and material key match a full recompute, that each side's
+//! `all_pieces_bitboard` is exactly the union of its piece bitboards, and that the pawn key changed
+//! only as the move says it should.
+//! Each unmake must also restore exactly the position the move was made from. A failure panics with
+//! the moves made since the check was switched on, so the corruption can be replayed.
+//!
+//! The switch and the moves are per thread, so threads that search or count perft for a thread
+//! with validation on have to switch it on themselves. Positions that are copied, moved on and
+//! then dropped without an unmake leave stale entries behind, which are discarded when an older
+//! position is unmade.
+
+use std::cell::{Cell, RefCell};
+
+use crate::endgame::material_key;
+use crate::fen::{algebraic_move_from_move, get_fen};
+use crate::hash::{pawn_zobrist_key, zobrist_lock, ZOBRIST_KEYS_PIECES, ZOBRIST_PIECE_INDEX_PAWN};
+use crate::make_move::{en_passant_captured_piece_square, CAPTURED_EP_PAWN, CAPTURED_PAWN};
+use crate::move_constants::{PIECE_MASK_FULL, PIECE_MASK_PAWN, PROMOTION_FULL_MOVE_MASK};
+use crate::opponent;
+use crate::types::{Move, Position, UnmakeInfo};
+use crate::utils::{from_square_part, to_square_part};
+
+/// Moves kept for reporting; older ones are dropped
+const MAX_TRACKED_MOVES: usize = 1024;
+
+thread_local! {
+    static MOVE_VALIDATION: Cell<bool> = const { Cell::new(false) };
+
+    /// Each move made on this thread with the positions before and after it
+    static MADE_MOVES: RefCell<Vec<(Move, Position, Position)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Switch validation on or off for the current thread
+pub fn set_move_validation(on: bool) {
+    MOVE_VALIDATION.with(|validation| validation.set(on));
+    MADE_MOVES.with(|made| made.borrow_mut().clear());
+}
+
+#[inline(always)]
+pub fn is_move_validation_enabled() -> bool {
+    MOVE_VALIDATION.with(|validation| validation.get())
+}
+
+#[cold]
+pub fn validate_make(before: &Position, after: &Position, mv: Move, unmake: &UnmakeInfo) {
+    MADE_MOVES.with(|made| {
+        let mut made = made.borrow_mut();
+        if made.len() == MAX_TRACKED_MOVES {
+            made.remove(0);
+        }
+        made.push((mv, *before, *after));
+    });
+
+    if let Err(e) = check_position(after) {
+        fail(&format!("after making {}: {}", algebraic_move_from_move(mv), e), after);
+    }
+    if after.pawn_zobrist_key != expected_pawn_key(before, mv, unmake) {
+        fail(
+            &format!("after making {}: pawn key changed unexpectedly", algebraic_move_from_move(mv)),
+            after,
+        );
+    }
+}
+
+#[cold]
+pub fn validate_unmake(before_unmake: &Position, restored: &Position, mv: Move) {
+    let made_from = MADE_MOVES.with(|made| {
+        let mut made = made.borrow_mut();
+        // Drop moves made on positions that were abandoned rather than unmade
+        while let Some((_, _, after)) = made.last() {
+            if after == before_unmake && after.zobrist_lock == before_unmake.zobrist_lock {
+                break;
+            }
+            made.pop();
+        }
+        made.pop()
+    });
+
+    if let Err(e) = check_position(restored) {
+        fail(&format!("after unmaking {}: {}", algebraic_move_from_move(mv), e), restored);
+    }
+    if let Some((made_move, before, _)) = made_from {
+        if made_move != mv {
+            fail(
+                &format!(
+                    "unmaking {} for a position reached by {}",
+                    algebraic_move_from_move(mv),
+                    algebraic_move_from_move(made_move)
+                ),
+                restored,
+            );
+        }
+        if before != *restored || before.zobrist_lock != restored.zobrist_lock {
+            fail(
+                &format!("unmaking {} did not restore {}", algebraic_move_from_move(mv), get_fen(&before)),
+                restored,
+            );
+        }
+    }
+}
+
+/// Check the incremental state of a position against values computed from scratch
+pub fn check_position(position: &Position) -> Result<(), String> {
+    if position.zobrist_lock != zobrist_lock(position) {
+        return Err(format!(
+            "zobrist lock {:x} should be {:x}",
+            position.zobrist_lock,
+            zobrist_lock(position)
+        ));
+    }
+    if position.pawn_zobrist_key != pawn_zobrist_key(position) {
+        return Err(format!(
+            "pawn key {:x} should be {:x}",
+            position.pawn_zobrist_key,
+            pawn_zobrist_key(position)
+        ));
+    }
+    if position.material_key != material_key(position) {
+        return Err(format!(
+            "material key {:x} should be {:x}",
+            position.material_key,
+            material_key(position)
+        ));
+    }
+    for (side, pieces) in ["white", "black"].iter().zip(position.pieces.iter()) {
+        let boards = [
+            pieces.pawn_bitboard,
+            pieces.knight_bitboard,
+            pieces.bishop_bitboard,
+            pieces.rook_bitboard,
+            pieces.queen_bitboard,
+            1 << pieces.king_square,
+        ];
+        let union = boards.iter().fold(0, |acc, b| acc | b);
+        let count: u32 = boards.iter().map(|b| b.count_ones()).sum();
+        if union != pieces.all_pieces_bitboard || count != pieces.all_pieces_bitboard.count_ones() {
+            return Err(format!(
+                "{} all pieces {:x} doesn't match its {} pieces {:x}",
+                side, pieces.all_pieces_bitboard, count, union
+            ));
+        }
+    }
+    if position.pieces[0].all_pieces_bitboard & position.pieces[1].all_pieces_bitboard != 0 {
+        return Err("white and black pieces overlap".to_string());
+    }
+    Ok(())
+}
+
+/// The incremental pawn key of `before` updated for the pawns the move moved, promoted or captured
+fn expected_pawn_key(before: &Position, mv: Move, unmake: &UnmakeInfo) -> u128 {
+    let mover = before.mover as usize;
+    let opponent = opponent!(before.mover) as usize;
+    let from = from_square_part(mv);
+    let to = to_square_part(mv);
+    let mut key = before.pawn_zobrist_key;
+
+    if mv & PIECE_MASK_FULL == PIECE_MASK_PAWN {
+        key ^= ZOBRIST_KEYS_PIECES[mover][ZOBRIST_PIECE_INDEX_PAWN][from as usize];
+        if mv & PROMOTION_FULL_MOVE_MASK == 0 {
+            key ^= ZOBRIST_KEYS_PIECES[mover][ZOBRIST_PIECE_INDEX_PAWN][to as usize];
+        }
+    }
+    match unmake.captured_piece {
+        CAPTURED_PAWN => key ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][to as usize],
+        CAPTURED_EP_PAWN => key ^= ZOBRIST_KEYS_PIECES[opponent][ZOBRIST_PIECE_INDEX_PAWN][en_passant_captured_piece_square(to) as usize],
+        _ => {}
+    }
+    key
+}
+
+fn fail(problem: &str, position: &Position) -> ! {
+    let moves = MADE_MOVES.with(|made| {
+        made.borrow()
+            .iter()
+            .map(|(m, _, _)| algebraic_move_from_move(*m))
+            .collect::<Vec<String>>()
+            .join(" ")
+    });
+    panic!("Position corrupted {} in {} after moves [{}]", problem, get_fen(position), moves);
+}
@@ -9,7 +9,8 @@
 //! Each imbalance type has its own helper function, and the main
 //! `material_imbalance_score()` function aggregates them all.
 
-use crate::types::{Bitboard, Pieces, Position, Score, BLACK, WHITE};
+use crate::opponent;
+use crate::types::{Bitboard, Mover, Pieces, Position, Score, BLACK, WHITE};
 
 /// Main entry point for material imbalance evaluation.
 /// Returns score adjustment from White's perspective.
@@ -33,6 +34,45 @@ pub fn material_imbalance_score(position: &Position) -> Score {
     score
 }
 
+/// The part of `material_imbalance_score` that depends only on the piece counts, so it can be
+/// kept in the material hash table. The rest is `knight_placement_score` for the side given by
+/// `queen_vs_knight_side`.
+#[inline(always)]
+pub fn material_imbalance_count_score(position: &Position) -> Score {
+    match queen_vs_knight_side(position) {
+        Some(WHITE) => queen_vs_knight_compensation(&position.pieces[WHITE as usize]),
+        Some(_) => -queen_vs_knight_compensation(&position.pieces[BLACK as usize]),
+        None => 0,
+    }
+}
+
+/// The side with a knight and pawns against a queen, if there is one
+#[inline(always)]
+pub fn queen_vs_knight_side(position: &Position) -> Option<Mover> {
+    let white = &position.pieces[WHITE as usize];
+    let black = &position.pieces[BLACK as usize];
+    if is_queen_vs_knight_and_pawns(black, white) {
+        Some(WHITE)
+    } else if is_queen_vs_knight_and_pawns(white, black) {
+        Some(BLACK)
+    } else {
+        None
+    }
+}
+
+/// The knight side's bonus for an active knight and connected pawns, from White's perspective
+#[inline(always)]
+pub fn knight_placement_score(position: &Position, knight_side: Mover) -> Score {
+    let knights = &position.pieces[knight_side as usize];
+    let queens = &position.pieces[opponent!(knight_side) as usize];
+    let bonus = knight_activity_bonus(knights, queens) + connected_pawns_bonus(knights.pawn_bitboard);
+    if knight_side == WHITE {
+        bonus
+    } else {
+        -bonus
+    }
+}
+
 /// Compensation bonus for the side with Knight + pawns when facing a Queen.
 ///
 /// In endgames where one side has a Queen (with or without pawns) and the
@@ -49,36 +89,52 @@ pub fn material_imbalance_score(position: &Position) -> Score {
 /// Rusty evaluated this as +362 for White, but it's actually ≈0.
 #[inline(always)]
 fn queen_vs_knight_and_pawns(queen_side: &Pieces, knight_side: &Pieces) -> Score {
+    if !is_queen_vs_knight_and_pawns(queen_side, knight_side) {
+        return 0;
+    }
+
+    queen_vs_knight_compensation(knight_side)
+        // Bonus for knight attacking enemy pawns (active knight)
+        + knight_activity_bonus(knight_side, queen_side)
+        // Bonus for connected pawns
+        + connected_pawns_bonus(knight_side.pawn_bitboard)
+}
+
+#[inline(always)]
+fn is_queen_vs_knight_and_pawns(queen_side: &Pieces, knight_side: &Pieces) -> bool {
     // Queen side must have exactly one queen
     if queen_side.queen_bitboard.count_ones() != 1 {
-        return 0;
+        return false;
     }
 
     // Queen side must have no rooks (otherwise it's not Q vs N+P)
     if queen_side.rook_bitboard != 0 {
-        return 0;
+        return false;
     }
 
     // Knight side must have at least one knight and no queen
     if knight_side.knight_bitboard == 0 || knight_side.queen_bitboard != 0 {
-        return 0;
+        return false;
     }
 
     // Knight side must have no rooks (pure Q vs N imbalance)
     if knight_side.rook_bitboard != 0 {
-        return 0;
+        return false;
     }
 
     // Neither side should have bishops (keeps it simple Q vs N)
     if queen_side.bishop_bitboard != 0 || knight_side.bishop_bitboard != 0 {
-        return 0;
+        return false;
     }
 
     // Knight side must have at least 1 pawn to have compensation
+    knight_side.pawn_bitboard != 0
+}
+
+/// The knight side's compensation for its number of pawns
+#[inline(always)]
+fn queen_vs_knight_compensation(knight_side: &Pieces) -> Score {
     let knight_side_pawns = knight_side.pawn_bitboard.count_ones();
-    if knight_side_pawns == 0 {
-        return 0;
-    }
 
     // Base compensation scales with pawn count:
     // 1 pawn: 60% of base (fortress harder with one pawn)
@@ -95,12 +151,6 @@ fn queen_vs_knight_and_pawns(queen_side: &Pieces, knight_side: &Pieces) -> Score
         bonus += (knight_side_pawns - 2) as Score * QUEEN_VS_KNIGHT_EXTRA_PAWN_BONUS;
     }
 
-    // Bonus for knight attacking enemy pawns (active knight)
-    bonus += knight_activity_bonus(knight_side, queen_side);
-
-    // Bonus for connected pawns
-    bonus += connected_pawns_bonus(knight_side.pawn_bitboard);
-
     bonus
 }
 
@@ -189,6 +239,26 @@ mod tests {
         assert!(bonus >= 1600, "Expected bonus >= 1600, got {}", bonus);
     }
 
+    #[test]
+    fn test_material_imbalance_score_splits_into_counts_and_placement() {
+        for fen in [
+            "1Q6/2k2Kpp/8/8/5Pn1/7P/8/8 w - - 0 3",
+            "8/2k2K2/8/2n5/8/4p3/4Q3/8 w - - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ] {
+            let position = get_position(fen);
+            let placement = queen_vs_knight_side(&position).map_or(0, |side| knight_placement_score(&position, side));
+            assert_eq!(
+                material_imbalance_count_score(&position) + placement,
+                material_imbalance_score(&position),
+                "{}",
+                fen
+            );
+        }
+        let position = get_position("8/2k2K2/8/2n5/8/4p3/4Q3/8 w - - 0 1");
+        assert_eq!(queen_vs_knight_side(&position), Some(BLACK));
+    }
+
     #[test]
     fn test_material_imbalance_score_position_8() {
         let position = get_position("1Q6/2k2Kpp/8/8/5Pn1/7P/8/8 w - - 0 3");
@@ -0,0 +1,195 @@
+//! Staged move generation for the main search
+//!
+//! Moves are handed out in the order they are most likely to cause a cutoff: the hash move, then
+//! captures that don't lose material, then the killer moves and countermove, then the remaining
+//! quiet moves by history, and finally the captures that lose material. Each stage is generated
+//! only when the previous one runs out, so a node that cuts off on a capture or a killer never
+//! generates its quiet moves at all. Like `generate_quiet_moves`, the quiet stage only promotes to a queen.
+//!
+//! When in check, all check evasions are generated in one go and ordered by `score_move`.
+
+use arrayvec::ArrayVec;
+
+use crate::bitboards::bit;
+use crate::move_constants::PROMOTION_FULL_MOVE_MASK;
+use crate::move_scores::{countermove, score_capture, score_move};
+use crate::moves::{generate_captures, generate_check_evasions, generate_quiet_moves, verify_move};
+use crate::opponent;
+use crate::search::pick_high_score_move;
+use crate::see::static_exchange_evaluation;
+use crate::types::{Move, MoveScoreArray, Position, SearchState, BLACK, WHITE};
+use crate::utils::to_square_part;
+
+/// The mate killer, two killers and the countermove
+const MAX_KILLER_CANDIDATES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+pub struct MovePicker {
+    stage: Stage,
+    in_check: bool,
+    hash_move: Move,
+    ply: usize,
+    moves: MoveScoreArray,
+    bad_captures: MoveScoreArray,
+    killers: ArrayVec<Move, MAX_KILLER_CANDIDATES>,
+    killer_index: usize,
+}
+
+impl MovePicker {
+    /// A picker for all pseudo-legal moves, starting with the hash move if it is valid here (0 for none)
+    pub fn new(hash_move: Move, ply: u8, in_check: bool) -> Self {
+        MovePicker {
+            stage: Stage::HashMove,
+            in_check,
+            hash_move,
+            ply: ply as usize,
+            moves: MoveScoreArray::new(),
+            bad_captures: MoveScoreArray::new(),
+            killers: ArrayVec::new(),
+            killer_index: 0,
+        }
+    }
+
+    /// A picker for a node that has already searched its hash move, which is then never returned
+    pub fn after_hash_move(hash_move: Move, ply: u8, in_check: bool) -> Self {
+        let mut picker = MovePicker::new(hash_move, ply, in_check);
+        picker.stage = picker.stage_after_hash_move();
+        picker
+    }
+
+    fn stage_after_hash_move(&self) -> Stage {
+        if self.in_check {
+            Stage::GenerateEvasions
+        } else {
+            Stage::GenerateCaptures
+        }
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// The next move to try, or None once every stage is exhausted
+    pub fn next_move(&mut self, position: &Position, search_state: &SearchState) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = self.stage_after_hash_move();
+                    if self.hash_move != 0 && verify_move(position, self.hash_move) {
+                        return Some(self.hash_move);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    let enemy = &position.pieces[opponent!(position.mover) as usize];
+                    for m in generate_captures(position) {
+                        if m == self.hash_move {
+                            continue;
+                        }
+                        // En passant captures and promotions are always worth trying early
+                        if enemy.all_pieces_bitboard & bit(to_square_part(m)) == 0 || m & PROMOTION_FULL_MOVE_MASK != 0 {
+                            self.moves.push((m, score_move(position, m, search_state, self.ply, enemy)));
+                            continue;
+                        }
+                        let see = static_exchange_evaluation(position, m);
+                        let score = score_capture(position, m, search_state, see);
+                        if see >= 0 {
+                            self.moves.push((m, score));
+                        } else {
+                            self.bad_captures.push((m, score));
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    if !self.moves.is_empty() {
+                        return Some(pick_high_score_move(&mut self.moves));
+                    }
+                    self.killers = self.killer_moves(position, search_state);
+                    self.stage = Stage::Killers;
+                }
+                Stage::Killers => {
+                    if self.killer_index < self.killers.len() {
+                        self.killer_index += 1;
+                        return Some(self.killers[self.killer_index - 1]);
+                    }
+                    self.stage = Stage::GenerateQuiets;
+                }
+                Stage::GenerateQuiets => {
+                    let enemy = &position.pieces[opponent!(position.mover) as usize];
+                    for m in generate_quiet_moves(position) {
+                        if m != self.hash_move && !self.killers.contains(&m) {
+                            self.moves.push((m, score_move(position, m, search_state, self.ply, enemy)));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if !self.moves.is_empty() {
+                        return Some(pick_high_score_move(&mut self.moves));
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if !self.bad_captures.is_empty() {
+                        return Some(pick_high_score_move(&mut self.bad_captures));
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::GenerateEvasions => {
+                    let enemy = &position.pieces[opponent!(position.mover) as usize];
+                    for m in generate_check_evasions(position) {
+                        if m != self.hash_move {
+                            self.moves.push((m, score_move(position, m, search_state, self.ply, enemy)));
+                        }
+                    }
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => {
+                    if !self.moves.is_empty() {
+                        return Some(pick_high_score_move(&mut self.moves));
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// The mate killer, killers and countermove for this ply that are quiet and playable here
+    fn killer_moves(&self, position: &Position, search_state: &SearchState) -> ArrayVec<Move, MAX_KILLER_CANDIDATES> {
+        let mut killers = ArrayVec::new();
+        let all_pieces = position.pieces[WHITE as usize].all_pieces_bitboard | position.pieces[BLACK as usize].all_pieces_bitboard;
+        let candidates = [
+            search_state.mate_killer[self.ply],
+            search_state.killer_moves[self.ply][0],
+            search_state.killer_moves[self.ply][1],
+            countermove(position, self.ply, search_state),
+        ];
+        for m in candidates {
+            if m != 0
+                && m != self.hash_move
+                && !killers.contains(&m)
+                && m & PROMOTION_FULL_MOVE_MASK == 0
+                && all_pieces & bit(to_square_part(m)) == 0
+                && to_square_part(m) != position.en_passant_square
+                && verify_move(position, m)
+            {
+                killers.push(m);
+            }
+        }
+        killers
+    }
+}
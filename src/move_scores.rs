@@ -73,9 +73,11 @@ pub fn attacker_value(piece: Move) -> Score {
 pub fn score_move(position: &Position, m: Move, search_state: &SearchState, ply: usize, enemy: &Pieces) -> Score {
     let to_square = to_square_part(m);
 
-    let score = if enemy.all_pieces_bitboard & bit(to_square) != 0 {
-        GOOD_CAPTURE_START + static_exchange_evaluation(position, m)
-    } else if m & PROMOTION_FULL_MOVE_MASK != 0 {
+    if enemy.all_pieces_bitboard & bit(to_square) != 0 {
+        return score_capture(position, m, search_state, static_exchange_evaluation(position, m));
+    }
+
+    let score = if m & PROMOTION_FULL_MOVE_MASK != 0 {
         match m & PROMOTION_FULL_MOVE_MASK {
             PROMOTION_ROOK_MOVE_MASK => 3,
             PROMOTION_BISHOP_MOVE_MASK => 2,
@@ -106,7 +108,18 @@ pub fn score_move(position: &Position, m: Move, search_state: &SearchState, ply:
         }
     };
 
-    let pawn_push_score = if m & PIECE_MASK_FULL == PIECE_MASK_PAWN {
+    score + pawn_push_score(position, m) + history_score(position, m, search_state, to_square)
+}
+
+/// Score a capture of a piece whose static exchange value is already known
+#[inline(always)]
+pub fn score_capture(position: &Position, m: Move, search_state: &SearchState, see: Score) -> Score {
+    GOOD_CAPTURE_START + see + pawn_push_score(position, m) + history_score(position, m, search_state, to_square_part(m))
+}
+
+#[inline(always)]
+fn pawn_push_score(position: &Position, m: Move) -> Score {
+    if m & PIECE_MASK_FULL == PIECE_MASK_PAWN {
         let to_square = to_square_part(m);
         if to_square >= 48 || to_square <= 15 {
             PAWN_PUSH_1
@@ -127,9 +140,7 @@ pub fn score_move(position: &Position, m: Move, search_state: &SearchState, ply:
         }
     } else {
         0
-    };
-
-    score + pawn_push_score + history_score(position, m, search_state, to_square)
+    }
 }
 
 #[inline(always)]
@@ -167,6 +178,16 @@ pub fn piece_value(pieces: &Pieces, sq: Square) -> Score {
 /// Check if move is a countermove to the previous opponent move
 #[inline(always)]
 fn countermove_score(position: &Position, ply: usize, m: Move, search_state: &SearchState) -> Score {
+    if countermove(position, ply, search_state) == m {
+        COUNTERMOVE_SCORE
+    } else {
+        0
+    }
+}
+
+/// The stored reply to the opponent's previous move, or 0 if there is none
+#[inline(always)]
+pub fn countermove(position: &Position, ply: usize, search_state: &SearchState) -> Move {
     if ply == 0 {
         return 0;
     }
@@ -179,11 +200,7 @@ fn countermove_score(position: &Position, ply: usize, m: Move, search_state: &Se
     let opponent_side = position.mover ^ 1;
     let prev_piece = piece_type_to_index(prev_move) + (opponent_side as usize * 6);
     let prev_to = to_square_part(prev_move) as usize;
-    if search_state.countermoves[prev_piece][prev_to] == m {
-        COUNTERMOVE_SCORE
-    } else {
-        0
-    }
+    search_state.countermoves[prev_piece][prev_to]
 }
 
 /// Convert move's piece mask to index 0-5
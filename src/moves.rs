@@ -3,10 +3,11 @@ use crate::bitboards::{
     NO_CHECK_CASTLE_SQUARES, PAWN_MOVES_CAPTURE, PAWN_MOVES_FORWARD, ROOK_RAYS,
 };
 use crate::magic_bitboards::{magic_moves_bishop, magic_moves_rook};
+use crate::make_move::make_move;
 use crate::move_constants::{
-    CASTLE_FLAG, CASTLE_MOVE, KING_INDEX, PIECE_MASK_BISHOP, PIECE_MASK_FULL, PIECE_MASK_KING, PIECE_MASK_KNIGHT, PIECE_MASK_QUEEN,
-    PIECE_MASK_ROOK, PROMOTION_BISHOP_MOVE_MASK, PROMOTION_KNIGHT_MOVE_MASK, PROMOTION_QUEEN_MOVE_MASK, PROMOTION_ROOK_MOVE_MASK,
-    PROMOTION_SQUARES, QUEEN_INDEX,
+    CASTLE_FLAG, CASTLE_MOVE, KING_INDEX, PIECE_MASK_BISHOP, PIECE_MASK_FULL, PIECE_MASK_KING, PIECE_MASK_KNIGHT, PIECE_MASK_PAWN,
+    PIECE_MASK_QUEEN, PIECE_MASK_ROOK, PROMOTION_BISHOP_MOVE_MASK, PROMOTION_KNIGHT_MOVE_MASK, PROMOTION_QUEEN_MOVE_MASK,
+    PROMOTION_ROOK_MOVE_MASK, PROMOTION_SQUARES, QUEEN_INDEX,
 };
 use crate::types::{Bitboard, Move, MoveList, Mover, Position, Square, BLACK, WHITE};
 use crate::utils::{from_square_mask, from_square_part, to_square_part};
@@ -730,3 +731,83 @@ fn generate_pawn_evasion_blocks(
         }
     }
 }
+
+/// Generate only legal moves. Pseudo-legal moves are filtered without making them: the king may
+/// not step onto an attacked square, in check every other move must capture or block the single
+/// checker, and a pinned piece may only move along the line between its king and the pinner.
+/// En passant captures, which can uncover a check along the rank, are the only moves that are
+/// made to test them.
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    let mover = position.mover;
+    let friendly = position.pieces[mover as usize];
+    let enemy = position.pieces[opponent!(mover) as usize];
+    let all_pieces = friendly.all_pieces_bitboard | enemy.all_pieces_bitboard;
+    let king_square = friendly.king_square;
+
+    // Squares a non-king move must land on: anywhere when not in check, on the checker or between
+    // it and the king in single check, and nowhere in double check
+    let checkers = get_checkers(position, mover);
+    let check_mask = match checkers.count_ones() {
+        0 => !0,
+        1 => checkers | between_squares(king_square, checkers.trailing_zeros() as Square),
+        _ => 0,
+    };
+
+    let pinned = pinned_pieces(position, mover, all_pieces);
+    let occupied_without_king = all_pieces & !bit(king_square);
+
+    let mut move_list = generate_moves(position);
+    move_list.retain(|m| {
+        let from_square = from_square_part(*m);
+        let to_square = to_square_part(*m);
+        if from_square == king_square {
+            // Castles are only generated when the squares the king starts on and passes are safe,
+            // so like any other king move they only need the landing square checking
+            !is_square_attacked_with_occupancy(position, to_square, mover, occupied_without_king)
+        } else if *m & PIECE_MASK_FULL == PIECE_MASK_PAWN && to_square == position.en_passant_square {
+            let mut new_position = *position;
+            make_move(position, *m, &mut new_position);
+            !is_check(&new_position, mover)
+        } else {
+            bit(to_square) & check_mask != 0
+                && (bit(from_square) & pinned == 0
+                    || between_squares(king_square, to_square) & bit(from_square) != 0
+                    || between_squares(king_square, from_square) & bit(to_square) != 0)
+        }
+    });
+
+    move_list
+}
+
+/// Returns a bitboard of the specified side's pieces that are pinned to its king by enemy sliders
+#[inline(always)]
+pub fn pinned_pieces(position: &Position, mover: Mover, all_pieces: Bitboard) -> Bitboard {
+    let king_square = position.pieces[mover as usize].king_square;
+    let enemy = position.pieces[opponent!(mover) as usize];
+
+    // Enemy sliders that would attack the king if none of the mover's pieces were in the way
+    let mut pinners = (magic_moves_rook(king_square, enemy.all_pieces_bitboard) & (enemy.rook_bitboard | enemy.queen_bitboard))
+        | (magic_moves_bishop(king_square, enemy.all_pieces_bitboard) & (enemy.bishop_bitboard | enemy.queen_bitboard));
+
+    let mut pinned: Bitboard = 0;
+    while pinners != 0 {
+        let pinner_square = get_and_unset_lsb!(pinners);
+        let blockers = between_squares(king_square, pinner_square) & all_pieces;
+        if blockers.count_ones() == 1 {
+            pinned |= blockers & position.pieces[mover as usize].all_pieces_bitboard;
+        }
+    }
+    pinned
+}
+
+/// Like `is_square_attacked`, but with sliders moving through the given occupancy
+#[inline(always)]
+pub fn is_square_attacked_with_occupancy(position: &Position, attacked_square: Square, attacked: Mover, all_pieces: Bitboard) -> bool {
+    let enemy = position.pieces[opponent!(attacked) as usize];
+
+    enemy.pawn_bitboard & PAWN_MOVES_CAPTURE[attacked as usize][attacked_square as usize] != 0
+        || enemy.knight_bitboard & KNIGHT_MOVES_BITBOARDS[attacked_square as usize] != 0
+        || bit(enemy.king_square) & KING_MOVES_BITBOARDS[attacked_square as usize] != 0
+        || is_square_attacked_by_straight_slider(enemy.rook_bitboard | enemy.queen_bitboard, attacked_square, all_pieces)
+        || is_square_attacked_by_diagonal_slider(enemy.bishop_bitboard | enemy.queen_bitboard, attacked_square, all_pieces)
+}
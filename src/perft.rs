@@ -1,41 +1,37 @@
-use crate::fen::algebraic_move_from_move;
+use crate::fen::{algebraic_move_from_move, try_get_position};
 use crate::make_move::{make_move_in_place, unmake_move};
-use crate::moves::{generate_moves, is_check};
+#[cfg(feature = "move-validation")]
+use crate::make_move_validation::{is_move_validation_enabled, set_move_validation};
+use crate::moves::{generate_legal_moves, generate_moves, is_check};
 use crate::types::{Move, Position};
 use num_format::{Locale, ToFormattedString};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
-pub fn perft(position: &mut Position, depth: u8) -> u64 {
-    let start = Instant::now();
-
-    return perft_inner(position, depth, depth, start, 0);
+/// Entries in the perft hash table, 16 bytes each
+pub const PERFT_HASH_ENTRIES: usize = 1 << 22;
 
-    fn perft_inner(position: &mut Position, depth: u8, start_depth: u8, start_time: Instant, mut total_nodes: u64) -> u64 {
-        let mut count = 0;
-        let mover = position.mover;
-
-        for m in generate_moves(position) {
-            let unmake_info = make_move_in_place(position, m);
+pub const PERFT_HASH_ENTRY_BYTES: usize = 16;
 
-            if !is_check(position, mover) {
-                count += if depth == 0 {
-                    1
-                } else {
-                    let nodes = perft_inner(position, depth - 1, start_depth, start_time, total_nodes);
-                    total_nodes += nodes;
-                    if depth == start_depth {
-                        show_for_move(start_time, total_nodes, m, nodes)
-                    }
-                    nodes
-                }
-            }
+pub fn perft(position: &mut Position, depth: u8) -> u64 {
+    let start = Instant::now();
 
-            unmake_move(position, m, &unmake_info);
-        }
+    if depth == 0 {
+        return perft_count(position, depth);
+    }
 
-        count
+    let mut total_nodes = 0;
+    for m in generate_legal_moves(position) {
+        let unmake_info = make_move_in_place(position, m);
+        let nodes = perft_count(position, depth - 1);
+        unmake_move(position, m, &unmake_info);
+        total_nodes += nodes;
+        show_for_move(start, total_nodes, m, nodes)
     }
 
+    return total_nodes;
+
     #[inline(always)]
     fn show_for_move(start_time: Instant, total_nodes: u64, m: Move, nodes: u64) {
         let duration = start_time.elapsed();
@@ -47,3 +43,202 @@ pub fn perft(position: &mut Position, depth: u8) -> u64 {
         )
     }
 }
+
+/// Count the nodes of the move tree as `perft` does, without printing anything
+pub fn perft_count(position: &mut Position, depth: u8) -> u64 {
+    let moves = generate_legal_moves(position);
+    if depth == 0 {
+        return moves.len() as u64;
+    }
+
+    let mut count = 0;
+    for m in moves {
+        let unmake_info = make_move_in_place(position, m);
+        count += perft_count(position, depth - 1);
+        unmake_move(position, m, &unmake_info);
+    }
+
+    count
+}
+
+/// The node count under each legal move, adding up to the `perft` count for the same depth
+pub fn perft_divide(position: &mut Position, depth: u8) -> Vec<(Move, u64)> {
+    generate_legal_moves(position)
+        .into_iter()
+        .map(|m| {
+            if depth == 0 {
+                return (m, 1);
+            }
+            let unmake_info = make_move_in_place(position, m);
+            let nodes = perft_count(position, depth - 1);
+            unmake_move(position, m, &unmake_info);
+            (m, nodes)
+        })
+        .collect()
+}
+
+/// Perft using the pseudo-legal generator, making every move to see whether it leaves the king in
+/// check. Slower than `perft`, but independent of `generate_legal_moves`, so the two can check
+/// each other.
+pub fn perft_pseudo_legal(position: &mut Position, depth: u8) -> u64 {
+    let mut count = 0;
+    let mover = position.mover;
+
+    for m in generate_moves(position) {
+        let unmake_info = make_move_in_place(position, m);
+        if !is_check(position, mover) {
+            count += if depth == 0 { 1 } else { perft_pseudo_legal(position, depth - 1) };
+        }
+        unmake_move(position, m, &unmake_info);
+    }
+
+    count
+}
+
+/// Node counts of positions already counted, keyed on the zobrist lock and the remaining depth.
+/// Entries are shared between threads without locking: the check word is stored XORed with the
+/// count, so an entry torn by two threads writing at once simply fails to match.
+pub struct PerftHashTable {
+    entries: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl PerftHashTable {
+    pub fn new(entries: usize) -> Self {
+        PerftHashTable {
+            entries: (0..entries.max(1)).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[inline(always)]
+    fn slot(&self, position: &Position, depth: u8) -> (&(AtomicU64, AtomicU64), u64) {
+        let lock = position.zobrist_lock ^ (depth as u128).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let index = (lock as u64 % self.entries.len() as u64) as usize;
+        // A check word of zero would match an empty entry
+        (&self.entries[index], (lock >> 64) as u64 | 1)
+    }
+
+    pub fn get(&self, position: &Position, depth: u8) -> Option<u64> {
+        let (entry, check) = self.slot(position, depth);
+        let count = entry.1.load(Ordering::Relaxed);
+        if entry.0.load(Ordering::Relaxed) ^ count == check {
+            Some(count)
+        } else {
+            None
+        }
+    }
+
+    pub fn store(&self, position: &Position, depth: u8, count: u64) {
+        let (entry, check) = self.slot(position, depth);
+        entry.0.store(check ^ count, Ordering::Relaxed);
+        entry.1.store(count, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Debug for PerftHashTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PerftHashTable({} entries)", self.entries.len())
+    }
+}
+
+/// As `perft_count`, using the hash table to skip positions that have been counted before
+pub fn perft_hashed(position: &mut Position, depth: u8, hash_table: &PerftHashTable) -> u64 {
+    if depth == 0 {
+        return generate_legal_moves(position).len() as u64;
+    }
+    if let Some(count) = hash_table.get(position, depth) {
+        return count;
+    }
+
+    let mut count = 0;
+    for m in generate_legal_moves(position) {
+        let unmake_info = make_move_in_place(position, m);
+        count += perft_hashed(position, depth - 1, hash_table);
+        unmake_move(position, m, &unmake_info);
+    }
+
+    hash_table.store(position, depth, count);
+    count
+}
+
+/// As `perft_divide`, with the root moves shared out between threads and the hash table shared
+/// by all of them. The moves are returned in the order they were generated.
+pub fn perft_divide_parallel(position: &Position, depth: u8, hash_table: &PerftHashTable) -> Vec<(Move, u64)> {
+    #[cfg(feature = "move-validation")]
+    let validation = is_move_validation_enabled();
+    generate_legal_moves(position)
+        .into_iter()
+        .collect::<Vec<Move>>()
+        .par_iter()
+        .map(|m| {
+            #[cfg(feature = "move-validation")]
+            set_move_validation(validation);
+            if depth == 0 {
+                return (*m, 1);
+            }
+            let mut child = *position;
+            make_move_in_place(&mut child, *m);
+            (*m, perft_hashed(&mut child, depth - 1, hash_table))
+        })
+        .collect()
+}
+
+/// One position of a perft suite, from a line such as `<fen> ;D1 20 ;D2 400 ;D3 8902`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerftSuiteEntry {
+    pub fen: String,
+    /// Expected node counts by depth in plies
+    pub expected: Vec<(u8, u64)>,
+}
+
+pub fn parse_perft_suite_line(line: &str) -> Result<PerftSuiteEntry, String> {
+    let mut fields = line.split(';');
+    let fen = fields.next().unwrap_or("").trim().to_string();
+    if fen.is_empty() {
+        return Err(format!("Missing FEN: {}", line));
+    }
+
+    let mut expected = vec![];
+    for field in fields.map(str::trim).filter(|f| !f.is_empty()) {
+        let (opcode, count) = field
+            .split_once(char::is_whitespace)
+            .ok_or(format!("Invalid perft opcode: {}", field))?;
+        let depth = opcode
+            .strip_prefix('D')
+            .and_then(|d| d.parse::<u8>().ok())
+            .filter(|d| *d > 0)
+            .ok_or(format!("Invalid perft opcode: {}", field))?;
+        let count = count.trim().parse::<u64>().map_err(|_| format!("Invalid perft count: {}", field))?;
+        expected.push((depth, count));
+    }
+
+    Ok(PerftSuiteEntry { fen, expected })
+}
+
+/// Run every position of a perft suite up to `max_depth` plies. Returns the number of counts
+/// checked and a description of each one that didn't match.
+pub fn run_perft_suite(suite: &str, max_depth: u8, hash_table: &PerftHashTable) -> Result<(usize, Vec<String>), String> {
+    let mut checked = 0;
+    let mut failures = vec![];
+
+    for line in suite.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#')) {
+        let entry = parse_perft_suite_line(line)?;
+        let position = try_get_position(&entry.fen).map_err(|e| format!("Invalid FEN {}: {}", entry.fen, e))?;
+        for (depth, expected) in entry.expected.iter().filter(|(d, _)| *d <= max_depth) {
+            let nodes: u64 = perft_divide_parallel(&position, depth - 1, hash_table).iter().map(|(_, n)| n).sum();
+            checked += 1;
+            if nodes != *expected {
+                failures.push(format!("{} D{}: expected {} got {}", entry.fen, depth, expected, nodes));
+            }
+        }
+    }
+
+    Ok((checked, failures))
+}
@@ -0,0 +1,132 @@
+//! Perft checked against shakmaty's move generator
+//!
+//! Both generators count the move tree from the same FEN. At each node the legal move lists are
+//! compared, and the divide counts decide which move to follow down: the first move whose counts
+//! disagree leads towards the bug. The search stops at the first position where the two move lists
+//! differ and reports the moves that lead there.
+
+use crate::fen::{algebraic_move_from_move, get_fen, try_get_position};
+use crate::make_move::{make_move_in_place, unmake_move};
+use crate::moves::generate_legal_moves;
+use crate::perft::perft_divide;
+use crate::pgn::chess_from_fen;
+use crate::types::Position;
+use shakmaty::{CastlingMode, Chess, Position as ShakmatyPosition};
+use std::fmt;
+
+/// Positions that exercise en passant, promotions, castling and pins
+pub const PERFT_VERIFY_FENS: [&str; 10] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+    "8/8/3k4/8/2pP4/8/8/B3K3 b - d3 0 1",
+    "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K1R1 b Qkq - 0 1",
+];
+
+/// The first position, found by following mismatching divide counts, where the two generators
+/// disagree about the legal moves
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerftDivergence {
+    /// Moves from the starting position to the diverging one
+    pub path: Vec<String>,
+    pub fen: String,
+    /// Legal moves that only shakmaty generates
+    pub missing: Vec<String>,
+    /// Moves that only we generate
+    pub extra: Vec<String>,
+}
+
+impl fmt::Display for PerftDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "moves differ after [{}] in {}: missing [{}] extra [{}]",
+            self.path.join(" "),
+            self.fen,
+            self.missing.join(" "),
+            self.extra.join(" ")
+        )
+    }
+}
+
+/// Why `verify_perft` did not return a node count
+#[derive(Debug, Clone, PartialEq)]
+pub enum PerftError {
+    InvalidFen(String),
+    Divergence(PerftDivergence),
+}
+
+impl fmt::Display for PerftError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PerftError::InvalidFen(e) => write!(f, "{}", e),
+            PerftError::Divergence(divergence) => write!(f, "{}", divergence),
+        }
+    }
+}
+
+/// Compare perft to the given depth in plies, as for `go perft`, with shakmaty. Returns the node
+/// count when the two agree, or the first divergence when they don't.
+pub fn verify_perft(fen: &str, depth: u8) -> Result<u64, PerftError> {
+    let mut position = try_get_position(fen).map_err(|e| PerftError::InvalidFen(format!("Invalid FEN: {}", e)))?;
+    let chess = chess_from_fen(fen).map_err(PerftError::InvalidFen)?;
+
+    verify_perft_position(&mut position, &chess, depth).map_err(PerftError::Divergence)
+}
+
+/// As `verify_perft`, for a position and its shakmaty equivalent that are already set up
+pub fn verify_perft_position(position: &mut Position, chess: &Chess, depth: u8) -> Result<u64, PerftDivergence> {
+    match find_divergence(position, chess, depth, &mut vec![]) {
+        Some(divergence) => Err(divergence),
+        None => Ok(shakmaty::perft(chess, depth as u32)),
+    }
+}
+
+fn find_divergence(position: &mut Position, chess: &Chess, depth: u8, path: &mut Vec<String>) -> Option<PerftDivergence> {
+    let mut ours: Vec<String> = generate_legal_moves(position)
+        .iter()
+        .map(|m| algebraic_move_from_move(*m))
+        .collect();
+    let mut theirs: Vec<String> = chess
+        .legal_moves()
+        .iter()
+        .map(|m| m.to_uci(CastlingMode::Standard).to_string())
+        .collect();
+    ours.sort();
+    theirs.sort();
+
+    if ours != theirs {
+        return Some(PerftDivergence {
+            path: path.clone(),
+            fen: get_fen(position),
+            missing: theirs.iter().filter(|m| !ours.contains(m)).cloned().collect(),
+            extra: ours.iter().filter(|m| !theirs.contains(m)).cloned().collect(),
+        });
+    }
+    if depth <= 1 {
+        return None;
+    }
+
+    for (m, nodes) in perft_divide(position, depth - 2) {
+        let uci = algebraic_move_from_move(m);
+        let chess_move = chess
+            .legal_moves()
+            .into_iter()
+            .find(|cm| cm.to_uci(CastlingMode::Standard).to_string() == uci)?;
+        let child = chess.clone().play(&chess_move).ok()?;
+        if nodes != shakmaty::perft(&child, depth as u32 - 1) {
+            let unmake_info = make_move_in_place(position, m);
+            path.push(uci);
+            let divergence = find_divergence(position, &child, depth - 1, path);
+            unmake_move(position, m, &unmake_info);
+            return divergence;
+        }
+    }
+
+    None
+}
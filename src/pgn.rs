@@ -0,0 +1,184 @@
+//! Reading games from PGN, and converting between SAN and our long algebraic moves
+//!
+//! Only the main line of each game is kept. Comments, variations, NAGs and the move suffixes
+//! `!` and `?` are skipped, since the tools that read PGN make their own judgements about the
+//! moves. SAN is converted with shakmaty, which already knows how to disambiguate moves.
+
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Chess, Position as ShakmatyPosition, PositionError};
+
+use crate::move_constants::START_POS;
+
+pub const PGN_RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// The main line in SAN
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from, from the `FEN` tag if there is one
+    pub fn start_fen(&self) -> &str {
+        self.tag("FEN").unwrap_or(START_POS)
+    }
+
+    /// The main line as long algebraic moves
+    pub fn uci_moves(&self) -> Result<Vec<String>, String> {
+        san_to_uci(self.start_fen(), &self.moves)
+    }
+}
+
+/// Read every game in a PGN file
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut tags = vec![];
+    let mut movetext = String::new();
+
+    for line in text.lines().map(|l| l.trim()) {
+        if let Some(tag) = parse_tag(line) {
+            if !movetext.trim().is_empty() {
+                games.extend(split_movetext(std::mem::take(&mut tags), &std::mem::take(&mut movetext)));
+            }
+            tags.push(tag);
+        } else if !line.starts_with('%') {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    if !movetext.trim().is_empty() || !tags.is_empty() {
+        games.extend(split_movetext(tags, &movetext));
+    }
+    games
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+/// Split movetext into games at each result, skipping comments, variations and NAGs. The tags
+/// belong to the first game; any further games in the same movetext have none.
+fn split_movetext(tags: Vec<(String, String)>, movetext: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut tags = Some(tags);
+    let mut moves = vec![];
+    let mut token = String::new();
+    let mut comment = false;
+    let mut line_comment = false;
+    let mut variation_depth = 0;
+
+    let mut end_token = |token: &mut String, moves: &mut Vec<String>, games: &mut Vec<PgnGame>| {
+        let t = std::mem::take(token);
+        if PGN_RESULTS.contains(&t.as_str()) {
+            games.push(PgnGame {
+                tags: tags.take().unwrap_or_default(),
+                moves: std::mem::take(moves),
+                result: t,
+            });
+        } else if let Some(san) = san_token(&t) {
+            moves.push(san);
+        }
+    };
+
+    for c in movetext.chars() {
+        match c {
+            _ if line_comment => line_comment = c != '\n',
+            '}' if comment => comment = false,
+            _ if comment => {}
+            '{' | ';' => {
+                end_token(&mut token, &mut moves, &mut games);
+                comment = c == '{';
+                line_comment = c == ';';
+            }
+            ')' if variation_depth > 0 => variation_depth -= 1,
+            '(' => {
+                end_token(&mut token, &mut moves, &mut games);
+                variation_depth += 1;
+            }
+            _ if variation_depth > 0 => {}
+            c if c.is_whitespace() => end_token(&mut token, &mut moves, &mut games),
+            c => token.push(c),
+        }
+    }
+    end_token(&mut token, &mut moves, &mut games);
+    if !moves.is_empty() || games.is_empty() {
+        games.push(PgnGame {
+            tags: tags.take().unwrap_or_default(),
+            moves,
+            result: "*".to_string(),
+        });
+    }
+    games
+}
+
+/// The SAN in a movetext token, without any move number in front of it or annotation after it
+fn san_token(token: &str) -> Option<String> {
+    if token.starts_with('$') {
+        return None;
+    }
+    let san = match token.rfind('.') {
+        Some(i) => &token[i + 1..],
+        None => token,
+    };
+    let san = san.trim_end_matches(['!', '?']);
+    if san.is_empty() || san.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Queenside first, or its first half would be taken for kingside castling
+    Some(san.replace("0-0-0", "O-O-O").replace("0-0", "O-O"))
+}
+
+/// Set up a shakmaty position from a FEN, dropping castling rights and en passant squares it doesn't accept
+pub(crate) fn chess_from_fen(fen: &str) -> Result<Chess, String> {
+    fen.parse::<Fen>()
+        .map_err(|e| format!("Invalid FEN: {}", e))?
+        .into_position(CastlingMode::Standard)
+        .or_else(PositionError::ignore_invalid_castling_rights)
+        .or_else(PositionError::ignore_invalid_ep_square)
+        .map_err(|e| format!("Invalid position: {}", e))
+}
+
+/// Convert moves in SAN, played in order from the given position, to long algebraic moves
+pub fn san_to_uci(fen: &str, moves: &[String]) -> Result<Vec<String>, String> {
+    let mut chess = chess_from_fen(fen)?;
+    moves
+        .iter()
+        .map(|san| {
+            let m = san
+                .parse::<SanPlus>()
+                .map_err(|_| format!("Invalid move {}", san))?
+                .san
+                .to_move(&chess)
+                .map_err(|_| format!("Illegal move {}", san))?;
+            chess.play_unchecked(&m);
+            Ok(m.to_uci(CastlingMode::Standard).to_string())
+        })
+        .collect()
+}
+
+/// Convert long algebraic moves, played in order from the given position, to SAN. The line stops
+/// at the first move that is not legal, so a principal variation that has gone wrong is cut short.
+pub fn uci_to_san(fen: &str, moves: &[String]) -> Vec<String> {
+    let mut chess = match chess_from_fen(fen) {
+        Ok(chess) => chess,
+        Err(_) => return vec![],
+    };
+    moves
+        .iter()
+        .map_while(|uci| {
+            let m = uci.parse::<UciMove>().ok()?.to_move(&chess).ok()?;
+            Some(SanPlus::from_move_and_play_unchecked(&mut chess, &m).to_string())
+        })
+        .collect()
+}
@@ -0,0 +1,187 @@
+//! Tactical puzzles mined from the games of a PGN
+//!
+//! Every position of a game's main line is searched twice with the same limits, as the move bench
+//! in `uci_bench` does: once for the best move, and once with `ignore_root_move` set to it for the
+//! second best. The position is a puzzle when exactly one move wins, scoring at least `decisive`
+//! while the second best does not, or exactly one move keeps the balance, scoring within `equal`
+//! of zero while the second best is worse than `-equal`. Either way the best move must also be at
+//! least `gap` ahead.
+//!
+//! The solution then follows the principal variation, and every later move of the solver must be
+//! unique in the same way unless it is the only legal move. A puzzle whose solution stops being
+//! unique is dropped. The line ends after `plies` plies, when the game is over, or when the
+//! principal variation runs out, always on a move of the solver.
+//!
+//! Puzzles are written as EPD with the best move as `bm`, the solution as `pv`, the score as `ce`
+//! and the gap as `c0`, so they can be added to the test positions in `mvm_test_fens` with the gap
+//! as the minimum score difference.
+
+use crate::annotate::search_position;
+use crate::datagen::game_result;
+use crate::fen::{algebraic_move_from_move, get_fen, try_get_position};
+use crate::make_move::make_move;
+use crate::moves::generate_legal_moves;
+use crate::pgn::{uci_to_san, PgnGame};
+use crate::search_limits::SearchLimits;
+use crate::types::{Move, Position, Score, SearchState, PV, WHITE};
+use crate::uci_command::{PuzzleParams, DEFAULT_PUZZLE_DEPTH};
+use crate::utils::hydrate_move_from_algebraic_move;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub fen: String,
+    /// The solution in SAN, starting with the best move and ending with a move of the solver
+    pub line: Vec<String>,
+    /// Score of the best move for the side to move
+    pub score: Score,
+    /// Centipawns between the best and second best moves
+    pub gap: Score,
+    pub id: String,
+}
+
+impl Puzzle {
+    pub fn epd(&self) -> String {
+        let fen: Vec<&str> = self.fen.split_whitespace().take(4).collect();
+        format!(
+            "{} bm {}; id \"{}\"; pv {}; ce {}; c0 \"gap {}\";",
+            fen.join(" "),
+            self.line[0],
+            self.id,
+            self.line.join(" "),
+            self.score,
+            self.gap
+        )
+    }
+}
+
+pub fn puzzle_limits(params: &PuzzleParams) -> SearchLimits {
+    let default_depth = if params.move_time.is_some() {
+        None
+    } else {
+        Some(DEFAULT_PUZZLE_DEPTH)
+    };
+    SearchLimits {
+        depth: params.depth.or(default_depth).unwrap_or(SearchLimits::default().depth),
+        hard_millis: params.move_time,
+        ..SearchLimits::default()
+    }
+}
+
+/// True if a best move scoring `best` is the only good move when the second best scores `second`
+pub fn is_unique(best: Score, second: Score, params: &PuzzleParams) -> bool {
+    let threshold = if best >= params.decisive { params.decisive } else { -params.equal };
+    best - second >= params.gap && second < threshold
+}
+
+/// The principal variation and score of the best move, with the score of the second best move, or
+/// `None` for it when the best move is the only legal one
+fn best_and_second(position: &mut Position, limits: &SearchLimits, search_state: &mut SearchState) -> (PV, Score, Option<Score>) {
+    let ((pv, best), _) = search_position(position, limits, None, search_state);
+    if generate_legal_moves(position).len() == 1 {
+        return (pv, best, None);
+    }
+    search_state.ignore_root_move = pv[0];
+    let ((_, second), _) = search_position(position, limits, None, search_state);
+    search_state.ignore_root_move = 0;
+    (pv, best, Some(second))
+}
+
+/// The solution from this position and its gap, if the position is a puzzle. `search_state.history`
+/// must end with the position and is left as it was.
+pub fn find_puzzle(position: &Position, params: &PuzzleParams, search_state: &mut SearchState) -> Option<(Vec<Move>, Score, Score)> {
+    let limits = puzzle_limits(params);
+    let history_length = search_state.history.len();
+    let solution = solve(*position, params, &limits, search_state);
+    search_state.history.truncate(history_length);
+    solution
+}
+
+fn solve(
+    mut position: Position,
+    params: &PuzzleParams,
+    limits: &SearchLimits,
+    search_state: &mut SearchState,
+) -> Option<(Vec<Move>, Score, Score)> {
+    if game_result(&position, &search_state.history).is_some() {
+        return None;
+    }
+    let (mut pv, score, second) = best_and_second(&mut position, limits, search_state);
+    let second = second?;
+    if !(score >= params.decisive || score.abs() <= params.equal) || !is_unique(score, second, params) {
+        return None;
+    }
+
+    let mut line = vec![];
+    let mut next = 0;
+    while line.len() < params.plies as usize && game_result(&position, &search_state.history).is_none() {
+        if next == 2 {
+            // The solver is to move again, after its move and the reply from the last search
+            let (next_pv, best, second) = best_and_second(&mut position, limits, search_state);
+            if second.is_some_and(|second| !is_unique(best, second, params)) {
+                return None;
+            }
+            pv = next_pv;
+            next = 0;
+        }
+        match pv.get(next) {
+            Some(&m) if m != 0 => {
+                let mut new_position = position;
+                make_move(&position, m, &mut new_position);
+                position = new_position;
+                search_state.history.push(position.zobrist_lock);
+                line.push(m);
+                next += 1;
+            }
+            _ => break,
+        }
+    }
+    if line.len().is_multiple_of(2) {
+        line.pop();
+    }
+    Some((line, score, score - second))
+}
+
+fn puzzle_id(game: &PgnGame, game_number: usize, position: &Position) -> String {
+    let event = game.tag("Event").filter(|e| !e.is_empty() && *e != "?").unwrap_or("Game");
+    let dots = if position.mover == WHITE { "." } else { "..." };
+    format!("{} {}, move {}{}", event, game_number, position.move_number, dots)
+}
+
+/// Every puzzle in the main line of a game. `game_number` only goes into the ids of the puzzles.
+pub fn game_puzzles(
+    game: &PgnGame,
+    game_number: usize,
+    params: &PuzzleParams,
+    search_state: &mut SearchState,
+) -> Result<Vec<Puzzle>, String> {
+    let mut position = try_get_position(game.start_fen()).map_err(|e| format!("Invalid FEN: {}", e))?;
+    let uci_moves = game.uci_moves()?;
+    search_state.history = vec![position.zobrist_lock];
+
+    let mut puzzles = vec![];
+    for uci in uci_moves.iter().map(Some).chain([None]) {
+        if let Some((line, score, gap)) = find_puzzle(&position, params, search_state) {
+            let fen = get_fen(&position);
+            let line: Vec<String> = line.iter().map(|m| algebraic_move_from_move(*m)).collect();
+            puzzles.push(Puzzle {
+                line: uci_to_san(&fen, &line),
+                fen,
+                score,
+                gap,
+                id: puzzle_id(game, game_number, &position),
+            });
+        }
+
+        if let Some(uci) = uci {
+            let mut new_position = position;
+            make_move(
+                &position,
+                hydrate_move_from_algebraic_move(&position, uci.clone()),
+                &mut new_position,
+            );
+            position = new_position;
+            search_state.history.push(position.zobrist_lock);
+        }
+    }
+    Ok(puzzles)
+}
@@ -1,4 +1,4 @@
-use crate::bitboards::{bit, epsbit, KING_MOVES_BITBOARDS, PAWN_MOVES_CAPTURE};
+use crate::bitboards::{bit, epsbit, KING_MOVES_BITBOARDS, PAWN_MOVES_CAPTURE, PAWN_MOVES_FORWARD, RANK_2_BITS, RANK_7_BITS};
 use crate::engine_constants::{PAWN_VALUE_AVERAGE, QUEEN_VALUE_AVERAGE};
 use crate::evaluate::evaluate_with_pawn_hash;
 use crate::make_move::{make_move_in_place, unmake_move};
@@ -7,11 +7,15 @@ use crate::move_constants::{
     PROMOTION_QUEEN_MOVE_MASK, PROMOTION_SQUARES,
 };
 use crate::move_scores::{attacker_bonus, piece_value, PAWN_ATTACKER_BONUS};
-use crate::moves::{generate_diagonal_slider_moves, generate_knight_moves, generate_straight_slider_moves, is_check};
+use crate::moves::{
+    generate_check_evasions, generate_diagonal_slider_moves, generate_knight_moves, generate_straight_slider_moves, is_check,
+};
+use crate::search::{store_hash_entry, MATE_SCORE, MATE_START};
 use crate::see::{captured_piece_value_see, see};
+use crate::types::BoundType::{Exact, Lower, Upper};
 use crate::types::{
-    is_stopped, pv_single, set_stop, Bitboard, Move, MoveList, MoveScoreList, PathScore, Pieces, Position, Score, SearchState, Square,
-    Window, BLACK, WHITE,
+    is_stopped, pv_single, set_stop, Bitboard, BoundType, HashEntry, Move, MoveList, MoveScore, MoveScoreList, PathScore, Pieces, Position,
+    Score, SearchState, Square, Window, BLACK, WHITE,
 };
 use crate::utils::{from_square_mask, send_info, to_square_part};
 use crate::{add_moves, check_time, get_and_unset_lsb, opponent};
@@ -122,17 +126,60 @@ pub fn quiesce(position: &mut Position, depth: u8, ply: u8, window: Window, sear
         return (pv_single(0), 0);
     }
     search_state.nodes += 1;
+    search_state.stats.qsearch_nodes += 1;
+    search_state.seldepth = search_state.seldepth.max(ply);
+
+    // Any stored result will do here, since quiescence nodes are stored with a height of zero.
+    // Mate scores are adjusted for the current ply as in the main search.
+    let index: usize = (position.zobrist_lock % search_state.hash_table.len() as u128) as usize;
+    let hash_entry = *search_state.hash_table.get(index);
+    let hash_move = if hash_entry.lock == position.zobrist_lock {
+        let score = match hash_entry.score {
+            s if s > MATE_START => s - ply as Score,
+            s if s < -MATE_START => s + ply as Score,
+            s => s,
+        };
+        match hash_entry.bound {
+            Exact => return (pv_single(hash_entry.mv), score.clamp(window.0, window.1)),
+            Lower if score >= window.1 => return (pv_single(hash_entry.mv), window.1),
+            Upper if score <= window.0 => return (pv_single(0), window.0),
+            _ => {}
+        }
+        hash_entry.mv
+    } else {
+        0
+    };
+
+    if depth == 0 {
+        return (
+            pv_single(0),
+            evaluate_with_pawn_hash(position, &search_state.pawn_hash_table, &search_state.material_hash_table),
+        );
+    }
 
-    let eval = evaluate_with_pawn_hash(position, &search_state.pawn_hash_table);
+    let in_check = is_check(position, position.mover);
 
-    if depth == 0 || eval >= window.1 {
+    // When in check there is no standing pat: every evasion is searched, and having none is mate
+    let eval = if in_check {
+        -MATE_SCORE + ply as Score
+    } else {
+        evaluate_with_pawn_hash(position, &search_state.pawn_hash_table, &search_state.material_hash_table)
+    };
+
+    if eval >= window.1 {
         return (pv_single(0), eval);
     }
 
     let mut alpha = window.0.max(eval);
     let mut best_move: Move = 0;
 
-    let ms = quiesce_moves(position);
+    let ms = if in_check {
+        generate_check_evasions(position)
+    } else {
+        let mut ms = quiesce_moves(position);
+        generate_quiet_queen_promotions(position, &mut ms);
+        ms
+    };
 
     // If there are no legal moves, return the evaluation score
     if ms.is_empty() {
@@ -142,11 +189,15 @@ pub fn quiesce(position: &mut Position, depth: u8, ply: u8, window: Window, sear
     let mut move_scores: MoveScoreList = vec![];
 
     for &m in &ms {
-        let score = score_quiesce_move(position, m, &position.pieces[opponent!(position.mover) as usize], search_state);
+        let score = if m == hash_move {
+            Score::MAX
+        } else {
+            score_quiesce_move(position, m, &position.pieces[opponent!(position.mover) as usize], search_state)
+        };
         move_scores.push((m, score));
     }
 
-    move_scores.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+    move_scores.sort_unstable_by_key(|ms| std::cmp::Reverse(ms.1));
 
     // Delta pruning margin: skip captures that can't raise alpha
     // even with full captured piece value plus this margin
@@ -156,24 +207,25 @@ pub fn quiesce(position: &mut Position, depth: u8, ply: u8, window: Window, sear
         let see_value = captured_piece_value_see(position, m);
 
         // Delta pruning: if capturing this piece can't possibly raise alpha, skip it
-        if eval + see_value + DELTA_MARGIN < alpha {
+        if !in_check && eval + see_value + DELTA_MARGIN < alpha {
             continue;
         }
 
         let old_mover = position.mover;
         let unmake = make_move_in_place(position, m);
 
-        if !is_check(position, old_mover) && see(see_value, bit(to_square_part(m)), position) > 0 {
+        if !is_check(position, old_mover) && (in_check || see(see_value, bit(to_square_part(m)), position) > 0) {
             let score = -quiesce(position, depth - 1, ply + 1, (-window.1, -alpha), search_state).1;
 
             unmake_move(position, m, &unmake);
 
             check_time!(search_state);
             if is_stopped(&search_state.stop) {
-                break;
+                return (pv_single(best_move), alpha);
             }
 
             if score >= window.1 {
+                store_quiesce_hash_entry(position, &hash_entry, Lower, (m, window.1), search_state, ply);
                 return (pv_single(m), window.1);
             }
             if score > alpha {
@@ -185,5 +237,39 @@ pub fn quiesce(position: &mut Position, depth: u8, ply: u8, window: Window, sear
         }
     }
 
+    let bound = if alpha > window.0 { Exact } else { Upper };
+    store_quiesce_hash_entry(position, &hash_entry, bound, (best_move, alpha), search_state, ply);
+
     (pv_single(best_move), alpha)
 }
+
+/// Store a quiescence result with a height of zero, so that it only replaces other quiescence
+/// results or entries left over from earlier searches
+#[inline(always)]
+fn store_quiesce_hash_entry(
+    position: &Position,
+    existing: &HashEntry,
+    bound: BoundType,
+    movescore: MoveScore,
+    search_state: &mut SearchState,
+    ply: u8,
+) {
+    store_hash_entry(position, 0, existing.height, existing.version, bound, movescore, search_state, ply);
+}
+
+/// Add pawn pushes to the promotion rank, as queen promotions only
+#[inline(always)]
+fn generate_quiet_queen_promotions(position: &Position, move_list: &mut MoveList) {
+    let colour_index = position.mover as usize;
+    let empty_squares = !(position.pieces[WHITE as usize].all_pieces_bitboard | position.pieces[BLACK as usize].all_pieces_bitboard);
+    let promoting_rank = if position.mover == WHITE { RANK_7_BITS } else { RANK_2_BITS };
+    let mut from_squares = position.pieces[colour_index].pawn_bitboard & promoting_rank;
+
+    while from_squares != 0 {
+        let from_square = get_and_unset_lsb!(from_squares);
+        let to_bitboard = PAWN_MOVES_FORWARD[colour_index][from_square as usize] & empty_squares;
+        if to_bitboard != 0 {
+            move_list.push(from_square_mask(from_square) | to_bitboard.trailing_zeros() as Move | PROMOTION_QUEEN_MOVE_MASK);
+        }
+    }
+}
@@ -16,19 +16,26 @@ use crate::move_constants::{
     EN_PASSANT_NOT_AVAILABLE, PIECE_MASK_BISHOP, PIECE_MASK_FULL, PIECE_MASK_KING, PIECE_MASK_KNIGHT, PIECE_MASK_PAWN, PIECE_MASK_QUEEN,
     PIECE_MASK_ROOK, PROMOTION_FULL_MOVE_MASK,
 };
+use crate::move_picker::MovePicker;
 use crate::move_scores::score_move;
-use crate::moves::{generate_captures, generate_check_evasions, generate_moves, generate_quiet_moves, is_check, verify_move};
+use crate::moves::{generate_captures, generate_legal_moves, is_check, verify_move};
 use crate::opponent;
 use crate::quiesce::quiesce;
+use crate::search_features::{
+    ALPHA_PRUNING, BETA_PRUNING, LATE_MOVE_PRUNING, LATE_MOVE_REDUCTIONS, MULTICUT, NULL_MOVE, PASSED_PAWN_EXTENSION, PAWN_PUSH_EXTENSION,
+    PROBCUT, SEE_PRUNING, THREAT_DETECTION,
+};
+use crate::search_limits::DETERMINISTIC_NODES_PER_MILLI;
 use crate::see::static_exchange_evaluation;
+use crate::time_management::DOMINANT_MOVE_MARGIN;
 use crate::types::BoundType::{Exact, Lower, Upper};
 use crate::types::{
     is_stopped, pv_prepend, pv_single, set_stop, BoundType, HashEntry, Move, MoveScore, MoveScoreArray, MoveScoreList, Mover, PathScore,
-    Position, Score, SearchState, Square, Window, BLACK, WHITE,
+    Position, Score, SearchState, SearchStats, Square, Window, BLACK, WHITE,
 };
-use crate::utils::{captured_piece_value, from_square_part, send_info, to_square_part};
+use crate::utils::{captured_piece_value, from_square_part, send_currmove_info, send_info, send_line_info, to_square_part, uci_score};
 use std::cmp::{max, min};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub const MAX_WINDOW: Score = 20000;
 pub const MATE_SCORE: Score = 10000;
@@ -37,6 +44,34 @@ pub const MATE_START: Score = MATE_SCORE - MATE_MARGIN;
 
 pub const LAST_EXTENSION_LAYER: u8 = 4;
 
+/// Call a `search_trace` function when built with the `search-trace` feature, otherwise nothing
+macro_rules! trace {
+    ($function:ident($($arg:expr),*)) => {
+        #[cfg(feature = "search-trace")]
+        {
+            #[allow(unused_imports)]
+            use crate::search_trace::*;
+            $function($($arg),*);
+        }
+    };
+}
+
+/// The lowest root score that proves a mate in `moves` moves or fewer
+pub fn mate_score_threshold(moves: u8) -> Score {
+    MATE_SCORE - (2 * moves as Score - 1)
+}
+
+/// Number of moves to mate for a mate score, negative when the side to move is being mated
+pub fn mate_in_moves(score: Score) -> Option<Score> {
+    if score > MATE_START {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_START {
+        Some(-(MATE_SCORE + score) / 2)
+    } else {
+        None
+    }
+}
+
 pub const MAX_NEW_EXTENSIONS_TREE_PART: [u8; 5] = [1, 0, 0, 0, 0];
 
 #[macro_export]
@@ -52,7 +87,7 @@ macro_rules! time_expired {
         if is_stopped(&$search_state.stop) || Instant::now() >= $search_state.end_time {
             if !is_stopped(&$search_state.stop) {
                 set_stop(&$search_state.stop, true);
-                send_info($search_state, false);
+                send_info($search_state);
             }
             true
         } else {
@@ -67,7 +102,7 @@ macro_rules! check_time {
         if !is_stopped(&$search_state.stop) && $search_state.nodes % 1000 == 0 {
             if $search_state.end_time < Instant::now() || $search_state.nodes >= $search_state.nodes_limit {
                 set_stop(&$search_state.stop, true);
-                send_info($search_state, false);
+                send_info($search_state);
             }
         }
     };
@@ -83,12 +118,20 @@ macro_rules! debug_out {
 }
 
 pub fn iterative_deepening(position: &mut Position, max_depth: u8, search_state: &mut SearchState) -> Move {
+    search_state.stats = SearchStats::default();
+    let mv = deepen(position, max_depth, search_state);
+    *search_state.last_search_stats.lock().unwrap() = search_state.stats;
+    mv
+}
+
+fn deepen(position: &mut Position, max_depth: u8, search_state: &mut SearchState) -> Move {
     search_state.start_time = Instant::now();
     set_stop(&search_state.stop, false);
     search_state.hash_table_version += 1;
+    search_state.tb_hits = 0;
+    search_state.root_material = total_material_value(position);
 
-    let original_mover = position.mover;
-    let all_moves = generate_moves(position);
+    let all_moves = generate_legal_moves(position);
     let mut legal_moves: MoveScoreList = Vec::with_capacity(all_moves.len());
 
     for m in all_moves {
@@ -101,19 +144,23 @@ pub fn iterative_deepening(position: &mut Position, max_depth: u8, search_state:
                 continue;
             }
         }
-        let unmake = make_move_in_place(position, m);
-        let legal = !is_check(position, original_mover);
-        unmake_move(position, m, &unmake);
-        if legal {
-            legal_moves.push((m, -MATE_SCORE));
-        }
+        legal_moves.push((m, -MATE_SCORE));
     }
 
+    // Nothing from an earlier search may be left for callers that read the result of this one
+    search_state.pv_lines.clear();
+
     // No legal moves = checkmate or stalemate, return null move
     if legal_moves.is_empty() {
+        let score = if is_check(position, position.mover) { -MATE_SCORE } else { 0 };
+        search_state.current_best = (pv_single(0), score);
         return 0;
     }
 
+    // Initialize with the first legal move so we always have a valid move to return
+    // even if time expires before the first search iteration completes
+    search_state.current_best = (pv_single(legal_moves[0].0), -MATE_SCORE);
+
     // Tablebase probe at root: if ≤6 pieces, return best move immediately
     // The tablebase knows the perfect result - no need to search
     let all_pieces = position.pieces[WHITE as usize].all_pieces_bitboard | position.pieces[BLACK as usize].all_pieces_bitboard;
@@ -125,6 +172,7 @@ pub fn iterative_deepening(position: &mut Position, max_depth: u8, search_state:
             let unmake = make_move_in_place(position, *m);
             // Probe DTZ after the move (from opponent's perspective, so negate score)
             if let Some(tb_score) = probe_dtz(position) {
+                search_state.tb_hits += 1;
                 let score = -tb_score;
                 if score > best_score {
                     best_score = score;
@@ -136,79 +184,182 @@ pub fn iterative_deepening(position: &mut Position, max_depth: u8, search_state:
 
         // If we found a valid TB move, return it immediately
         if best_move != 0 {
-            println!("info depth 1 score cp {} pv {}", best_score, algebraic_move_from_move(best_move));
+            search_state.current_best = (pv_single(best_move), best_score);
+            search_state.pv_lines = vec![search_state.current_best.clone()];
+            println!(
+                "info depth 1 score {} tbhits {} pv {}",
+                uci_score(best_score),
+                search_state.tb_hits,
+                algebraic_move_from_move(best_move)
+            );
             return best_move;
         }
     }
 
     clear_history_table(search_state);
     clear_killers(search_state);
+    if search_state.deterministic {
+        clear_search_memory(search_state);
+    }
+    search_state.time_manager.start_search(legal_moves.len());
 
     if search_state.history.is_empty() {
         search_state.history.push(position.zobrist_lock)
     }
 
-    let mut aspiration_window = (-MAX_WINDOW, MAX_WINDOW);
-
-    // Initialize with the first legal move so we always have a valid move to return
-    // even if time expires before the first search iteration completes
-    search_state.current_best = (pv_single(legal_moves[0].0), -MATE_SCORE);
-
-    const ASPIRATION_RADIUS: [Score; 6] = [25, 50, 100, 200, 400, 800];
+    // Each MultiPV line keeps its own aspiration window from one iteration to the next
+    let line_count = min(search_state.multi_pv as usize, legal_moves.len());
+    let mut aspiration_windows: Vec<Window> = vec![(-MAX_WINDOW, MAX_WINDOW); line_count];
 
     for iterative_depth in 1..=max_depth {
         //println!("Iterative depth {}", iterative_depth);
-        let mut c = 0;
         search_state.iterative_depth = iterative_depth;
+        search_state.seldepth = 0;
+
+        if let Some(mate_moves) = search_state.mate_search {
+            // Fail-high-only window from just below the required mate score up to MAX_WINDOW, so a
+            // root move either proves a mate within the limit or fails low. Beta and alpha pruning
+            // switch themselves off with the bounds at mate scores.
+            let mate_window = (mate_score_threshold(mate_moves) - 1, MAX_WINDOW);
+            let mate_best = start_search(position, &mut legal_moves, search_state, mate_window, 1);
+            if time_expired!(search_state) {
+                return search_state.current_best.0[0];
+            }
+            legal_moves.sort_by(|(_, a), (_, b)| b.cmp(a));
+            legal_moves = legal_moves.into_iter().map(|m| (m.0, -MATE_SCORE)).collect();
+            search_state.root_moves = legal_moves.clone();
+            if mate_best.1 > mate_window.0 {
+                search_state.current_best = mate_best.clone();
+                search_state.pv_lines = vec![mate_best];
+                send_info(search_state);
+                return search_state.current_best.0[0];
+            }
+            continue;
+        }
 
-        loop {
-            //println!("Searching with aspiration window {} {} at [{}]", aspiration_window.0, aspiration_window.1, c);
-            let aspire_best = start_search(position, &mut legal_moves, search_state, aspiration_window);
+        // Each line is searched with the moves of the lines before it taken out, so that every
+        // line gets an exact score rather than a bound from a null window search
+        let mut remaining_moves = legal_moves.clone();
+        let mut pv_lines: Vec<PathScore> = Vec::with_capacity(line_count);
+        for (line, aspiration_window) in aspiration_windows.iter_mut().enumerate() {
+            let line_best = aspiration_search(position, &mut remaining_moves, search_state, aspiration_window, line as u8 + 1);
             if time_expired!(search_state) {
                 return search_state.current_best.0[0];
             }
+            if line == 0 {
+                search_state.current_best = line_best.clone();
+            }
+            *aspiration_window = (line_best.1 - ASPIRATION_RADIUS[0], line_best.1 + ASPIRATION_RADIUS[0]);
 
-            if aspire_best.1 > aspiration_window.0 && aspire_best.1 < aspiration_window.1 {
-                //println!("Found a move within the aspiration window {} {}", algebraic_move_from_move(aspire_best.0[0]), aspire_best.1);
-                search_state.current_best = aspire_best;
-                //println!("Current best move is {} {}", algebraic_move_from_move(search_state.current_best.0[0]), search_state.current_best.1);
-                break;
-            } else {
-                //println!("Move score was outside the aspiration window {} {} {} {}", aspire_best.1, aspiration_window.0, aspiration_window.1, c);
-                c += 1;
-                if c == ASPIRATION_RADIUS.len() {
-                    aspiration_window = (-MAX_WINDOW, MAX_WINDOW);
-                } else if aspire_best.1 <= aspiration_window.0 {
-                    aspiration_window.0 = max(-MAX_WINDOW, aspiration_window.0 - ASPIRATION_RADIUS[c]);
-                } else if aspire_best.1 >= aspiration_window.1 {
-                    aspiration_window.1 = min(MAX_WINDOW, aspiration_window.1 + ASPIRATION_RADIUS[c]);
-                }
-                //println!("New aspiration window {} {}", aspiration_window.0, aspiration_window.1);
-            };
+            remaining_moves.sort_by(|(_, a), (_, b)| b.cmp(a));
+            remaining_moves.retain(|m| m.0 != line_best.0[0]);
+            pv_lines.push(line_best);
         }
 
-        legal_moves.sort_by(|(_, a), (_, b)| b.cmp(a));
-
-        legal_moves = legal_moves.into_iter().map(|m| (m.0, -MATE_SCORE)).collect();
+        // Order the next iteration by the lines found, then the other moves by their scores
+        legal_moves = pv_lines
+            .iter()
+            .map(|line| line.0[0])
+            .chain(remaining_moves.iter().map(|m| m.0))
+            .map(|m| (m, -MATE_SCORE))
+            .collect();
         search_state.root_moves = legal_moves.clone();
+        search_state.pv_lines = pv_lines;
 
-        if search_state.multi_pv == 1 {
-            aspiration_window = (
-                search_state.current_best.1 - ASPIRATION_RADIUS[0],
-                search_state.current_best.1 + ASPIRATION_RADIUS[0],
-            );
-        }
+        send_info(search_state);
 
-        send_info(search_state, true);
+        search_state
+            .time_manager
+            .update(search_state.current_best.0[0], search_state.current_best.1);
+        if search_state.time_manager.wants_second_best(search_elapsed(search_state)) {
+            let second_best = match search_state.pv_lines.get(1) {
+                Some(line) => line.1,
+                None => second_best_bound(position, &legal_moves, search_state),
+            };
+            if time_expired!(search_state) {
+                break;
+            }
+            search_state.time_manager.update_second_best(second_best);
+        }
+        if !search_state.time_manager.should_start_iteration(search_elapsed(search_state)) {
+            break;
+        }
     }
 
     legal_moves[0].0
 }
 
-pub fn start_search(position: &mut Position, legal_moves: &mut MoveScoreList, search_state: &mut SearchState, window: Window) -> PathScore {
+/// An upper bound on the score of the best root move other than the first, from a reduced depth
+/// null window search at the margin the first move needs to dominate. The search stops at the
+/// first move to reach that margin, and its score is returned instead.
+fn second_best_bound(position: &mut Position, legal_moves: &MoveScoreList, search_state: &mut SearchState) -> Score {
+    let threshold = search_state.current_best.1 - DOMINANT_MOVE_MARGIN;
+    let depth = max(1, search_state.iterative_depth / 2);
+    let mut second_best = -MATE_SCORE;
+
+    for (m, _) in legal_moves.iter().skip(1) {
+        trace!(next_move(*m));
+        let unmake = make_move_in_place(position, *m);
+        search_state.history.push(position.zobrist_lock);
+        let score = -search(position, depth - 1, 1, (-threshold, -threshold + 1), search_state, false).1;
+        search_state.history.pop();
+        unmake_move(position, *m, &unmake);
+
+        second_best = max(second_best, score);
+        if second_best >= threshold || time_expired!(search_state) {
+            break;
+        }
+    }
+    second_best
+}
+
+const ASPIRATION_RADIUS: [Score; 6] = [25, 50, 100, 200, 400, 800];
+
+/// Search the root moves for one MultiPV line, widening the aspiration window on the side that
+/// failed until the best score falls inside it. The window is left where the search settled.
+fn aspiration_search(
+    position: &mut Position,
+    legal_moves: &mut MoveScoreList,
+    search_state: &mut SearchState,
+    aspiration_window: &mut Window,
+    line: u8,
+) -> PathScore {
+    let mut c = 0;
+    loop {
+        let aspire_best = start_search(position, legal_moves, search_state, *aspiration_window, line);
+        if time_expired!(search_state) || (aspire_best.1 > aspiration_window.0 && aspire_best.1 < aspiration_window.1) {
+            return aspire_best;
+        }
+
+        let bound = if aspire_best.1 <= aspiration_window.0 {
+            "upperbound"
+        } else {
+            "lowerbound"
+        };
+        send_line_info(search_state, line, &aspire_best, Some(bound));
+        c += 1;
+        if c == ASPIRATION_RADIUS.len() {
+            *aspiration_window = (-MAX_WINDOW, MAX_WINDOW);
+        } else if aspire_best.1 <= aspiration_window.0 {
+            aspiration_window.0 = max(-MAX_WINDOW, aspiration_window.0 - ASPIRATION_RADIUS[c]);
+        } else if aspire_best.1 >= aspiration_window.1 {
+            aspiration_window.1 = min(MAX_WINDOW, aspiration_window.1 + ASPIRATION_RADIUS[c]);
+        }
+    }
+}
+
+pub fn start_search(
+    position: &mut Position,
+    legal_moves: &mut MoveScoreList,
+    search_state: &mut SearchState,
+    window: Window,
+    line: u8,
+) -> PathScore {
     let mut current_best: PathScore = (pv_single(legal_moves[0].0), window.0);
 
-    for mv in legal_moves {
+    for (move_number, mv) in legal_moves.iter_mut().enumerate() {
+        send_currmove_info(search_state, mv.0, move_number + 1);
+        trace!(next_move(mv.0));
         let unmake = make_move_in_place(position, mv.0);
         prefetch_hash(position, search_state); // Prefetch child position's hash entry
         search_state.history.push(position.zobrist_lock);
@@ -227,11 +378,10 @@ pub fn start_search(position: &mut Position, legal_moves: &mut MoveScoreList, se
         unmake_move(position, mv.0, &unmake);
         mv.1 = path_score.1;
 
-        search_state.pv.insert(mv.0, (pv_prepend(mv.0, &path_score.0), mv.1));
-
         if mv.1 > current_best.1 && time_remains!(search_state.end_time) {
             current_best = (pv_prepend(mv.0, &path_score.0), mv.1);
-            send_info(search_state, false);
+            let bound = if mv.1 >= window.1 { Some("lowerbound") } else { None };
+            send_line_info(search_state, line, &current_best, bound);
         }
 
         if time_expired!(search_state) {
@@ -250,6 +400,24 @@ fn clear_killers(search_state: &mut SearchState) {
     }
 }
 
+/// Forget everything earlier searches left behind, so that a search depends only on its position and limits
+fn clear_search_memory(search_state: &mut SearchState) {
+    search_state.hash_table.clear();
+    search_state.pawn_hash_table.clear();
+    search_state.material_hash_table.clear();
+    search_state.countermoves = [[0; 64]; 12];
+    search_state.ply_move = [0; MAX_DEPTH as usize];
+}
+
+/// Time spent on this search, or the time the nodes searched stand for in deterministic mode
+fn search_elapsed(search_state: &SearchState) -> Duration {
+    if search_state.deterministic {
+        Duration::from_millis(search_state.nodes / DETERMINISTIC_NODES_PER_MILLI)
+    } else {
+        search_state.start_time.elapsed()
+    }
+}
+
 fn clear_history_table(search_state: &mut SearchState) {
     for piece in &mut search_state.history_moves {
         for from_sq in piece {
@@ -293,7 +461,7 @@ pub fn store_hash_entry(
 }
 
 #[inline(always)]
-fn total_material_value(position: &Position) -> Score {
+pub fn total_material_value(position: &Position) -> Score {
     let piece_material = piece_material(position, WHITE) + piece_material(position, BLACK);
     let pawn_material = pawn_material(position, WHITE) + pawn_material(position, BLACK);
     piece_material + pawn_material
@@ -405,6 +573,26 @@ pub fn search(
     window: Window,
     search_state: &mut SearchState,
     on_null_move: bool,
+) -> PathScore {
+    #[cfg(feature = "search-trace")]
+    {
+        crate::search_trace::enter_node(position, depth, ply, window);
+        let path_score = search_node(position, depth, ply, window, search_state, on_null_move);
+        crate::search_trace::leave_node(path_score.1);
+        path_score
+    }
+    #[cfg(not(feature = "search-trace"))]
+    search_node(position, depth, ply, window, search_state, on_null_move)
+}
+
+#[inline(always)]
+fn search_node(
+    position: &mut Position,
+    depth: u8,
+    ply: u8,
+    window: Window,
+    search_state: &mut SearchState,
+    on_null_move: bool,
 ) -> PathScore {
     // Check stop flag at TOP before any moves are made - safe to return here
     if is_stopped(&search_state.stop) {
@@ -418,6 +606,7 @@ pub fn search(
 
     if is_draw(position, search_state, ply) {
         search_state.nodes += 1;
+        search_state.stats.nodes += 1;
         return (pv_single(0), draw_value(position, search_state));
     }
 
@@ -433,6 +622,8 @@ pub fn search(
     }
 
     search_state.nodes += 1;
+    search_state.stats.nodes += 1;
+    search_state.seldepth = max(search_state.seldepth, ply);
 
     let mut alpha = window.0;
     let mut beta = window.1;
@@ -445,6 +636,7 @@ pub fn search(
 
     let index: usize = (position.zobrist_lock % search_state.hash_table.len() as u128) as usize;
     let hash_entry = search_state.hash_table.get(index);
+    search_state.stats.hash_probes += 1;
     let mut hash_move = if hash_entry.lock == position.zobrist_lock {
         // Adjust any mate score so that the score appears calculated from the current root rather than the root when the position was stored
         // When we found the mate, we set the score to reflect the distance from the root, and then, when we stored the score in the TT, we
@@ -463,8 +655,14 @@ pub fn search(
         if hash_entry.height >= depth {
             hash_height = hash_entry.height;
             hash_version = hash_entry.version;
+            match hash_entry.bound {
+                Exact => search_state.stats.hash_hits_exact += 1,
+                Lower => search_state.stats.hash_hits_lower += 1,
+                Upper => search_state.stats.hash_hits_upper += 1,
+            }
             if hash_entry.bound == Exact {
                 search_state.hash_hits_exact += 1;
+                trace!(event(TraceEvent::HashCutoff { score }));
                 return (pv_single(hash_entry.mv), score);
             }
             if hash_entry.bound == Lower && score > alpha {
@@ -474,6 +672,7 @@ pub fn search(
                 beta = score
             }
             if alpha >= beta {
+                trace!(event(TraceEvent::HashCutoff { score }));
                 return (pv_single(hash_entry.mv), score);
             }
         }
@@ -486,33 +685,43 @@ pub fn search(
     };
 
     let in_check = is_check(position, position.mover);
+    let features = search_state.search_features;
 
     let mut lazy_eval: Score = -Score::MAX;
 
-    if scouting && depth <= BETA_PRUNE_MAX_DEPTH && !in_check && beta.abs() < MATE_START {
-        lazy_eval = evaluate_with_pawn_hash(position, &search_state.pawn_hash_table);
+    if features.enabled(BETA_PRUNING) && scouting && depth <= BETA_PRUNE_MAX_DEPTH && !in_check && beta.abs() < MATE_START {
+        lazy_eval = evaluate_with_pawn_hash(position, &search_state.pawn_hash_table, &search_state.material_hash_table);
         let margin = BETA_PRUNE_MARGIN_PER_DEPTH * depth as Score;
         if lazy_eval - margin as Score >= beta {
+            trace!(event(TraceEvent::BetaPrune { eval: lazy_eval, margin }));
             return (pv_single(0), lazy_eval - margin);
         }
     }
 
-    let alpha_prune_flag = if depth <= ALPHA_PRUNE_MARGINS.len() as u8 && scouting && !in_check && alpha.abs() < MATE_START {
-        if lazy_eval == -Score::MAX {
-            lazy_eval = evaluate_with_pawn_hash(position, &search_state.pawn_hash_table);
-        }
+    let alpha_prune_flag =
+        if features.enabled(ALPHA_PRUNING) && depth <= ALPHA_PRUNE_MARGINS.len() as u8 && scouting && !in_check && alpha.abs() < MATE_START
+        {
+            if lazy_eval == -Score::MAX {
+                lazy_eval = evaluate_with_pawn_hash(position, &search_state.pawn_hash_table, &search_state.material_hash_table);
+            }
 
-        lazy_eval + ALPHA_PRUNE_MARGINS[depth as usize - 1] < alpha
-    } else {
-        false
-    };
+            lazy_eval + ALPHA_PRUNE_MARGINS[depth as usize - 1] < alpha
+        } else {
+            false
+        };
+    if alpha_prune_flag {
+        trace!(event(TraceEvent::AlphaPruneNode { eval: lazy_eval }));
+    }
 
     // Threat detection: when null move fails badly, opponent has a dangerous threat
     // We'll use this to reduce LMR aggressiveness rather than extending
     let mut threat_detected = false;
 
-    if !on_null_move && scouting && depth >= NULL_MOVE_MIN_DEPTH && null_move_material(position) && !in_check {
+    if features.enabled(NULL_MOVE) && !on_null_move && scouting && depth >= NULL_MOVE_MIN_DEPTH && null_move_material(position) && !in_check
+    {
         let old_ep = make_null_move(position);
+        search_state.stats.null_move_attempts += 1;
+        trace!(next_label("null"));
 
         let score = -search(
             position,
@@ -531,19 +740,22 @@ pub fn search(
         }
 
         if score >= beta {
+            trace!(event(TraceEvent::NullMoveCutoff { score }));
+            search_state.stats.null_move_cutoffs += 1;
             return (pv_single(0), beta);
         }
 
         // If null move fails significantly below alpha, opponent has a threat
         // Use higher threshold (400 = losing a piece) to be selective
-        if score < alpha - THREAT_EXTENSION_MARGIN {
+        if features.enabled(THREAT_DETECTION) && score < alpha - THREAT_EXTENSION_MARGIN {
+            trace!(event(TraceEvent::NullMoveThreat { score }));
             threat_detected = true;
         }
     }
 
     // Probcut: at high depth, do a shallow search with raised beta
     // If a capture fails high, the position is probably winning and can be cut
-    if scouting && !in_check && depth >= PROBCUT_MIN_DEPTH && beta.abs() < MATE_START {
+    if features.enabled(PROBCUT) && scouting && !in_check && depth >= PROBCUT_MIN_DEPTH && beta.abs() < MATE_START {
         let probcut_beta = beta + PROBCUT_MARGIN;
         let probcut_depth = depth - PROBCUT_DEPTH_REDUCTION;
 
@@ -560,6 +772,7 @@ pub fn search(
             prefetch_hash(position, search_state);
 
             if !is_check(position, old_mover) {
+                trace!(next_move(m));
                 let score = -search(
                     position,
                     probcut_depth,
@@ -577,6 +790,8 @@ pub fn search(
                 }
 
                 if score >= probcut_beta {
+                    trace!(event(TraceEvent::ProbCut { mv: m, score }));
+                    search_state.stats.probcut_cutoffs += 1;
                     return (pv_single(0), beta);
                 }
             } else {
@@ -587,7 +802,7 @@ pub fn search(
 
     // Multi-cut: at high depth, if multiple moves fail high at shallow depth,
     // the position is probably good and can be cut
-    if scouting && !in_check && depth >= MULTICUT_MIN_DEPTH && beta.abs() < MATE_START {
+    if features.enabled(MULTICUT) && scouting && !in_check && depth >= MULTICUT_MIN_DEPTH && beta.abs() < MATE_START {
         let multicut_depth = depth - MULTICUT_DEPTH_REDUCTION;
         let mut fail_high_count: u8 = 0;
 
@@ -600,7 +815,7 @@ pub fn search(
             .collect();
 
         // Sort by score descending to try best captures first
-        scored_captures.sort_by(|a, b| b.1.cmp(&a.1));
+        scored_captures.sort_by_key(|ms| std::cmp::Reverse(ms.1));
 
         for (m, _) in scored_captures.iter().take(MULTICUT_MOVES_TO_TRY as usize) {
             let old_mover = position.mover;
@@ -608,6 +823,7 @@ pub fn search(
             prefetch_hash(position, search_state);
 
             if !is_check(position, old_mover) {
+                trace!(next_move(*m));
                 let score = -search(position, multicut_depth, ply + 1, (-beta, -beta + 1), search_state, false).1;
 
                 unmake_move(position, *m, &unmake);
@@ -619,6 +835,8 @@ pub fn search(
                 if score >= beta {
                     fail_high_count += 1;
                     if fail_high_count >= MULTICUT_REQUIRED_CUTOFFS {
+                        trace!(event(TraceEvent::MultiCut { cutoffs: fail_high_count }));
+                        search_state.stats.multicut_cutoffs += 1;
                         return (pv_single(0), beta);
                     }
                 }
@@ -633,14 +851,20 @@ pub fn search(
     // Check extension: extend by 1 ply when in check
     let check_extension: u8 = if in_check && ply < search_state.iterative_depth * 2 { 1 } else { 0 };
     let real_depth = depth + check_extension;
+    if check_extension > 0 {
+        trace!(event(TraceEvent::CheckExtension));
+    }
 
     let verified_hash_move = if !scouting && hash_move == 0 && depth + check_extension > IID_MIN_DEPTH {
+        trace!(next_label("iid"));
         hash_move = search_wrapper(depth - IID_REDUCE_DEPTH, ply, search_state, (-alpha - 1, -alpha), position, 0).0[0];
         hash_move != 0
     } else {
         hash_move != 0 && verify_move(position, hash_move)
     };
 
+    search_state.stats.expanded_nodes += 1;
+
     // Try hash move first if valid
     if verified_hash_move {
         let old_mover = position.mover;
@@ -650,6 +874,8 @@ pub fn search(
 
         if !is_check(position, old_mover) {
             legal_move_count += 1;
+            trace!(next_move(hash_move));
+            search_state.stats.moves_searched += 1;
             let path_score = search_wrapper(real_depth, ply, search_state, (-beta, -alpha), position, 0);
             let score = path_score.1;
             let singular_depth = real_depth;
@@ -665,6 +891,9 @@ pub fn search(
                 if best_pathscore.1 > alpha {
                     alpha = best_pathscore.1;
                     if alpha >= beta {
+                        trace!(event(TraceEvent::BetaCutoff { mv: hash_move, score }));
+                        search_state.stats.beta_cutoffs += 1;
+                        search_state.stats.first_move_cutoffs += 1;
                         return cutoff_unmake(
                             position,
                             singular_depth,
@@ -686,62 +915,10 @@ pub fn search(
         }
     }
 
-    // MOVE GENERATION: Use check evasions when in check, staged generation otherwise
-    let mut move_scores: MoveScoreArray;
-    let mut quiets_added: bool;
-
-    if in_check {
-        // When in check, generate only check evasion moves
-        let mut evasions = generate_check_evasions(position);
-        if verified_hash_move {
-            evasions.retain(|m| *m != hash_move);
-        }
-        move_scores = {
-            let enemy = &position.pieces[opponent!(position.mover) as usize];
-            evasions
-                .into_iter()
-                .map(|m| (m, score_move(position, m, search_state, ply as usize, enemy)))
-                .collect()
-        };
-        quiets_added = true; // No staged generation when in check
-    } else {
-        // Normal staged move generation: captures first, then quiets
-        let mut captures = generate_captures(position);
-        if verified_hash_move {
-            captures.retain(|m| *m != hash_move);
-        }
-        move_scores = {
-            let enemy = &position.pieces[opponent!(position.mover) as usize];
-            captures
-                .into_iter()
-                .map(|m| (m, score_move(position, m, search_state, ply as usize, enemy)))
-                .collect()
-        };
-        quiets_added = false;
-    }
+    // MOVE GENERATION: Check evasions when in check, staged generation otherwise
+    let mut move_picker = MovePicker::after_hash_move(if verified_hash_move { hash_move } else { 0 }, ply, in_check);
 
-    loop {
-        // If we've exhausted the current move list, add quiet moves if we haven't yet
-        if move_scores.is_empty() {
-            if quiets_added {
-                break; // All moves processed
-            }
-            // Add quiet moves
-            quiets_added = true;
-            let mut quiets = generate_quiet_moves(position);
-            if verified_hash_move {
-                quiets.retain(|m| *m != hash_move);
-            }
-            let enemy = &position.pieces[opponent!(position.mover) as usize];
-            for m in quiets {
-                move_scores.push((m, score_move(position, m, search_state, ply as usize, enemy)));
-            }
-            if move_scores.is_empty() {
-                break; // No quiet moves either
-            }
-        }
-
-        let m = pick_high_score_move(&mut move_scores);
+    while let Some(m) = move_picker.next_move(position, search_state) {
         let old_mover = position.mover;
         // For alpha pruning and LMR, treat promotions like captures (don't prune/reduce them)
         let is_tactical = captured_piece_value(position, m) > 0;
@@ -751,16 +928,29 @@ pub fn search(
         // Only in scout (null-window) searches to avoid missing important PV moves
         // Don't prune promotions (they change material dramatically) or when in check
         // Don't prune when searching for mate (alpha/beta near mate scores)
-        if scouting && is_tactical && !is_promotion && !in_check && depth <= SEE_PRUNE_MAX_DEPTH && alpha.abs() < MATE_START {
+        if features.enabled(SEE_PRUNING)
+            && scouting
+            && is_tactical
+            && !is_promotion
+            && !in_check
+            && depth <= SEE_PRUNE_MAX_DEPTH
+            && alpha.abs() < MATE_START
+        {
             let see_threshold = -(SEE_PRUNE_MARGIN * (depth as Score) * (depth as Score));
             if static_exchange_evaluation(position, m) < see_threshold {
+                trace!(event(TraceEvent::SeePrune { mv: m }));
+                search_state.stats.see_prunes += 1;
                 continue;
             }
         }
 
         // Pawn push extension: extend by 1 ply for pawn push to 7th rank
         // Only if no check extension already applied (avoid over-extending)
-        let pawn_push_ext: u8 = if check_extension == 0 && is_pawn_push_to_7th(position, m) && ply < search_state.iterative_depth * 2 {
+        let pawn_push_ext: u8 = if features.enabled(PAWN_PUSH_EXTENSION)
+            && check_extension == 0
+            && is_pawn_push_to_7th(position, m)
+            && ply < search_state.iterative_depth * 2
+        {
             1
         } else {
             0
@@ -769,7 +959,8 @@ pub fn search(
         // Passed pawn push extension: extend by 1 ply for passed pawn reaching 5th/6th rank
         // Only apply in endgames to avoid search explosion in complex middlegames
         // Only if no other extension already applied (avoid over-extending)
-        let passed_pawn_ext: u8 = if check_extension == 0
+        let passed_pawn_ext: u8 = if features.enabled(PASSED_PAWN_EXTENSION)
+            && check_extension == 0
             && pawn_push_ext == 0
             && is_end_game(position)
             && is_passed_pawn_push(position, m)
@@ -781,6 +972,12 @@ pub fn search(
         };
 
         let move_extension = check_extension + pawn_push_ext + passed_pawn_ext;
+        if pawn_push_ext > 0 {
+            trace!(event(TraceEvent::PawnPushExtension { mv: m }));
+        }
+        if passed_pawn_ext > 0 {
+            trace!(event(TraceEvent::PassedPawnExtension { mv: m }));
+        }
 
         let unmake = make_move_in_place(position, m);
         prefetch_hash(position, search_state); // Prefetch child position's hash entry
@@ -794,6 +991,7 @@ pub fn search(
             legal_move_count += 1;
 
             if legal_move_count > 1 && alpha_prune_flag && !is_tactical && !is_check(position, position.mover) {
+                trace!(event(TraceEvent::AlphaPrune { mv: m }));
                 unmake_move(position, m, &unmake);
                 continue;
             }
@@ -801,7 +999,8 @@ pub fn search(
             // Late Move Pruning (LMP): skip late quiet moves at shallow depths
             // More aggressive than LMR - completely skips the move instead of reducing
             // Don't prune in endgames (every move matters) or near mate scores
-            if scouting
+            if features.enabled(LATE_MOVE_PRUNING)
+                && scouting
                 && depth <= LMP_MAX_DEPTH
                 && !in_check
                 && !is_tactical
@@ -813,11 +1012,14 @@ pub fn search(
                 && !is_check(position, position.mover)
                 && alpha.abs() < MATE_START
             {
+                trace!(event(TraceEvent::LateMovePrune { mv: m }));
+                search_state.stats.lmp_prunes += 1;
                 unmake_move(position, m, &unmake);
                 continue;
             }
 
-            let lmr = if move_extension == 0
+            let lmr = if features.enabled(LATE_MOVE_REDUCTIONS)
+                && move_extension == 0
                 && legal_move_count > LMR_LEGAL_MOVES_BEFORE_ATTEMPT
                 && real_depth > LMR_MIN_DEPTH
                 && !is_tactical
@@ -836,8 +1038,14 @@ pub fn search(
                 0
             };
 
+            if lmr > 0 {
+                trace!(event(TraceEvent::LateMoveReduction { mv: m, reduction: lmr }));
+            }
+
             // Apply extensions to search depth
             let search_depth = depth + move_extension;
+            trace!(next_move(m));
+            search_state.stats.moves_searched += 1;
 
             let path_score = if scout_search {
                 lmr_scout_search(lmr, ply, search_state, (alpha, beta), search_depth, position)
@@ -863,6 +1071,11 @@ pub fn search(
                 if best_pathscore.1 > alpha {
                     alpha = best_pathscore.1;
                     if alpha >= beta {
+                        trace!(event(TraceEvent::BetaCutoff { mv: m, score }));
+                        search_state.stats.beta_cutoffs += 1;
+                        if legal_move_count == 1 {
+                            search_state.stats.first_move_cutoffs += 1;
+                        }
                         return cutoff_unmake(
                             position,
                             real_depth,
@@ -959,8 +1172,12 @@ fn lmr_scout_search(
     let beta = window.1;
     let mut scout_path = search_wrapper(real_depth, ply, search_state, (-alpha - 1, -alpha), new_position, lmr);
 
+    if lmr > 0 {
+        search_state.stats.lmr_searches += 1;
+    }
     if scout_path.1 > alpha && lmr > 0 {
         // We are in an LMR search and we Need to research with full window. but still with late move reduction
+        search_state.stats.lmr_researches += 1;
         scout_path = search_wrapper(real_depth, ply, search_state, (-beta, -alpha), new_position, lmr);
         if scout_path.1 > alpha {
             // Need to research with full window and no reduction
@@ -0,0 +1,104 @@
+//! Switches for the search heuristics, set with the `SearchFeatures` option
+//!
+//! Every pruning rule, reduction and extension is on by default. Switching one off shows whether
+//! it causes a bad move, or what it is worth in a match, without rebuilding the engine. The option
+//! is a string holding either the bitmask itself or a list of names, applied in order starting
+//! from all features: `-multicut` turns multicut off, `none lmr` leaves only late move reductions.
+
+use std::fmt;
+
+pub const BETA_PRUNING: u16 = 1 << 0;
+pub const ALPHA_PRUNING: u16 = 1 << 1;
+pub const NULL_MOVE: u16 = 1 << 2;
+pub const PROBCUT: u16 = 1 << 3;
+pub const MULTICUT: u16 = 1 << 4;
+pub const LATE_MOVE_PRUNING: u16 = 1 << 5;
+pub const SEE_PRUNING: u16 = 1 << 6;
+pub const LATE_MOVE_REDUCTIONS: u16 = 1 << 7;
+pub const THREAT_DETECTION: u16 = 1 << 8;
+pub const PAWN_PUSH_EXTENSION: u16 = 1 << 9;
+pub const PASSED_PAWN_EXTENSION: u16 = 1 << 10;
+
+pub const ALL_SEARCH_FEATURES: u16 = (1 << 11) - 1;
+
+pub const SEARCH_FEATURE_NAMES: [(&str, u16); 11] = [
+    ("betaprune", BETA_PRUNING),
+    ("alphaprune", ALPHA_PRUNING),
+    ("nullmove", NULL_MOVE),
+    ("probcut", PROBCUT),
+    ("multicut", MULTICUT),
+    ("lmp", LATE_MOVE_PRUNING),
+    ("seeprune", SEE_PRUNING),
+    ("lmr", LATE_MOVE_REDUCTIONS),
+    ("threat", THREAT_DETECTION),
+    ("pawnpush", PAWN_PUSH_EXTENSION),
+    ("passedpawn", PASSED_PAWN_EXTENSION),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchFeatures {
+    pub bits: u16,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        SearchFeatures { bits: ALL_SEARCH_FEATURES }
+    }
+}
+
+impl SearchFeatures {
+    #[inline(always)]
+    pub fn enabled(&self, feature: u16) -> bool {
+        self.bits & feature != 0
+    }
+
+    pub fn set(&mut self, feature: u16, on: bool) {
+        if on {
+            self.bits |= feature;
+        } else {
+            self.bits &= !feature;
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<SearchFeatures, String> {
+        if let Ok(bits) = value.trim().parse::<u16>() {
+            return if bits <= ALL_SEARCH_FEATURES {
+                Ok(SearchFeatures { bits })
+            } else {
+                Err(format!("SearchFeatures must be between 0 and {}", ALL_SEARCH_FEATURES))
+            };
+        }
+
+        let mut features = SearchFeatures::default();
+        for token in value.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+            let (name, on) = match token.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (token, true),
+            };
+            match name.to_lowercase().as_str() {
+                "all" => features.bits = if on { ALL_SEARCH_FEATURES } else { 0 },
+                "none" => features.bits = 0,
+                name => match SEARCH_FEATURE_NAMES.iter().find(|(n, _)| *n == name) {
+                    Some((_, feature)) => features.set(*feature, on),
+                    None => return Err(format!("Unknown search feature {}", name)),
+                },
+            }
+        }
+        Ok(features)
+    }
+}
+
+impl fmt::Display for SearchFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = SEARCH_FEATURE_NAMES
+            .iter()
+            .filter(|(_, feature)| self.enabled(*feature))
+            .map(|(name, _)| *name)
+            .collect();
+        match self.bits {
+            ALL_SEARCH_FEATURES => write!(f, "all"),
+            0 => write!(f, "none"),
+            _ => write!(f, "{}", names.join(" ")),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! The limits of one search, taken from the parameters of a `go` command
+//!
+//! Any combination of depth, nodes, movetime, clock and mate can be given, and the search stops at
+//! whichever limit it reaches first. A mate search of n moves is a depth limit of 2n plies on top
+//! of any depth given. `infinite` removes the time limits, so the search runs until `stop` unless
+//! a depth, node or mate limit ends it first.
+//!
+//! With the `Deterministic` option the clock never stops the search. The hard time limit becomes a node
+//! budget of `DETERMINISTIC_NODES_PER_MILLI` nodes per millisecond, and the soft limit is checked
+//! against the nodes searched so far, so the same position and limits always give the same search.
+
+use std::cmp::{max, min};
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+use crate::engine_constants::MAX_DEPTH;
+use crate::time_management::{allocate_time, TimeManager};
+use crate::types::{Mover, SearchState, WHITE};
+use crate::uci_command::GoParams;
+
+/// The hard limit used when there is no time limit
+pub const UNLIMITED_MILLIS: u64 = 86400 * 1000;
+
+/// The search speed assumed when time limits are converted into node budgets
+pub const DETERMINISTIC_NODES_PER_MILLI: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: u8,
+    pub nodes: u64,
+    /// Enforced by `end_time`, wherever the search is
+    pub hard_millis: Option<u64>,
+    /// Checked between iterations; only set when the time comes from the clock
+    pub soft_millis: Option<u64>,
+    pub mate: Option<u8>,
+    pub infinite: bool,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            depth: MAX_DEPTH,
+            nodes: u64::MAX,
+            hard_millis: None,
+            soft_millis: None,
+            mate: None,
+            infinite: false,
+        }
+    }
+}
+
+impl SearchLimits {
+    pub fn from_go(params: &GoParams, mover: Mover, move_overhead: u64) -> SearchLimits {
+        let mut limits = SearchLimits {
+            infinite: params.infinite,
+            mate: params.mate,
+            ..SearchLimits::default()
+        };
+        if let Some(depth) = params.depth {
+            limits.depth = min(depth, MAX_DEPTH as u64) as u8;
+        }
+        if let Some(mate) = params.mate {
+            limits.depth = min(limits.depth, mate.saturating_mul(2));
+        }
+        if let Some(nodes) = params.nodes {
+            limits.nodes = nodes;
+        }
+        if params.infinite {
+            return limits;
+        }
+
+        let (clock_millis, increment_millis) = if mover == WHITE {
+            (params.wtime.unwrap_or(0), params.winc.unwrap_or(0))
+        } else {
+            (params.btime.unwrap_or(0), params.binc.unwrap_or(0))
+        };
+        if clock_millis > 0 {
+            let allocation = allocate_time(clock_millis, increment_millis, params.moves_to_go.unwrap_or(0), move_overhead);
+            limits.hard_millis = Some(allocation.hard_millis);
+            limits.soft_millis = Some(allocation.soft_millis);
+        }
+        if let Some(move_time) = params.move_time {
+            let move_millis = max(1, move_time.saturating_sub(move_overhead));
+            limits.hard_millis = Some(limits.hard_millis.map_or(move_millis, |hard| min(hard, move_millis)));
+            limits.soft_millis = None;
+        }
+        limits
+    }
+
+    /// Strength limits only apply to games, not to analysis or mate searches
+    pub fn limit_strength(&self) -> bool {
+        !self.infinite && self.mate.is_none()
+    }
+
+    /// Set the node, time and mate limits on the search state. Call this just before searching,
+    /// because the time limit runs from now.
+    pub fn apply(&self, search_state: &mut SearchState) {
+        search_state.nodes_limit = self.nodes;
+        search_state.mate_search = self.mate;
+        search_state.time_manager = TimeManager::new(self.soft_millis.map(Duration::from_millis));
+        let hard_millis = match self.hard_millis {
+            Some(hard_millis) if search_state.deterministic => {
+                search_state.nodes_limit = min(self.nodes, hard_millis.saturating_mul(DETERMINISTIC_NODES_PER_MILLI));
+                UNLIMITED_MILLIS
+            }
+            Some(hard_millis) => hard_millis,
+            None => UNLIMITED_MILLIS,
+        };
+        search_state.end_time = Instant::now().add(Duration::from_millis(hard_millis));
+    }
+}
@@ -0,0 +1,314 @@
+//! A record of the decisions the search made, for working out why a move was or wasn't found
+//!
+//! Only built with the `search-trace` feature; without it the hooks in `search` compile to nothing.
+//! Between `start_trace` and `finish_trace`, every node searched on the current thread up to the
+//! given ply is recorded with the moves that lead to it, its window, depth and static evaluation,
+//! the pruning rules and extensions that fired there, and the score it returned. The nodes of each
+//! root move, for every iteration and re-search, form a list of trees that can be written out as
+//! JSON or as an indented text log restricted to the lines below a chosen sequence of moves.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::evaluate::evaluate;
+use crate::fen::algebraic_move_from_move;
+use crate::types::{Move, Position, Score, Window};
+
+/// A pruning rule, extension or cutoff that applied at a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    HashCutoff {
+        score: Score,
+    },
+    BetaPrune {
+        eval: Score,
+        margin: Score,
+    },
+    /// Static eval is so far below alpha that quiet moves after the first are skipped
+    AlphaPruneNode {
+        eval: Score,
+    },
+    NullMoveCutoff {
+        score: Score,
+    },
+    NullMoveThreat {
+        score: Score,
+    },
+    ProbCut {
+        mv: Move,
+        score: Score,
+    },
+    MultiCut {
+        cutoffs: u8,
+    },
+    CheckExtension,
+    PawnPushExtension {
+        mv: Move,
+    },
+    PassedPawnExtension {
+        mv: Move,
+    },
+    SeePrune {
+        mv: Move,
+    },
+    AlphaPrune {
+        mv: Move,
+    },
+    LateMovePrune {
+        mv: Move,
+    },
+    LateMoveReduction {
+        mv: Move,
+        reduction: u8,
+    },
+    BetaCutoff {
+        mv: Move,
+        score: Score,
+    },
+}
+
+impl TraceEvent {
+    pub fn rule(&self) -> &'static str {
+        match self {
+            TraceEvent::HashCutoff { .. } => "hash cutoff",
+            TraceEvent::BetaPrune { .. } => "beta prune",
+            TraceEvent::AlphaPruneNode { .. } => "alpha prune node",
+            TraceEvent::NullMoveCutoff { .. } => "null move cutoff",
+            TraceEvent::NullMoveThreat { .. } => "null move threat",
+            TraceEvent::ProbCut { .. } => "probcut",
+            TraceEvent::MultiCut { .. } => "multicut",
+            TraceEvent::CheckExtension => "check extension",
+            TraceEvent::PawnPushExtension { .. } => "pawn push extension",
+            TraceEvent::PassedPawnExtension { .. } => "passed pawn extension",
+            TraceEvent::SeePrune { .. } => "see prune",
+            TraceEvent::AlphaPrune { .. } => "alpha prune",
+            TraceEvent::LateMovePrune { .. } => "lmp",
+            TraceEvent::LateMoveReduction { .. } => "lmr",
+            TraceEvent::BetaCutoff { .. } => "beta cutoff",
+        }
+    }
+
+    /// The move the rule applied to, for rules that apply to a single move
+    pub fn mv(&self) -> Option<Move> {
+        match *self {
+            TraceEvent::ProbCut { mv, .. }
+            | TraceEvent::PawnPushExtension { mv }
+            | TraceEvent::PassedPawnExtension { mv }
+            | TraceEvent::SeePrune { mv }
+            | TraceEvent::AlphaPrune { mv }
+            | TraceEvent::LateMovePrune { mv }
+            | TraceEvent::LateMoveReduction { mv, .. }
+            | TraceEvent::BetaCutoff { mv, .. } => Some(mv),
+            _ => None,
+        }
+    }
+
+    fn values(&self) -> Vec<(&'static str, i64)> {
+        match *self {
+            TraceEvent::HashCutoff { score }
+            | TraceEvent::NullMoveCutoff { score }
+            | TraceEvent::NullMoveThreat { score }
+            | TraceEvent::ProbCut { score, .. }
+            | TraceEvent::BetaCutoff { score, .. } => vec![("score", score as i64)],
+            TraceEvent::BetaPrune { eval, margin } => vec![("eval", eval as i64), ("margin", margin as i64)],
+            TraceEvent::AlphaPruneNode { eval } => vec![("eval", eval as i64)],
+            TraceEvent::MultiCut { cutoffs } => vec![("cutoffs", cutoffs as i64)],
+            TraceEvent::LateMoveReduction { reduction, .. } => vec![("reduction", reduction as i64)],
+            _ => vec![],
+        }
+    }
+
+    fn to_json(self) -> String {
+        let mut fields = vec![format!("\"rule\":\"{}\"", self.rule())];
+        if let Some(m) = self.mv() {
+            fields.push(format!("\"move\":\"{}\"", algebraic_move_from_move(m)));
+        }
+        for (name, value) in self.values() {
+            fields.push(format!("\"{}\":{}", name, value));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rule())?;
+        if let Some(m) = self.mv() {
+            write!(f, " {}", algebraic_move_from_move(m))?;
+        }
+        for (name, value) in self.values() {
+            write!(f, " {} {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceNode {
+    /// Moves from the root, with "null" for a null move and "iid" for an internal iterative deepening search
+    pub path: Vec<String>,
+    pub ply: u8,
+    pub depth: u8,
+    pub window: Window,
+    pub static_eval: Score,
+    pub events: Vec<TraceEvent>,
+    /// From the point of view of the side to move at this node
+    pub score: Score,
+    pub children: Vec<TraceNode>,
+}
+
+struct Tracer {
+    max_ply: u8,
+    roots: Vec<TraceNode>,
+    stack: Vec<TraceNode>,
+    /// The label for the next child of each node on the stack, and of the root below the stack
+    next_labels: Vec<String>,
+    /// Nodes entered beyond `max_ply` that have not returned yet
+    untraced: u32,
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+/// Start recording every node searched on this thread up to `max_ply`
+pub fn start_trace(max_ply: u8) {
+    TRACER.with(|tracer| {
+        *tracer.borrow_mut() = Some(Tracer {
+            max_ply,
+            roots: vec![],
+            stack: vec![],
+            next_labels: vec![String::new()],
+            untraced: 0,
+        })
+    });
+}
+
+/// Stop recording and return the trees of nodes searched below each root move
+pub fn finish_trace() -> Vec<TraceNode> {
+    TRACER.with(|tracer| tracer.borrow_mut().take().map(|t| t.roots).unwrap_or_default())
+}
+
+fn with_tracer(f: impl FnOnce(&mut Tracer)) {
+    TRACER.with(|tracer| {
+        if let Some(t) = tracer.borrow_mut().as_mut() {
+            f(t)
+        }
+    });
+}
+
+pub fn enter_node(position: &Position, depth: u8, ply: u8, window: Window) {
+    with_tracer(|t| {
+        if t.untraced > 0 || ply > t.max_ply {
+            t.untraced += 1;
+            return;
+        }
+        let mut path = t.stack.last().map(|n| n.path.clone()).unwrap_or_default();
+        path.push(t.next_labels.last().cloned().unwrap_or_default());
+        t.stack.push(TraceNode {
+            path,
+            ply,
+            depth,
+            window,
+            static_eval: evaluate(position),
+            events: vec![],
+            score: 0,
+            children: vec![],
+        });
+        t.next_labels.push(String::new());
+    });
+}
+
+pub fn leave_node(score: Score) {
+    with_tracer(|t| {
+        if t.untraced > 0 {
+            t.untraced -= 1;
+            return;
+        }
+        if let Some(mut node) = t.stack.pop() {
+            t.next_labels.pop();
+            node.score = score;
+            match t.stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => t.roots.push(node),
+            }
+        }
+    });
+}
+
+/// Label the nodes the current node searches next with this move
+pub fn next_move(m: Move) {
+    next_label(&algebraic_move_from_move(m));
+}
+
+pub fn next_label(label: &str) {
+    with_tracer(|t| {
+        if t.untraced == 0 {
+            if let Some(next) = t.next_labels.last_mut() {
+                *next = label.to_string();
+            }
+        }
+    });
+}
+
+pub fn event(event: TraceEvent) {
+    with_tracer(|t| {
+        if t.untraced == 0 {
+            if let Some(node) = t.stack.last_mut() {
+                node.events.push(event);
+            }
+        }
+    });
+}
+
+pub fn trace_to_json(nodes: &[TraceNode]) -> String {
+    format!("[{}]", nodes.iter().map(node_to_json).collect::<Vec<String>>().join(","))
+}
+
+fn node_to_json(node: &TraceNode) -> String {
+    format!(
+        "{{\"path\":[{}],\"ply\":{},\"depth\":{},\"alpha\":{},\"beta\":{},\"eval\":{},\"score\":{},\"events\":[{}],\"children\":{}}}",
+        node.path.iter().map(|m| format!("\"{}\"", m)).collect::<Vec<String>>().join(","),
+        node.ply,
+        node.depth,
+        node.window.0,
+        node.window.1,
+        node.static_eval,
+        node.score,
+        node.events.iter().map(|e| e.to_json()).collect::<Vec<String>>().join(","),
+        trace_to_json(&node.children)
+    )
+}
+
+/// One line per node, indented by ply, for the nodes on the way to `filter` and all nodes below it.
+/// An empty filter keeps every node.
+pub fn trace_to_text(nodes: &[TraceNode], filter: &[&str]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        write_text(node, filter, &mut text);
+    }
+    text
+}
+
+fn write_text(node: &TraceNode, filter: &[&str], text: &mut String) {
+    let shared = node.path.len().min(filter.len());
+    if node.path[..shared].iter().zip(filter).any(|(m, f)| m != f) {
+        return;
+    }
+    let events = node.events.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(", ");
+    text.push_str(&format!(
+        "{}{} depth {} window [{}, {}] eval {} score {}{}{}\n",
+        "  ".repeat(node.ply.saturating_sub(1) as usize),
+        node.path.join(" "),
+        node.depth,
+        node.window.0,
+        node.window.1,
+        node.static_eval,
+        node.score,
+        if events.is_empty() { "" } else { ": " },
+        events
+    ));
+    for child in &node.children {
+        write_text(child, filter, text);
+    }
+}
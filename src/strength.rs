@@ -0,0 +1,130 @@
+//! Playing strength reduction for the `UCI_LimitStrength`, `UCI_Elo` and `Skill Level` options
+//!
+//! A reduced skill level caps the search depth and node count, and then picks the move to play
+//! at random from the best few root moves, with worse moves becoming more likely as the level
+//! drops. The root moves are searched with MultiPV so that alternatives have scores to choose from.
+
+use crate::engine_constants::PAWN_VALUE_AVERAGE;
+use crate::search::iterative_deepening;
+use crate::types::{pv_single, Move, Position, Score, SearchState};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::{max, min};
+
+pub const MAX_SKILL_LEVEL: u8 = 20;
+pub const MIN_ELO: u32 = 1000;
+pub const MAX_ELO: u32 = 2800;
+pub const DEFAULT_ELO: u32 = 1500;
+
+/// Number of root moves searched with MultiPV when playing below full strength
+pub const SKILL_CANDIDATE_MOVES: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strength {
+    pub skill_level: u8,
+    pub limit_strength: bool,
+    pub elo: u32,
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Strength {
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+        }
+    }
+}
+
+impl Strength {
+    /// `UCI_LimitStrength` takes precedence over `Skill Level`, as required by the UCI protocol
+    pub fn effective_skill_level(&self) -> u8 {
+        if self.limit_strength {
+            elo_to_skill_level(self.elo)
+        } else {
+            self.skill_level
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.effective_skill_level() < MAX_SKILL_LEVEL
+    }
+
+    pub fn depth_limit(&self) -> u8 {
+        1 + self.effective_skill_level() / 2
+    }
+
+    pub fn nodes_limit(&self) -> u64 {
+        1000 << (self.effective_skill_level() / 2)
+    }
+}
+
+/// Linear mapping from the advertised Elo range onto skill levels 0 to 20. The Elo values are
+/// nominal: they are not anchored to any rated reference, so they only order the levels. The
+/// calibration example (`cargo run --release --example calibrate_strength`) measures how each
+/// level performs relative to the full-strength engine.
+pub fn elo_to_skill_level(elo: u32) -> u8 {
+    let elo = elo.clamp(MIN_ELO, MAX_ELO);
+    ((elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO)) as u8
+}
+
+/// Run `iterative_deepening` under the strength settings in the search state. At full strength this
+/// is a normal search; otherwise the depth and nodes are capped and the move is chosen by
+/// `pick_weakened_move`.
+pub fn search_with_strength(position: &mut Position, max_depth: u8, search_state: &mut SearchState) -> Move {
+    let strength = search_state.strength;
+    if !strength.is_limited() {
+        return iterative_deepening(position, max_depth, search_state);
+    }
+
+    let multi_pv = search_state.multi_pv;
+    search_state.multi_pv = max(multi_pv, SKILL_CANDIDATE_MOVES);
+    search_state.nodes_limit = min(search_state.nodes_limit, strength.nodes_limit());
+    let best_move = iterative_deepening(position, min(max_depth, strength.depth_limit()), search_state);
+    search_state.multi_pv = multi_pv;
+
+    if best_move == 0 {
+        return best_move;
+    }
+    let skill_level = strength.effective_skill_level();
+    if search_state.deterministic {
+        // Seeded by the position, so the same position always gets the same weakened move
+        let mut rng = StdRng::seed_from_u64(position.zobrist_lock as u64);
+        pick_weakened_move(search_state, skill_level, best_move, &mut rng)
+    } else {
+        pick_weakened_move(search_state, skill_level, best_move, &mut rand::thread_rng())
+    }
+}
+
+/// Choose among the best `SKILL_CANDIDATE_MOVES` root moves using the MultiPV lines in `search_state.pv_lines`.
+/// Each candidate gets a push made of a share of its deficit to the best move and a random
+/// amount, both growing as the skill level falls; the highest pushed score is played.
+/// `current_best` is updated so that the ponder move matches the chosen line.
+pub fn pick_weakened_move<R: Rng>(search_state: &mut SearchState, skill_level: u8, best_move: Move, rng: &mut R) -> Move {
+    let mut candidates: Vec<(Move, Score)> = search_state.pv_lines.iter().map(|line| (line.0[0], line.1)).collect();
+    if candidates.is_empty() {
+        return best_move;
+    }
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.1));
+    candidates.truncate(SKILL_CANDIDATE_MOVES as usize);
+
+    let top_score = candidates[0].1;
+    let delta = min(top_score - candidates[candidates.len() - 1].1, PAWN_VALUE_AVERAGE);
+    let weakness = 120 - 2 * skill_level as Score;
+
+    let mut chosen = candidates[0].0;
+    let mut max_score = Score::MIN;
+    for (m, score) in candidates {
+        let push = (weakness * (top_score - score) + delta * rng.gen_range(0..weakness)) / 128;
+        if score + push >= max_score {
+            max_score = score + push;
+            chosen = m;
+        }
+    }
+
+    search_state.current_best = match search_state.pv_lines.iter().find(|line| line.0[0] == chosen) {
+        Some(line) => line.clone(),
+        None => (pv_single(chosen), max_score),
+    };
+    chosen
+}
@@ -0,0 +1,146 @@
+//! Time allocation for clock-based searches
+//!
+//! A clock search gets two limits. The hard limit is enforced by `end_time` and stops the search
+//! wherever it is. The soft limit is checked between iterations: a new iteration is not started
+//! once the soft limit has passed, because it would most likely be abandoned before completing.
+//! The soft limit is scaled by how settled the search looks, growing when the best move changes
+//! or the score drops, and shrinking when the same move keeps coming back. Once the best move has
+//! settled, the search stops early if it is clearly better than every alternative.
+
+use crate::types::{Move, Score};
+use std::cmp::{max, min};
+use std::time::Duration;
+
+/// Moves assumed to be left in the game when the GUI doesn't send `movestogo`
+pub const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// The hard limit is this many times the soft limit, capped by the time left on the clock
+pub const HARD_LIMIT_MULTIPLE: u64 = 4;
+
+/// Iterations with the same best move before the soft limit starts to shrink
+pub const STABLE_ITERATIONS_BEFORE_CUT: u32 = 3;
+
+/// The best move dominates when it scores at least this much more than the second best move
+pub const DOMINANT_MOVE_MARGIN: Score = 150;
+
+/// Fraction of the soft limit that must be used before the search can stop on a dominant move
+pub const DOMINANT_MOVE_MIN_FRACTION: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeAllocation {
+    pub soft_millis: u64,
+    pub hard_millis: u64,
+}
+
+/// Split the remaining clock time into soft and hard limits for one move. The move overhead is
+/// taken off the clock first to cover GUI and network lag.
+pub fn allocate_time(remaining_millis: u64, increment_millis: u64, moves_to_go: u64, move_overhead_millis: u64) -> TimeAllocation {
+    let available = remaining_millis.saturating_sub(move_overhead_millis);
+    let moves_remaining = if moves_to_go == 0 {
+        DEFAULT_MOVES_TO_GO
+    } else {
+        min(moves_to_go, DEFAULT_MOVES_TO_GO)
+    };
+
+    // Never plan to spend more than half of the clock on one move, unless it is the last move before the time control
+    let max_millis = if moves_remaining == 1 { available * 9 / 10 } else { available / 2 };
+
+    let soft = min(available / (moves_remaining + 1) + increment_millis * 3 / 4, max_millis);
+    let hard = min(soft * HARD_LIMIT_MULTIPLE, max_millis);
+
+    TimeAllocation {
+        soft_millis: max(1, soft),
+        hard_millis: max(1, hard),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeManager {
+    pub soft_limit: Option<Duration>,
+    single_reply: bool,
+    best_move: Move,
+    best_score: Score,
+    best_move_changes: f64,
+    score_drop: Score,
+    stable_iterations: u32,
+    dominant: bool,
+}
+
+impl TimeManager {
+    pub fn new(soft_limit: Option<Duration>) -> Self {
+        TimeManager {
+            soft_limit,
+            ..TimeManager::default()
+        }
+    }
+
+    /// Clear the per-search history, keeping the soft limit
+    pub fn start_search(&mut self, legal_move_count: usize) {
+        *self = TimeManager {
+            single_reply: legal_move_count == 1,
+            ..TimeManager::new(self.soft_limit)
+        };
+    }
+
+    /// Record the result of a completed iteration
+    pub fn update(&mut self, best_move: Move, best_score: Score) {
+        self.best_move_changes /= 2.0;
+        if self.best_move == 0 {
+            self.score_drop = 0;
+        } else {
+            if best_move == self.best_move {
+                self.stable_iterations += 1;
+            } else {
+                self.best_move_changes += 1.0;
+                self.stable_iterations = 0;
+            }
+            self.score_drop = self.best_score - best_score;
+        }
+        self.best_move = best_move;
+        self.best_score = best_score;
+        self.dominant = false;
+    }
+
+    /// Whether the score of the second best move is wanted for the last iteration. It is only
+    /// asked for once the best move has settled and enough of the soft limit has been used.
+    pub fn wants_second_best(&self, elapsed: Duration) -> bool {
+        match self.soft_limit {
+            Some(soft_limit) if !self.single_reply && self.best_move != 0 => {
+                self.stable_iterations >= STABLE_ITERATIONS_BEFORE_CUT
+                    && elapsed.as_secs_f64() >= soft_limit.as_secs_f64() * DOMINANT_MOVE_MIN_FRACTION
+            }
+            _ => false,
+        }
+    }
+
+    /// Record the score, or an upper bound on the score, of the second best move in the last iteration
+    pub fn update_second_best(&mut self, second_best_score: Score) {
+        self.dominant = self.best_score - second_best_score >= DOMINANT_MOVE_MARGIN;
+    }
+
+    /// Whether the best move of the last iteration is clearly better than all the others
+    pub fn is_dominant(&self) -> bool {
+        self.dominant
+    }
+
+    /// Factor applied to the soft limit, between roughly 0.5 and 3
+    pub fn scale(&self) -> f64 {
+        let instability = 1.0 + self.best_move_changes;
+        let falling_score = (1.0 + self.score_drop as f64 / 200.0).clamp(1.0, 1.5);
+        let stability = if self.stable_iterations >= STABLE_ITERATIONS_BEFORE_CUT {
+            (1.0 - 0.1 * (self.stable_iterations - STABLE_ITERATIONS_BEFORE_CUT + 1) as f64).max(0.5)
+        } else {
+            1.0
+        };
+        instability * falling_score * stability
+    }
+
+    /// Whether there is time to start another iteration, given the time used so far
+    pub fn should_start_iteration(&self, elapsed: Duration) -> bool {
+        match self.soft_limit {
+            None => true,
+            Some(_) if self.single_reply || self.dominant => false,
+            Some(soft_limit) => elapsed.as_secs_f64() < soft_limit.as_secs_f64() * self.scale(),
+        }
+    }
+}
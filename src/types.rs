@@ -1,10 +1,18 @@
-use crate::engine_constants::{HASH_ENTRY_BYTES, MAX_DEPTH, NUM_KILLER_MOVES, NUM_PAWN_HASH_ENTRIES};
+use crate::endgame::Endgame;
+use crate::engine_constants::{
+    DEFAULT_MOVE_OVERHEAD_MILLIS, HASHFULL_SAMPLE_SIZE, HASH_ENTRY_BYTES, MAX_DEPTH, NUM_KILLER_MOVES, NUM_MATERIAL_HASH_ENTRIES,
+    NUM_PAWN_HASH_ENTRIES,
+};
 use crate::move_constants::{BK_CASTLE, BQ_CASTLE, START_POS, WK_CASTLE, WQ_CASTLE};
+use crate::perft::PerftHashTable;
+use crate::search_features::SearchFeatures;
+use crate::strength::Strength;
+use crate::time_management::TimeManager;
 use arrayvec::ArrayVec;
 use std::cell::UnsafeCell;
-use std::collections::HashMap;
+use std::cmp::min;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Instant;
 
@@ -19,6 +27,8 @@ pub type Bound = Score;
 pub type Window = (Bound, Bound);
 pub type Score = i32;
 pub type HashLock = u128;
+/// The number of each piece type other than kings on each side, see `endgame::material_key`
+pub type MaterialKey = u64;
 pub type HashIndex = u32;
 /// Thread-safe wrapper for the hash table that allows sharing between threads.
 /// Uses UnsafeCell for interior mutability - data races on individual hash entries
@@ -120,6 +130,19 @@ impl SharedHashTable {
         }
     }
 
+    /// Permille of a sample of entries written during the current search, for the UCI `hashfull` field
+    pub fn hashfull(&self, version: u32) -> u32 {
+        let sample = min(HASHFULL_SAMPLE_SIZE, self.num_entries);
+        if sample == 0 {
+            return 0;
+        }
+        let used = (0..sample).filter(|i| {
+            let entry = self.get(*i);
+            entry.lock != 0 && entry.version == version
+        });
+        (used.count() * 1000 / sample) as u32
+    }
+
     /// Clear the hash table (used by ucinewgame)
     pub fn clear(&self) {
         let empty = HashEntry {
@@ -246,6 +269,81 @@ impl std::fmt::Debug for PawnHashTable {
     }
 }
 
+// Material hash table entry - caches the evaluation terms that depend only on the piece counts.
+// The empty entry is right for bare kings, whose key is zero.
+#[derive(Copy, Clone, Default)]
+pub struct MaterialHashEntry {
+    pub key: MaterialKey,
+    pub imbalance: Score, // Trade, bishop/knight and material imbalance scores, all but the knight placement
+    pub phase: Score,     // Material of both sides, kings excluded
+    pub endgame: Option<(&'static Endgame, Mover)>,
+    pub queen_vs_knight: Option<Mover>, // The side with knight and pawns against a queen, whose placement counts
+}
+
+// Material hash table - the few material configurations of a search are looked up by material key
+pub struct MaterialHashTable {
+    data: UnsafeCell<Box<[MaterialHashEntry; NUM_MATERIAL_HASH_ENTRIES]>>,
+}
+
+// SAFETY: Same reasoning as SharedHashTable - data races just cause cache misses
+unsafe impl Send for MaterialHashTable {}
+unsafe impl Sync for MaterialHashTable {}
+
+impl MaterialHashTable {
+    pub fn new() -> Self {
+        MaterialHashTable {
+            data: UnsafeCell::new(Box::new([MaterialHashEntry::default(); NUM_MATERIAL_HASH_ENTRIES])),
+        }
+    }
+
+    // The piece counts sit in the low bits of each side's half of the key, so they are mixed first
+    #[inline(always)]
+    fn index(key: MaterialKey) -> usize {
+        (key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as usize % NUM_MATERIAL_HASH_ENTRIES
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: MaterialKey) -> Option<MaterialHashEntry> {
+        // SAFETY: We accept data races as they only cause cache misses
+        let entry = unsafe { (*self.data.get())[Self::index(key)] };
+        if entry.key == key {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn set(&self, entry: MaterialHashEntry) {
+        // SAFETY: We accept data races as they only cause cache misses
+        unsafe {
+            (*self.data.get())[Self::index(entry.key)] = entry;
+        }
+    }
+
+    pub fn clear(&self) {
+        // SAFETY: Called before search starts, no concurrent access expected
+        unsafe {
+            let data = &mut *self.data.get();
+            for entry in data.iter_mut() {
+                *entry = MaterialHashEntry::default();
+            }
+        }
+    }
+}
+
+impl Default for MaterialHashTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for MaterialHashTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MaterialHashTable({} entries)", NUM_MATERIAL_HASH_ENTRIES)
+    }
+}
+
 pub const MAX_PV_LENGTH: usize = 64;
 pub type PV = ArrayVec<Move, MAX_PV_LENGTH>;
 pub type PathScore = (PV, Score);
@@ -281,6 +379,8 @@ pub struct UnmakeInfo {
     pub en_passant_square: Square,
     pub half_moves: u16,
     pub zobrist_lock: HashLock,
+    pub pawn_zobrist_key: HashLock,
+    pub material_key: MaterialKey,
     pub captured_piece: u8, // 0 = none, 1-5 = pawn/knight/bishop/rook/queen
 }
 
@@ -335,6 +435,94 @@ impl SearchHandle {
     }
 }
 
+/// Counters for how often each part of the search does its job, reset at the start of each search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub qsearch_nodes: u64,
+    pub hash_probes: u64,
+    pub hash_hits_exact: u64,
+    pub hash_hits_lower: u64,
+    pub hash_hits_upper: u64,
+    /// Nodes that went on to search moves, and the moves they searched
+    pub expanded_nodes: u64,
+    pub moves_searched: u64,
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub null_move_attempts: u64,
+    pub null_move_cutoffs: u64,
+    pub probcut_cutoffs: u64,
+    pub multicut_cutoffs: u64,
+    pub lmr_searches: u64,
+    pub lmr_researches: u64,
+    pub lmp_prunes: u64,
+    pub see_prunes: u64,
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let all_nodes = self.nodes + self.qsearch_nodes;
+        writeln!(
+            f,
+            "Search nodes {}, quiesce nodes {} ({:.1}%)",
+            self.nodes,
+            self.qsearch_nodes,
+            percent(self.qsearch_nodes, all_nodes)
+        )?;
+        writeln!(
+            f,
+            "Hash probes {}, exact hits {:.1}%, lower bound hits {:.1}%, upper bound hits {:.1}%",
+            self.hash_probes,
+            percent(self.hash_hits_exact, self.hash_probes),
+            percent(self.hash_hits_lower, self.hash_probes),
+            percent(self.hash_hits_upper, self.hash_probes)
+        )?;
+        writeln!(
+            f,
+            "Beta cutoffs {}, on the first move {:.1}%",
+            self.beta_cutoffs,
+            percent(self.first_move_cutoffs, self.beta_cutoffs)
+        )?;
+        writeln!(
+            f,
+            "Null move attempts {}, cutoffs {} ({:.1}%)",
+            self.null_move_attempts,
+            self.null_move_cutoffs,
+            percent(self.null_move_cutoffs, self.null_move_attempts)
+        )?;
+        writeln!(
+            f,
+            "Probcut cutoffs {}, multicut cutoffs {}",
+            self.probcut_cutoffs, self.multicut_cutoffs
+        )?;
+        writeln!(
+            f,
+            "LMR searches {}, re-searched {} ({:.1}%)",
+            self.lmr_searches,
+            self.lmr_researches,
+            percent(self.lmr_researches, self.lmr_searches)
+        )?;
+        writeln!(f, "LMP prunes {}, SEE prunes {}", self.lmp_prunes, self.see_prunes)?;
+        write!(
+            f,
+            "Branching factor {:.2} moves searched per expanded node",
+            if self.expanded_nodes == 0 {
+                0.0
+            } else {
+                self.moves_searched as f64 / self.expanded_nodes as f64
+            }
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct SearchState {
     pub current_best: PathScore,
@@ -345,6 +533,9 @@ pub struct SearchState {
     pub hash_table: Arc<SharedHashTable>,
     pub hash_table_version: u32,
     pub pawn_hash_table: Arc<PawnHashTable>,
+    pub material_hash_table: Arc<MaterialHashTable>,
+    /// Created by the first perft with the size of the main hash table, and kept for later ones
+    pub perft_hash_table: Option<Arc<PerftHashTable>>,
     pub killer_moves: [[Move; NUM_KILLER_MOVES]; MAX_DEPTH as usize],
     pub mate_killer: [Move; MAX_DEPTH as usize],
     pub countermoves: [[Move; 64]; 12],       // [piece_12][to_square] -> best countermove
@@ -355,7 +546,7 @@ pub struct SearchState {
     pub nodes_limit: u64,
     pub show_info: bool,
     pub hash_hits_exact: u64,
-    pub pv: HashMap<Move, PathScore>,
+    pub pv_lines: Vec<PathScore>, // One line per MultiPV line from the last completed iteration, best first
     pub hash_clashes: u64,
     pub history: PositionHistory,
     pub multi_pv: u8,
@@ -364,6 +555,20 @@ pub struct SearchState {
     pub search_moves: Option<Vec<Move>>,
     pub stop: Arc<AtomicBool>,
     pub last_info_nodes: u64,
+    pub strength: Strength,
+    pub search_features: SearchFeatures,
+    pub time_manager: TimeManager,
+    pub move_overhead: u64,
+    pub mate_search: Option<u8>,
+    pub seldepth: u8,
+    pub tb_hits: u64,
+    pub show_wdl: bool,
+    /// Time limits become node budgets and nothing is kept from one search to the next
+    pub deterministic: bool,
+    pub root_material: Score,
+    pub stats: SearchStats,
+    /// The statistics of the last completed search, shared with the state it was cloned from
+    pub last_search_stats: Arc<Mutex<SearchStats>>,
 }
 
 impl Clone for SearchState {
@@ -378,6 +583,8 @@ impl Clone for SearchState {
             hash_table: Arc::clone(&self.hash_table),
             hash_table_version: self.hash_table_version,
             pawn_hash_table: Arc::clone(&self.pawn_hash_table),
+            material_hash_table: Arc::clone(&self.material_hash_table),
+            perft_hash_table: self.perft_hash_table.clone(),
             killer_moves: self.killer_moves,
             mate_killer: self.mate_killer,
             countermoves: self.countermoves,
@@ -388,7 +595,7 @@ impl Clone for SearchState {
             nodes_limit: self.nodes_limit,
             show_info: self.show_info,
             hash_hits_exact: self.hash_hits_exact,
-            pv: self.pv.clone(),
+            pv_lines: self.pv_lines.clone(),
             hash_clashes: self.hash_clashes,
             history: self.history.clone(),
             multi_pv: self.multi_pv,
@@ -397,6 +604,18 @@ impl Clone for SearchState {
             search_moves: self.search_moves.clone(),
             stop: Arc::clone(&self.stop),
             last_info_nodes: self.last_info_nodes,
+            strength: self.strength,
+            search_features: self.search_features,
+            time_manager: self.time_manager,
+            move_overhead: self.move_overhead,
+            mate_search: self.mate_search,
+            seldepth: self.seldepth,
+            tb_hits: self.tb_hits,
+            show_wdl: self.show_wdl,
+            deterministic: self.deterministic,
+            root_material: self.root_material,
+            stats: self.stats,
+            last_search_stats: Arc::clone(&self.last_search_stats),
         }
     }
 }
@@ -411,6 +630,8 @@ pub fn default_search_state() -> SearchState {
         hash_table: Arc::new(SharedHashTable::new()),
         hash_table_version: 1,
         pawn_hash_table: Arc::new(PawnHashTable::new()),
+        material_hash_table: Arc::new(MaterialHashTable::new()),
+        perft_hash_table: None,
         killer_moves: [[0, 0]; MAX_DEPTH as usize],
         mate_killer: [0; MAX_DEPTH as usize],
         countermoves: [[0; 64]; 12],
@@ -421,7 +642,7 @@ pub fn default_search_state() -> SearchState {
         nodes_limit: u64::MAX,
         show_info: true,
         hash_hits_exact: 0,
-        pv: HashMap::new(),
+        pv_lines: vec![],
         hash_clashes: 0,
         history: vec![],
         multi_pv: 1,
@@ -430,6 +651,18 @@ pub fn default_search_state() -> SearchState {
         search_moves: None,
         stop: Arc::new(AtomicBool::new(false)),
         last_info_nodes: 0,
+        strength: Strength::default(),
+        search_features: SearchFeatures::default(),
+        time_manager: TimeManager::default(),
+        move_overhead: DEFAULT_MOVE_OVERHEAD_MILLIS,
+        mate_search: None,
+        seldepth: 0,
+        tb_hits: 0,
+        show_wdl: false,
+        deterministic: false,
+        root_material: 0,
+        stats: SearchStats::default(),
+        last_search_stats: Arc::new(Mutex::new(SearchStats::default())),
     }
 }
 
@@ -600,6 +833,8 @@ pub struct Position {
     pub half_moves: u16,
     pub move_number: u16,
     pub zobrist_lock: u128,
+    pub pawn_zobrist_key: HashLock,
+    pub material_key: MaterialKey,
 }
 
 impl PartialEq for Position {
@@ -1,10 +1,11 @@
-use crate::engine_constants::UCI_MILLIS_REDUCTION;
+use crate::engine_constants::{DEFAULT_MOVE_OVERHEAD_MILLIS, HASH_ENTRY_BYTES, MAX_MOVE_OVERHEAD_MILLIS};
 use crate::tablebase::init_tablebase;
 
 use either::{Either, Left, Right};
 
-use regex::Regex;
-use std::cmp::{max, min};
+use std::fs;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::ops::Add;
 use std::process::exit;
 use std::sync::atomic::AtomicBool;
@@ -12,15 +13,25 @@ use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::fen::{algebraic_move_from_move, get_fen, get_position};
+use crate::annotate::{annotate_game, annotated_pgn};
+use crate::datagen::run_datagen;
+use crate::fen::{algebraic_move_from_move, get_fen, get_position, try_get_position};
 use crate::make_move::make_move;
+#[cfg(feature = "move-validation")]
+use crate::make_move_validation::set_move_validation;
 use crate::move_constants::START_POS;
-use crate::moves::{generate_moves, is_check};
+use crate::moves::{generate_legal_moves, is_check};
 
-use crate::perft::perft;
+use crate::perft::{perft_divide_parallel, PerftHashTable, PERFT_HASH_ENTRY_BYTES};
+use crate::pgn::parse_pgn;
+use crate::puzzles::game_puzzles;
 use crate::search::iterative_deepening;
-use crate::types::{set_stop, Move, Position, SearchHandle, SearchState, SharedHashTable, UciState, BLACK, WHITE};
+use crate::search_features::SearchFeatures;
+use crate::search_limits::{SearchLimits, UNLIMITED_MILLIS};
+use crate::strength::{elo_to_skill_level, search_with_strength, DEFAULT_ELO, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use crate::types::{set_stop, Move, Mover, Position, Score, SearchHandle, SearchState, SharedHashTable, UciState, BLACK, WHITE};
 use crate::uci_bench::cmd_benchmark;
+use crate::uci_command::{parse_command, tokenize, AnnotateParams, DatagenParams, GoParams, PuzzleParams, UciCommand};
 use crate::utils::hydrate_move_from_algebraic_move;
 
 fn replace_shortcuts(l: &str) -> &str {
@@ -79,92 +90,71 @@ pub fn run_command_test(uci_state: &mut UciState, search_state: &mut SearchState
 /// Run a command synchronously (blocking). Used for benchmarking where we need
 /// to read results from search_state after the search completes.
 pub fn run_command_sync(uci_state: &mut UciState, search_state: &mut SearchState, l: &str) -> Either<String, Option<String>> {
-    let mut trimmed_line = replace_shortcuts(l).trim().replace("  ", " ");
-    if trimmed_line.starts_with("position startpos") {
-        trimmed_line = trimmed_line.replace("startpos", &("fen ".to_string() + START_POS));
-    }
-    let parts = trimmed_line.split(' ').collect::<Vec<&str>>();
+    let command = match parse_command(replace_shortcuts(l.trim())) {
+        Ok(command) => command,
+        Err(error) => return Left(error),
+    };
 
-    match *parts.first().unwrap() {
-        "go" => cmd_go_sync(uci_state, search_state, parts),
-        "ucinewgame" => {
+    match command {
+        UciCommand::Go(params) => cmd_go_sync(uci_state, search_state, params),
+        UciCommand::UciNewGame => {
             // Simplified ucinewgame for sync mode (no search handle needed)
             search_state.nodes = 0;
             search_state.root_moves.clear();
-            search_state.pv.clear();
+            search_state.pv_lines.clear();
             search_state.hash_table.clear();
             uci_state.fen = START_POS.parse().unwrap();
             Right(None)
         }
-        "position" => cmd_position(uci_state, search_state, parts),
         _ => {
             let mut search_handle: Option<SearchHandle> = None;
-            run_command(uci_state, search_state, &mut search_handle, l)
+            execute_command(uci_state, search_state, &mut search_handle, command)
         }
     }
 }
 
 /// Synchronous version of cmd_go for benchmarking
-fn cmd_go_sync(uci_state: &mut UciState, search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, Option<String>> {
-    let t = parts.get(1).unwrap();
+fn cmd_go_sync(uci_state: &mut UciState, search_state: &mut SearchState, params: GoParams) -> Either<String, Option<String>> {
     search_state.nodes = 0;
-    search_state.nodes_limit = u64::MAX;
     set_stop(&search_state.stop, false);
 
-    match *t {
-        "perft" => {
-            let depth = parts.get(2).unwrap().to_string().parse().unwrap();
-            cmd_perft(depth, uci_state);
-            Right(None)
-        }
-        "infinite" => {
-            let mut position = get_position(uci_state.fen.trim());
-            search_state.end_time = Instant::now().add(Duration::from_secs(86400));
-            let mv = iterative_deepening(&mut position, 200, search_state);
-            Right(Some(format_bestmove(mv, search_state)))
-        }
-        "mate" => {
-            let mate_depth = parts.get(2).and_then(|s| s.parse::<u8>().ok()).unwrap_or(100);
-            let mut position = get_position(uci_state.fen.trim());
-            search_state.end_time = Instant::now().add(Duration::from_secs(86400));
-            let mv = iterative_deepening(&mut position, mate_depth.saturating_mul(2), search_state);
-            Right(Some(format_bestmove(mv, search_state)))
-        }
-        _ => {
-            let line = parts.join(" ");
-            uci_state.wtime = extract_go_param("wtime", &line, 0);
-            uci_state.btime = extract_go_param("btime", &line, 0);
-            uci_state.winc = extract_go_param("winc", &line, 0);
-            uci_state.binc = extract_go_param("binc", &line, 0);
-            uci_state.moves_to_go = extract_go_param("movestogo", &line, 0);
-            uci_state.depth = extract_go_param("depth", &line, 250);
-            uci_state.nodes = extract_go_param("nodes", &line, u64::MAX);
-            uci_state.move_time = extract_go_param("movetime", &line, 10000000);
-
-            search_state.nodes_limit = uci_state.nodes;
+    if let Some(depth) = params.perft {
+        return cmd_perft(depth, params.perft_divide, uci_state, search_state);
+    }
 
-            let mut position = get_position(uci_state.fen.trim());
+    let mut position = get_position(uci_state.fen.trim());
 
-            // Parse searchmoves if present
-            search_state.search_moves = parse_searchmoves(&line, &position);
+    let limits = search_limits_from_go(uci_state, &params, position.mover, search_state.move_overhead);
+    search_state.search_moves = parse_searchmoves(&params.search_moves, &position);
+    limits.apply(search_state);
 
-            if position.mover == WHITE {
-                calc_from_colour_times(uci_state, uci_state.wtime, uci_state.winc);
-            } else {
-                calc_from_colour_times(uci_state, uci_state.btime, uci_state.binc);
-            }
-
-            uci_state.move_time = max(10, uci_state.move_time - min(uci_state.move_time, UCI_MILLIS_REDUCTION as u64));
+    let mv = if limits.limit_strength() {
+        search_with_strength(&mut position, limits.depth, search_state)
+    } else {
+        iterative_deepening(&mut position, limits.depth, search_state)
+    };
 
-            search_state.end_time = Instant::now().add(Duration::from_millis(uci_state.move_time));
-            let mv = iterative_deepening(&mut position, uci_state.depth as u8, search_state);
+    // Clear search_moves and mate_search after search completes
+    search_state.search_moves = None;
+    search_state.mate_search = None;
 
-            // Clear search_moves after search completes
-            search_state.search_moves = None;
+    Right(Some(format_bestmove(mv, search_state)))
+}
 
-            Right(Some(format_bestmove(mv, search_state)))
-        }
-    }
+/// Store the parameters of a `go` in the UCI state and return the limits of the search
+fn search_limits_from_go(uci_state: &mut UciState, params: &GoParams, mover: Mover, move_overhead: u64) -> SearchLimits {
+    let limits = SearchLimits::from_go(params, mover, move_overhead);
+    uci_state.wtime = params.wtime.unwrap_or(0);
+    uci_state.btime = params.btime.unwrap_or(0);
+    uci_state.winc = params.winc.unwrap_or(0);
+    uci_state.binc = params.binc.unwrap_or(0);
+    uci_state.moves_to_go = params.moves_to_go.unwrap_or(0);
+    uci_state.depth = limits.depth as u64;
+    uci_state.nodes = limits.nodes;
+    uci_state.mate = limits.mate.is_some();
+    uci_state.infinite = limits.infinite;
+    uci_state.move_time = limits.hard_millis.unwrap_or(UNLIMITED_MILLIS);
+    limits
 }
 
 pub fn run_command(
@@ -173,131 +163,88 @@ pub fn run_command(
     search_handle: &mut Option<SearchHandle>,
     l: &str,
 ) -> Either<String, Option<String>> {
-    let mut trimmed_line = replace_shortcuts(l).trim().replace("  ", " ");
-    if trimmed_line.starts_with("position startpos") {
-        trimmed_line = trimmed_line.replace("startpos", &("fen ".to_string() + START_POS));
+    match parse_command(replace_shortcuts(l.trim())) {
+        Ok(command) => execute_command(uci_state, search_state, search_handle, command),
+        Err(error) => Left(error),
     }
-    let parts = trimmed_line.split(' ').collect::<Vec<&str>>();
-
-    match *parts.first().unwrap() {
-        "bench" => cmd_benchmark(uci_state, search_state, parts),
-        "uci" => cmd_uci(),
-        "isready" => cmd_isready(),
-        "state" => cmd_state(uci_state, search_state),
-        "go" => cmd_go(uci_state, search_state, search_handle, parts),
-        "stop" => cmd_stop(search_handle),
-        "setoption" => cmd_setoption(parts, search_state),
-        "register" => cmd_register(),
-        "ucinewgame" => cmd_ucinewgame(uci_state, search_state, search_handle),
-        "debug" => cmd_debug(uci_state, parts),
-        "quit" => {
+}
+
+fn execute_command(
+    uci_state: &mut UciState,
+    search_state: &mut SearchState,
+    search_handle: &mut Option<SearchHandle>,
+    command: UciCommand,
+) -> Either<String, Option<String>> {
+    match command {
+        UciCommand::Empty => Right(None),
+        UciCommand::Bench(millis) => cmd_benchmark(uci_state, search_state, millis),
+        UciCommand::Uci => cmd_uci(),
+        UciCommand::IsReady => cmd_isready(),
+        UciCommand::State => cmd_state(uci_state, search_state),
+        UciCommand::Go(params) => cmd_go(uci_state, search_state, search_handle, params),
+        UciCommand::Stop => cmd_stop(search_handle),
+        UciCommand::SetOption { name, value } => cmd_setoption(&name, value.as_deref(), search_state),
+        UciCommand::Register => cmd_register(),
+        UciCommand::UciNewGame => cmd_ucinewgame(uci_state, search_state, search_handle),
+        UciCommand::Debug(on) => cmd_debug(uci_state, on),
+        UciCommand::Quit => {
             // Stop any running search before quitting
             if let Some(handle) = search_handle.take() {
                 handle.stop_and_wait();
             }
             exit(0)
         }
-        "mvm" => cmd_mvm(search_state, parts),
-        "position" => cmd_position(uci_state, search_state, parts),
-        _ => Left("Unknown command".parse().unwrap()),
+        UciCommand::Mvm { millis, games } => cmd_mvm(search_state, millis, games),
+        UciCommand::Datagen(params) => cmd_datagen(search_state, &params),
+        UciCommand::Annotate(params) => cmd_annotate(search_state, &params),
+        UciCommand::Puzzles(params) => cmd_puzzles(search_state, &params),
+        UciCommand::Position { fen, moves } => cmd_position(uci_state, search_state, &fen, &moves),
     }
 }
 
-fn fen_and_moves(parts: Vec<&str>) -> (String, Vec<String>) {
-    if !parts.contains(&"moves") {
-        let fen = parts.join(" ").replace("position fen", "");
-        return (fen.trim().parse().unwrap(), vec![]);
-    }
-    let fen_and_moves_string = parts.join(" ").replace("position fen", "");
-    let two_parts = fen_and_moves_string.split("moves").collect::<Vec<&str>>();
-    let fen = two_parts[0];
-    let moves = two_parts[1]
-        .trim()
-        .split(' ')
-        .collect::<Vec<&str>>()
-        .into_iter()
-        .map(|move_string| move_string.to_string())
-        .collect();
-
-    (fen.trim().parse().unwrap(), moves)
-}
-
 pub fn is_legal_move(position: &Position, algebraic_move: &str) -> bool {
-    let moves = generate_moves(position);
-    for m in moves {
-        let am = algebraic_move_from_move(m);
-        if am == algebraic_move {
-            let mut new_position = *position;
-            make_move(position, m, &mut new_position);
-            if !is_check(&new_position, position.mover) {
-                return true;
-            }
-        }
-    }
-    false
+    generate_legal_moves(position)
+        .iter()
+        .any(|m| algebraic_move_from_move(*m) == algebraic_move)
 }
 
-fn cmd_position(uci_state: &mut UciState, search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, Option<String>> {
-    //    cmd_ucinewgame(uci_state, search_state);
-    let t = parts.get(1).unwrap();
-    match *t {
-        "fen" => {
-            search_state.history = vec![];
-
-            let re = Regex::new(
-                r"\s*^(((?:[rnbqkpRNBQKP1-8]+/){7})[rnbqkpRNBQKP1-8]+)\s([b|w])\s([K|Q|k|q]{1,4}|-)\s(-|[a-h][1-8])\s(\d+\s\d+)$",
-            )
-            .unwrap();
-            let (fen, moves) = fen_and_moves(parts);
-
-            uci_state.fen = fen.parse().unwrap();
-
-            if re.is_match(&fen) {
-                uci_state.fen = fen;
-                let mut position = get_position(&uci_state.fen);
-                let mut new_position = position;
-                search_state.history.push(new_position.zobrist_lock);
-                if !moves.is_empty() {
-                    for m in moves {
-                        if !is_legal_move(&new_position, &m) {
-                            return Left("Illegal move found".parse::<String>().unwrap() + " " + &*m);
-                        }
-                        let hydrated_move = hydrate_move_from_algebraic_move(&position, m.to_string());
-                        make_move(&position, hydrated_move, &mut new_position);
-                        search_state.history.push(new_position.zobrist_lock);
-                        position = new_position
-                    }
-                }
-                uci_state.fen = get_fen(&position);
-                Right(None)
-            } else {
-                Left("Invalid FEN".parse().unwrap())
-            }
+fn cmd_position(uci_state: &mut UciState, search_state: &mut SearchState, fen: &str, moves: &[String]) -> Either<String, Option<String>> {
+    let mut position = match try_get_position(fen) {
+        Ok(position) => position,
+        Err(e) => return Left(format!("Invalid FEN: {}", e)),
+    };
+
+    search_state.history = vec![];
+    let mut new_position = position;
+    search_state.history.push(new_position.zobrist_lock);
+    for m in moves {
+        if !is_legal_move(&new_position, m) {
+            return Left("Illegal move found".parse::<String>().unwrap() + " " + m);
         }
-        _ => Left("Unknown position command".parse().unwrap()),
+        let hydrated_move = hydrate_move_from_algebraic_move(&position, m.to_string());
+        make_move(&position, hydrated_move, &mut new_position);
+        search_state.history.push(new_position.zobrist_lock);
+        position = new_position
     }
+    uci_state.fen = get_fen(&position);
+    Right(None)
 }
 
+/// Find the numeric value following `needle` in a whitespace-separated command line
 pub fn extract_go_param(needle: &str, haystack: &str, default: u64) -> u64 {
-    let re = r"".to_string() + &*needle.to_string() + &*" ([0-9]*)".to_string();
-    let regex = Regex::new(&re).unwrap();
-    let caps = regex.captures(haystack);
-    match caps {
-        Some(x) => {
-            let s = x.get(1).unwrap().as_str();
-            s.parse::<u64>().unwrap()
-        }
-        None => default,
-    }
+    tokenize(haystack)
+        .windows(2)
+        .find(|pair| pair[0] == needle)
+        .and_then(|pair| pair[1].parse::<u64>().ok())
+        .unwrap_or(default)
 }
 
 fn cmd_state(mut _uci_state: &mut UciState, search_state: &mut SearchState) -> Either<String, Option<String>> {
-    Right(Some(format!(r#"Nodes {}"#, search_state.nodes)))
+    let stats = *search_state.last_search_stats.lock().unwrap();
+    Right(Some(format!("Nodes {}\n{}", stats.nodes + stats.qsearch_nodes, stats)))
 }
 
-fn cmd_mvm(search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, Option<String>> {
-    let millis = parts.get(1).unwrap().to_string().parse().unwrap();
-    let count = parts.get(2).unwrap().to_string().parse().unwrap();
+fn cmd_mvm(search_state: &mut SearchState, millis: u64, count: u32) -> Either<String, Option<String>> {
     let mut engine_1_wins = 0;
     let mut engine_2_wins = 0;
     let mut draws = 0;
@@ -313,15 +260,7 @@ fn cmd_mvm(search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, O
             let mut new_position = position;
             make_move(&position, mv, &mut new_position);
 
-            let mut legal_move_count = 0;
-            for m in generate_moves(&new_position) {
-                let mut p = new_position;
-                make_move(&new_position, m, &mut p);
-                if !is_check(&p, new_position.mover) {
-                    legal_move_count += 1;
-                }
-            }
-            if new_position.half_moves > 100 || legal_move_count == 0 {
+            if new_position.half_moves > 100 || generate_legal_moves(&new_position).is_empty() {
                 break new_position;
             }
 
@@ -340,62 +279,118 @@ fn cmd_mvm(search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, O
     Right(Some("Done".parse().unwrap()))
 }
 
+fn cmd_datagen(search_state: &SearchState, params: &DatagenParams) -> Either<String, Option<String>> {
+    let mut writer = match File::create(&params.file) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => return Left(format!("Cannot create {}: {}", params.file, e)),
+    };
+    match run_datagen(params, search_state, &mut writer).and_then(|positions| writer.flush().map(|_| positions)) {
+        Ok(positions) => Right(Some(format!(
+            "Wrote {} positions from {} games to {}",
+            positions, params.games, params.file
+        ))),
+        Err(e) => Left(format!("Cannot write {}: {}", params.file, e)),
+    }
+}
+
+fn cmd_annotate(search_state: &mut SearchState, params: &AnnotateParams) -> Either<String, Option<String>> {
+    let text = match fs::read_to_string(&params.file) {
+        Ok(text) => text,
+        Err(e) => return Left(format!("Cannot read {}: {}", params.file, e)),
+    };
+    let games = parse_pgn(&text);
+
+    // Annotating replaces the game history, so keep the one from the last position command
+    let history = std::mem::take(&mut search_state.history);
+    let show_info = search_state.show_info;
+    search_state.show_info = false;
+    let annotated: Result<String, String> = games
+        .iter()
+        .enumerate()
+        .map(|(i, game)| {
+            annotate_game(game, params, search_state)
+                .and_then(|annotations| annotated_pgn(game, &annotations, params))
+                .map_err(|e| format!("Game {}: {}", i + 1, e))
+        })
+        .collect();
+    search_state.history = history;
+    search_state.show_info = show_info;
+
+    match (annotated, &params.out) {
+        (Err(e), _) => Left(e),
+        (Ok(pgn), None) => Right(Some(pgn.trim_end().to_string())),
+        (Ok(pgn), Some(out)) => match fs::write(out, pgn) {
+            Ok(()) => Right(Some(format!("Annotated {} games to {}", games.len(), out))),
+            Err(e) => Left(format!("Cannot write {}: {}", out, e)),
+        },
+    }
+}
+
+fn cmd_puzzles(search_state: &mut SearchState, params: &PuzzleParams) -> Either<String, Option<String>> {
+    let text = match fs::read_to_string(&params.file) {
+        Ok(text) => text,
+        Err(e) => return Left(format!("Cannot read {}: {}", params.file, e)),
+    };
+    let games = parse_pgn(&text);
+
+    // Mining puzzles replaces the game history, so keep the one from the last position command
+    let history = std::mem::take(&mut search_state.history);
+    let show_info = search_state.show_info;
+    search_state.show_info = false;
+    let puzzles: Result<Vec<String>, String> = games
+        .iter()
+        .enumerate()
+        .map(|(i, game)| {
+            game_puzzles(game, i + 1, params, search_state)
+                .map(|puzzles| puzzles.iter().map(|p| p.epd()).collect::<Vec<String>>())
+                .map_err(|e| format!("Game {}: {}", i + 1, e))
+        })
+        .collect::<Result<Vec<Vec<String>>, String>>()
+        .map(|puzzles| puzzles.concat());
+    search_state.history = history;
+    search_state.show_info = show_info;
+
+    match (puzzles, &params.out) {
+        (Err(e), _) => Left(e),
+        (Ok(puzzles), None) if puzzles.is_empty() => Right(Some(format!("No puzzles in {} games", games.len()))),
+        (Ok(puzzles), None) => Right(Some(puzzles.join("\n"))),
+        (Ok(puzzles), Some(out)) => match fs::write(out, puzzles.iter().map(|p| format!("{}\n", p)).collect::<String>()) {
+            Ok(()) => Right(Some(format!(
+                "Wrote {} puzzles from {} games to {}",
+                puzzles.len(),
+                games.len(),
+                out
+            ))),
+            Err(e) => Left(format!("Cannot write {}: {}", out, e)),
+        },
+    }
+}
+
 fn cmd_go(
     uci_state: &mut UciState,
     search_state: &mut SearchState,
     search_handle: &mut Option<SearchHandle>,
-    parts: Vec<&str>,
+    params: GoParams,
 ) -> Either<String, Option<String>> {
     // If there's already a search running, wait for it first
     if let Some(handle) = search_handle.take() {
         handle.stop_and_wait();
     }
 
-    let t = parts.get(1).unwrap();
-
     // perft runs synchronously (no threading needed)
-    if *t == "perft" {
-        let depth = parts.get(2).unwrap().to_string().parse().unwrap();
-        cmd_perft(depth, uci_state);
-        return Right(None);
+    if let Some(depth) = params.perft {
+        return cmd_perft(depth, params.perft_divide, uci_state, search_state);
     }
 
-    // Parse go parameters - calculate move_time_ms to pass to thread
-    // The actual end_time will be calculated INSIDE the thread to avoid
-    // counting clone/spawn overhead against search time
-    let line = parts.join(" ");
-    let (max_depth, move_time_ms, nodes_limit) = if *t == "infinite" {
-        (200u8, 86400 * 1000u64, u64::MAX) // 24 hours in ms
-    } else if *t == "mate" {
-        let mate_depth = parts.get(2).and_then(|s| s.parse::<u8>().ok()).unwrap_or(100);
-        (mate_depth.saturating_mul(2), 86400 * 1000u64, u64::MAX)
-    } else {
-        uci_state.wtime = extract_go_param("wtime", &line, 0);
-        uci_state.btime = extract_go_param("btime", &line, 0);
-        uci_state.winc = extract_go_param("winc", &line, 0);
-        uci_state.binc = extract_go_param("binc", &line, 0);
-        uci_state.moves_to_go = extract_go_param("movestogo", &line, 0);
-        uci_state.depth = extract_go_param("depth", &line, 250);
-        uci_state.nodes = extract_go_param("nodes", &line, u64::MAX);
-        uci_state.move_time = extract_go_param("movetime", &line, 10000000);
-
-        let position = get_position(uci_state.fen.trim());
-        if position.mover == WHITE {
-            calc_from_colour_times(uci_state, uci_state.wtime, uci_state.winc);
-        } else {
-            calc_from_colour_times(uci_state, uci_state.btime, uci_state.binc);
-        }
-
-        uci_state.move_time = max(10, uci_state.move_time - min(uci_state.move_time, UCI_MILLIS_REDUCTION as u64));
-
-        (uci_state.depth as u8, uci_state.move_time, uci_state.nodes)
-    };
-
     // Clone position for the search thread
     let mut position = get_position(uci_state.fen.trim());
 
+    // The limits are applied INSIDE the thread, so that clone/spawn overhead
+    // isn't counted against search time
+    let limits = search_limits_from_go(uci_state, &params, position.mover, search_state.move_overhead);
+
     // Parse searchmoves if present
-    let search_moves = parse_searchmoves(&line, &position);
+    let search_moves = parse_searchmoves(&params.search_moves, &position);
 
     // Create a new stop flag for this search
     let stop_flag = Arc::new(AtomicBool::new(false));
@@ -404,19 +399,26 @@ fn cmd_go(
     // Note: hash_table is shared via Arc (no 128MB copy!)
     let mut thread_search_state = search_state.clone();
     thread_search_state.nodes = 0;
-    thread_search_state.nodes_limit = nodes_limit;
     thread_search_state.stop = stop_flag.clone();
     thread_search_state.search_moves = search_moves;
+    #[cfg(feature = "move-validation")]
+    let debug = uci_state.debug;
 
     // Spawn the search thread with a larger stack size to prevent stack overflow
     // during deep searches (default 2MB is not enough for very deep positions)
     let handle = thread::Builder::new()
         .stack_size(16 * 1024 * 1024) // 16 MB stack (matches RUST_MIN_STACK recommendation)
         .spawn(move || {
-            // Calculate end_time HERE inside the thread - this ensures clone/spawn
+            #[cfg(feature = "move-validation")]
+            set_move_validation(debug);
+            // Set end_time HERE inside the thread - this ensures clone/spawn
             // overhead doesn't eat into search time
-            thread_search_state.end_time = Instant::now().add(Duration::from_millis(move_time_ms));
-            let mv = iterative_deepening(&mut position, max_depth, &mut thread_search_state);
+            limits.apply(&mut thread_search_state);
+            let mv = if limits.limit_strength() {
+                search_with_strength(&mut position, limits.depth, &mut thread_search_state)
+            } else {
+                iterative_deepening(&mut position, limits.depth, &mut thread_search_state)
+            };
             println!("{}", format_bestmove(mv, &thread_search_state));
         })
         .expect("Failed to spawn search thread");
@@ -427,48 +429,19 @@ fn cmd_go(
     Right(None)
 }
 
-/// Parse searchmoves from go command line
+/// Convert the searchmoves of a go command into moves, skipping any that are not legal
 /// Returns None if searchmoves not specified, Some(vec) with the moves otherwise
-fn parse_searchmoves(line: &str, position: &Position) -> Option<Vec<Move>> {
-    // Find "searchmoves" keyword
-    if let Some(idx) = line.find("searchmoves") {
-        let after_keyword = &line[idx + "searchmoves".len()..];
-        let mut moves = Vec::new();
-
-        // UCI keywords that end the searchmoves list
-        let keywords = [
-            "ponder",
-            "wtime",
-            "btime",
-            "winc",
-            "binc",
-            "movestogo",
-            "depth",
-            "nodes",
-            "mate",
-            "movetime",
-            "infinite",
-        ];
-
-        for token in after_keyword.split_whitespace() {
-            // Stop if we hit another keyword
-            if keywords.contains(&token) {
-                break;
-            }
-            // Parse move and add to list
-            let mv = hydrate_move_from_algebraic_move(position, token.to_string());
-            if mv != 0 {
-                moves.push(mv);
-            }
-        }
+fn parse_searchmoves(search_moves: &[String], position: &Position) -> Option<Vec<Move>> {
+    let moves: Vec<Move> = search_moves
+        .iter()
+        .filter(|m| is_legal_move(position, m))
+        .map(|m| hydrate_move_from_algebraic_move(position, m.to_string()))
+        .collect();
 
-        if moves.is_empty() {
-            None
-        } else {
-            Some(moves)
-        }
-    } else {
+    if moves.is_empty() {
         None
+    } else {
+        Some(moves)
     }
 }
 
@@ -491,16 +464,6 @@ fn format_bestmove(mv: u32, search_state: &SearchState) -> String {
     format!("bestmove {}", bestmove)
 }
 
-fn calc_from_colour_times(uci_state: &mut UciState, millis: u64, inc_millis: u64) {
-    if millis > 0 {
-        // When movestogo is not specified (0), assume 30 moves remaining
-        // Previously this used ALL remaining time on the first move!
-        let moves_remaining = if uci_state.moves_to_go == 0 { 30 } else { uci_state.moves_to_go };
-        uci_state.move_time = min(uci_state.move_time, (millis as f64 / (moves_remaining as f64 + 1.0)) as u64);
-        uci_state.move_time = (uci_state.move_time as f64 * 0.95) as u64 + inc_millis;
-    }
-}
-
 fn cmd_uci() -> Either<String, Option<String>> {
     Right(Some(format!(
         "id name Rusty Rival {}
@@ -510,8 +473,23 @@ option name Clear Hash type button
 option name MultiPV type spin default 1 min 1 max 20
 option name Contempt type spin default 0 min -1000 max 1000
 option name SyzygyPath type string default <empty>
+option name UCI_LimitStrength type check default false
+option name UCI_Elo type spin default {} min {} max {}
+option name Skill Level type spin default {} min 0 max {}
+option name Move Overhead type spin default {} min 0 max {}
+option name UCI_ShowWDL type check default false
+option name SearchFeatures type string default {}
+option name Deterministic type check default false
 uciok",
-        env!("CARGO_PKG_VERSION")
+        env!("CARGO_PKG_VERSION"),
+        DEFAULT_ELO,
+        MIN_ELO,
+        MAX_ELO,
+        MAX_SKILL_LEVEL,
+        MAX_SKILL_LEVEL,
+        DEFAULT_MOVE_OVERHEAD_MILLIS,
+        MAX_MOVE_OVERHEAD_MILLIS,
+        SearchFeatures::default()
     )))
 }
 
@@ -519,82 +497,156 @@ fn cmd_isready() -> Either<String, Option<String>> {
     Right(Some("readyok".parse().unwrap()))
 }
 
-fn cmd_debug(uci_state: &mut UciState, parts: Vec<&str>) -> Either<String, Option<String>> {
-    if parts.len() != 2 || !["on", "off"].contains(&parts[1]) {
-        return Left::<String, Option<String>>("usage: debug [on|off]".parse().unwrap());
-    }
-
-    uci_state.debug = parts[1] == "on";
+fn cmd_debug(uci_state: &mut UciState, on: bool) -> Either<String, Option<String>> {
+    uci_state.debug = on;
+    #[cfg(feature = "move-validation")]
+    set_move_validation(on);
 
     Right(None)
 }
 
-fn cmd_perft(depth: u8, uci_state: &UciState) -> Either<String, Option<String>> {
+fn cmd_perft(depth: u8, divide: bool, uci_state: &UciState, search_state: &mut SearchState) -> Either<String, Option<String>> {
     let start = Instant::now();
-    let nodes = perft(&mut get_position(uci_state.fen.trim()), depth - 1);
+    // Take as much memory as the main hash table
+    let entries = search_state.hash_table.len() * HASH_ENTRY_BYTES as usize / PERFT_HASH_ENTRY_BYTES;
+    let hash_table = search_state
+        .perft_hash_table
+        .get_or_insert_with(|| Arc::new(PerftHashTable::new(entries)));
+    let counts = perft_divide_parallel(&get_position(uci_state.fen.trim()), depth - 1, hash_table);
+    let nodes: u64 = counts.iter().map(|(_, n)| n).sum();
     let duration = start.elapsed();
-    println!("Time elapsed in perft is: {:?}", duration);
-    println!("{} nodes {} nps", nodes, (nodes as f64 / (duration.as_millis() as f64)) * 1000.0);
+    if divide {
+        // The format tools such as perftree expect
+        for (m, count) in counts {
+            println!("{}: {}", algebraic_move_from_move(m), count);
+        }
+        println!();
+        println!("Nodes searched: {}", nodes);
+    } else {
+        println!("Time elapsed in perft is: {:?}", duration);
+        println!("{} nodes {} nps", nodes, (nodes as f64 / (duration.as_millis() as f64)) * 1000.0);
+    }
     Right(None)
 }
 
-fn cmd_setoption(parts: Vec<&str>, search_state: &mut SearchState) -> Either<String, Option<String>> {
-    if parts.len() < 3 || parts[1] != "name" {
-        Left("usage: setoption name <name> [value <value>]".parse().unwrap())
-    } else {
-        let option = parts[2].to_lowercase();
-        match option.as_str() {
-            "hash" => {
-                if parts.len() == 5 && parts[3] == "value" {
-                    match parts[4].parse::<usize>() {
-                        Ok(mb) if (1..=16384).contains(&mb) => {
-                            search_state.hash_table = Arc::new(SharedHashTable::new_with_mb(mb));
-                            search_state.hash_table_version += 1;
-                            Right(None)
-                        }
-                        _ => Left("Hash size must be between 1 and 16384 MB".parse().unwrap()),
-                    }
-                } else {
-                    Left("usage: setoption name Hash value <MB>".parse().unwrap())
+fn cmd_setoption(name: &str, value: Option<&str>, search_state: &mut SearchState) -> Either<String, Option<String>> {
+    match name.to_lowercase().as_str() {
+        "hash" => match value {
+            Some(v) => match v.parse::<usize>() {
+                Ok(mb) if (1..=16384).contains(&mb) => {
+                    search_state.hash_table = Arc::new(SharedHashTable::new_with_mb(mb));
+                    search_state.hash_table_version += 1;
+                    search_state.perft_hash_table = None;
+                    Right(None)
                 }
+                _ => Left("Hash size must be between 1 and 16384 MB".parse().unwrap()),
+            },
+            None => Left("usage: setoption name Hash value <MB>".parse().unwrap()),
+        },
+        "clear hash" => {
+            search_state.hash_table.clear();
+            Right(None)
+        }
+        "multipv" => match value.map(|v| v.parse::<u8>()) {
+            Some(Ok(multi_pv)) if (1..=20).contains(&multi_pv) => {
+                search_state.multi_pv = multi_pv;
+                Right(None)
             }
-            "clear" => {
-                search_state.hash_table.clear();
+            Some(_) => Left("MultiPV must be between 1 and 20".parse().unwrap()),
+            None => Left("usage: setoption name MultiPV value <lines>".parse().unwrap()),
+        },
+        "contempt" => match value.map(|v| v.parse::<Score>()) {
+            Some(Ok(contempt)) if (-1000..=1000).contains(&contempt) => {
+                search_state.contempt = contempt;
                 Right(None)
             }
-            "multipv" => {
-                if parts.len() == 5 {
-                    search_state.multi_pv = parts[4].parse().unwrap();
+            Some(_) => Left("Contempt must be between -1000 and 1000".parse().unwrap()),
+            None => Left("usage: setoption name Contempt value <centipawns>".parse().unwrap()),
+        },
+        "syzygypath" => match value {
+            Some(path) if !path.is_empty() => match init_tablebase(path) {
+                Ok(count) => {
+                    println!("info string Loaded {} tablebase files from {}", count, path);
                     Right(None)
-                } else {
-                    Left("Invalid option command".parse().unwrap())
                 }
+                Err(e) => Left(format!("Failed to load tablebases: {}", e)),
+            },
+            _ => Left("usage: setoption name SyzygyPath value <path>".parse().unwrap()),
+        },
+        "uci_limitstrength" => match value {
+            Some("true") => {
+                search_state.strength.limit_strength = true;
+                Right(None)
             }
-            "contempt" => {
-                if parts.len() == 5 {
-                    search_state.contempt = parts[4].parse().unwrap();
-                    Right(None)
-                } else {
-                    Left("Invalid option command".parse().unwrap())
-                }
+            Some("false") => {
+                search_state.strength.limit_strength = false;
+                Right(None)
             }
-            "syzygypath" => {
-                // Handle path with spaces by joining everything after "value"
-                if parts.len() >= 5 && parts[3] == "value" {
-                    let path = parts[4..].join(" ");
-                    match init_tablebase(&path) {
-                        Ok(count) => {
-                            println!("info string Loaded {} tablebase files from {}", count, path);
-                            Right(None)
-                        }
-                        Err(e) => Left(format!("Failed to load tablebases: {}", e)),
-                    }
-                } else {
-                    Left("usage: setoption name SyzygyPath value <path>".parse().unwrap())
-                }
+            _ => Left("usage: setoption name UCI_LimitStrength value [true|false]".parse().unwrap()),
+        },
+        "uci_showwdl" => match value {
+            Some("true") => {
+                search_state.show_wdl = true;
+                Right(None)
             }
-            _ => Left("Unknown option".parse().unwrap()),
-        }
+            Some("false") => {
+                search_state.show_wdl = false;
+                Right(None)
+            }
+            _ => Left("usage: setoption name UCI_ShowWDL value [true|false]".parse().unwrap()),
+        },
+        "deterministic" => match value {
+            Some("true") => {
+                search_state.deterministic = true;
+                Right(None)
+            }
+            Some("false") => {
+                search_state.deterministic = false;
+                Right(None)
+            }
+            _ => Left("usage: setoption name Deterministic value [true|false]".parse().unwrap()),
+        },
+        "uci_elo" => match value.map(|v| v.parse::<u32>()) {
+            Some(Ok(elo)) if (MIN_ELO..=MAX_ELO).contains(&elo) => {
+                search_state.strength.elo = elo;
+                Right(Some(format!(
+                    "info string UCI_Elo is nominal and uncalibrated, {} plays at Skill Level {}",
+                    elo,
+                    elo_to_skill_level(elo)
+                )))
+            }
+            Some(_) => Left(format!("UCI_Elo must be between {} and {}", MIN_ELO, MAX_ELO)),
+            None => Left("usage: setoption name UCI_Elo value <elo>".parse().unwrap()),
+        },
+        "move overhead" => match value.map(|v| v.parse::<u64>()) {
+            Some(Ok(millis)) if millis <= MAX_MOVE_OVERHEAD_MILLIS => {
+                search_state.move_overhead = millis;
+                Right(None)
+            }
+            Some(_) => Left(format!("Move Overhead must be between 0 and {}", MAX_MOVE_OVERHEAD_MILLIS)),
+            None => Left("usage: setoption name Move Overhead value <millis>".parse().unwrap()),
+        },
+        "skill level" => match value.map(|v| v.parse::<u8>()) {
+            Some(Ok(level)) if level <= MAX_SKILL_LEVEL => {
+                search_state.strength.skill_level = level;
+                Right(None)
+            }
+            Some(_) => Left(format!("Skill Level must be between 0 and {}", MAX_SKILL_LEVEL)),
+            None => Left("usage: setoption name Skill Level value <level>".parse().unwrap()),
+        },
+        "searchfeatures" => match value.map(SearchFeatures::parse) {
+            Some(Ok(features)) => {
+                search_state.search_features = features;
+                Right(None)
+            }
+            Some(Err(e)) => Left(e),
+            None => Left(
+                "usage: setoption name SearchFeatures value <bitmask | [-]feature ...>"
+                    .parse()
+                    .unwrap(),
+            ),
+        },
+        _ => Left("Unknown option".parse().unwrap()),
     }
 }
 
@@ -613,10 +665,10 @@ fn cmd_ucinewgame(
     }
 
     search_state.nodes = 0;
-    // Clear root_moves and pv to prevent stale data from previous games
+    // Clear root_moves and pv_lines to prevent stale data from previous games
     // being output if time expires before the first search iteration completes
     search_state.root_moves.clear();
-    search_state.pv.clear();
+    search_state.pv_lines.clear();
     search_state.hash_table.clear();
     uci_state.fen = START_POS.parse().unwrap();
     Right(None)
@@ -4,23 +4,18 @@ use crate::types::{Move, Score, SearchState, UciState};
 use crate::uci::run_command_sync;
 use crate::utils::hydrate_move_from_algebraic_move;
 use ansi_term::Colour::{Green, Red, Yellow};
-use either::{Either, Left, Right};
+use either::{Either, Right};
 use num_format::{Locale, ToFormattedString};
 use std::thread;
 use std::time::Instant;
 
-pub fn cmd_benchmark(uci_state: &mut UciState, search_state: &mut SearchState, parts: Vec<&str>) -> Either<String, Option<String>> {
-    if parts.len() != 2 {
-        return Left::<String, Option<String>>("usage: bench <millis>".parse().unwrap());
-    }
-
+pub fn cmd_benchmark(uci_state: &mut UciState, search_state: &mut SearchState, millis: u32) -> Either<String, Option<String>> {
     let start = Instant::now();
     let positions = get_test_fens();
     let total = positions.len();
 
     let show_info = search_state.show_info;
     search_state.show_info = false;
-    let millis: u32 = parts.get(1).unwrap().to_string().parse().unwrap();
 
     let mut total_nodes = 0;
     let mut total_correct = 0;
@@ -0,0 +1,412 @@
+//! UCI command parsing
+//!
+//! Input lines are split into whitespace-separated tokens and parsed into a typed
+//! `UciCommand`. Parsing never panics: malformed input produces an error message
+//! which the caller reports back to the GUI as an `info string`.
+
+use crate::move_constants::START_POS;
+use crate::types::Score;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UciCommand {
+    Empty,
+    Uci,
+    IsReady,
+    Debug(bool),
+    SetOption { name: String, value: Option<String> },
+    Register,
+    UciNewGame,
+    Position { fen: String, moves: Vec<String> },
+    Go(GoParams),
+    Stop,
+    Quit,
+    State,
+    Bench(u32),
+    Mvm { millis: u64, games: u32 },
+    Datagen(DatagenParams),
+    Annotate(AnnotateParams),
+    Puzzles(PuzzleParams),
+}
+
+/// Parameters of a `go` command. Anything not given on the command line is `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GoParams {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    pub depth: Option<u64>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<u64>,
+    pub mate: Option<u8>,
+    pub perft: Option<u8>,
+    /// With `perft`, list the node count under each move
+    pub perft_divide: bool,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub search_moves: Vec<String>,
+}
+
+/// Parameters of a `datagen` command, with defaults for anything not given
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatagenParams {
+    pub games: u32,
+    /// Nodes searched for each move
+    pub nodes: u64,
+    pub threads: usize,
+    pub seed: u64,
+    /// Random legal plies played from the start position before the engine takes over
+    pub random_plies: u8,
+    /// Score in centipawns beyond which a game is adjudicated as won
+    pub adjudicate: Score,
+    pub file: String,
+}
+
+impl Default for DatagenParams {
+    fn default() -> Self {
+        DatagenParams {
+            games: 100,
+            nodes: 5000,
+            threads: 1,
+            seed: 0,
+            random_plies: 8,
+            adjudicate: 1500,
+            file: "datagen.txt".to_string(),
+        }
+    }
+}
+
+/// Parameters of an `annotate` command. Without a depth or move time, moves are searched to
+/// `DEFAULT_ANNOTATE_DEPTH`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotateParams {
+    pub file: String,
+    /// Where to write the annotated PGN, instead of returning it
+    pub out: Option<String>,
+    pub depth: Option<u8>,
+    pub move_time: Option<u64>,
+    /// Centipawns lost against the best move for a `?!`
+    pub inaccuracy: Score,
+    /// Centipawns lost against the best move for a `?`
+    pub mistake: Score,
+    /// Centipawns lost against the best move for a `??`
+    pub blunder: Score,
+}
+
+pub const DEFAULT_ANNOTATE_DEPTH: u8 = 12;
+
+impl Default for AnnotateParams {
+    fn default() -> Self {
+        AnnotateParams {
+            file: String::new(),
+            out: None,
+            depth: None,
+            move_time: None,
+            inaccuracy: 50,
+            mistake: 100,
+            blunder: 300,
+        }
+    }
+}
+
+/// Parameters of a `puzzles` command. Without a depth or move time, positions are searched to
+/// `DEFAULT_PUZZLE_DEPTH`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuzzleParams {
+    pub file: String,
+    /// Where to write the puzzles as EPD, instead of returning them
+    pub out: Option<String>,
+    pub depth: Option<u8>,
+    pub move_time: Option<u64>,
+    /// Centipawns the best move must be ahead of the second best, at every move of the solution
+    pub gap: Score,
+    /// Score from which the best move counts as winning
+    pub decisive: Score,
+    /// Scores within this of zero count as equal
+    pub equal: Score,
+    /// Most plies in the solution line
+    pub plies: u8,
+}
+
+pub const DEFAULT_PUZZLE_DEPTH: u8 = 10;
+
+impl Default for PuzzleParams {
+    fn default() -> Self {
+        PuzzleParams {
+            file: String::new(),
+            out: None,
+            depth: None,
+            move_time: None,
+            gap: 200,
+            decisive: 300,
+            equal: 50,
+            plies: 5,
+        }
+    }
+}
+
+pub const POSITION_USAGE: &str = "usage: position [startpos | fen <fen>] [moves <move> ...]";
+pub const SETOPTION_USAGE: &str = "usage: setoption name <name> [value <value>]";
+pub const ANNOTATE_USAGE: &str = "usage: annotate file <pgn> [out <pgn>] [depth <n>] [movetime <millis>] [inaccuracy <centipawns>] [mistake <centipawns>] [blunder <centipawns>]";
+pub const PUZZLES_USAGE: &str = "usage: puzzles file <pgn> [out <epd>] [depth <n>] [movetime <millis>] [gap <centipawns>] [decisive <centipawns>] [equal <centipawns>] [plies <n>]";
+pub const DATAGEN_USAGE: &str =
+    "usage: datagen [games <n>] [nodes <n>] [threads <n>] [seed <n>] [randomplies <n>] [adjudicate <centipawns>] [file <path>]";
+
+pub fn tokenize(line: &str) -> Vec<&str> {
+    line.split_whitespace().collect()
+}
+
+pub fn parse_command(line: &str) -> Result<UciCommand, String> {
+    let tokens = tokenize(line);
+    let (command, args) = match tokens.split_first() {
+        Some(split) => split,
+        None => return Ok(UciCommand::Empty),
+    };
+
+    match *command {
+        "uci" => Ok(UciCommand::Uci),
+        "isready" => Ok(UciCommand::IsReady),
+        "debug" => parse_debug(args),
+        "setoption" => parse_setoption(args),
+        "register" => Ok(UciCommand::Register),
+        "ucinewgame" => Ok(UciCommand::UciNewGame),
+        "position" => parse_position(args),
+        "go" => parse_go(args).map(UciCommand::Go),
+        "stop" => Ok(UciCommand::Stop),
+        "quit" => Ok(UciCommand::Quit),
+        "state" => Ok(UciCommand::State),
+        "bench" => parse_bench(args),
+        "mvm" => parse_mvm(args),
+        "datagen" => parse_datagen(args).map(UciCommand::Datagen),
+        "annotate" => parse_annotate(args).map(UciCommand::Annotate),
+        "puzzles" => parse_puzzles(args).map(UciCommand::Puzzles),
+        _ => Err("Unknown command".to_string()),
+    }
+}
+
+fn parse_debug(args: &[&str]) -> Result<UciCommand, String> {
+    match args {
+        ["on"] => Ok(UciCommand::Debug(true)),
+        ["off"] => Ok(UciCommand::Debug(false)),
+        _ => Err("usage: debug [on|off]".to_string()),
+    }
+}
+
+fn parse_setoption(args: &[&str]) -> Result<UciCommand, String> {
+    if args.len() < 2 || args[0] != "name" {
+        return Err(SETOPTION_USAGE.to_string());
+    }
+
+    let rest = &args[1..];
+    match rest.iter().position(|t| *t == "value") {
+        Some(0) => Err(SETOPTION_USAGE.to_string()),
+        Some(i) => Ok(UciCommand::SetOption {
+            name: rest[..i].join(" "),
+            value: Some(rest[i + 1..].join(" ")),
+        }),
+        None => Ok(UciCommand::SetOption {
+            name: rest.join(" "),
+            value: None,
+        }),
+    }
+}
+
+fn parse_position(args: &[&str]) -> Result<UciCommand, String> {
+    let (fen, rest) = match args.split_first() {
+        Some((&"startpos", rest)) => (START_POS.to_string(), rest),
+        Some((&"fen", rest)) => {
+            let fen_end = rest.iter().position(|t| *t == "moves").unwrap_or(rest.len());
+            if fen_end == 0 {
+                return Err("Invalid FEN".to_string());
+            }
+            (rest[..fen_end].join(" "), &rest[fen_end..])
+        }
+        Some(_) => return Err("Unknown position command".to_string()),
+        None => return Err(POSITION_USAGE.to_string()),
+    };
+
+    let moves = match rest.split_first() {
+        Some((&"moves", moves)) => moves.iter().map(|m| m.to_string()).collect(),
+        Some(_) => return Err(POSITION_USAGE.to_string()),
+        None => vec![],
+    };
+
+    Ok(UciCommand::Position { fen, moves })
+}
+
+/// Parse the arguments of a `go` command. Unknown tokens are ignored, as required by the
+/// UCI protocol, but a known keyword with a missing or malformed value is an error.
+pub fn parse_go(args: &[&str]) -> Result<GoParams, String> {
+    let mut params = GoParams::default();
+    let mut tokens = args.iter().peekable();
+
+    while let Some(&keyword) = tokens.next() {
+        match keyword {
+            "wtime" => params.wtime = Some(time_value(keyword, tokens.next())?),
+            "btime" => params.btime = Some(time_value(keyword, tokens.next())?),
+            "winc" => params.winc = Some(time_value(keyword, tokens.next())?),
+            "binc" => params.binc = Some(time_value(keyword, tokens.next())?),
+            "movetime" => params.move_time = Some(time_value(keyword, tokens.next())?),
+            "movestogo" => params.moves_to_go = Some(number_value(keyword, tokens.next())?),
+            "depth" => params.depth = Some(number_value(keyword, tokens.next())?),
+            "nodes" => params.nodes = Some(number_value(keyword, tokens.next())?),
+            "mate" => {
+                let moves: u8 = number_value(keyword, tokens.next())?;
+                if moves == 0 {
+                    return Err("mate depth must be at least 1".to_string());
+                }
+                params.mate = Some(moves);
+            }
+            "perft" => {
+                let depth: u8 = number_value(keyword, tokens.next())?;
+                if depth == 0 {
+                    return Err("perft depth must be at least 1".to_string());
+                }
+                params.perft = Some(depth);
+            }
+            "divide" => params.perft_divide = true,
+            "infinite" => params.infinite = true,
+            "ponder" => params.ponder = true,
+            "searchmoves" => {
+                while let Some(&&m) = tokens.peek() {
+                    if !is_move_token(m) {
+                        break;
+                    }
+                    params.search_moves.push(m.to_string());
+                    tokens.next();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(params)
+}
+
+fn number_value<T: std::str::FromStr>(keyword: &str, token: Option<&&str>) -> Result<T, String> {
+    match token {
+        Some(t) => t.parse::<T>().map_err(|_| format!("Invalid value for {}: {}", keyword, t)),
+        None => Err(format!("Missing value for {}", keyword)),
+    }
+}
+
+fn path_value(keyword: &str, token: Option<&&str>) -> Result<String, String> {
+    match token {
+        Some(t) => Ok(t.to_string()),
+        None => Err(format!("Missing value for {}", keyword)),
+    }
+}
+
+/// Clock values are clamped at zero because some GUIs send negative times when a
+/// player has overstepped the clock.
+fn time_value(keyword: &str, token: Option<&&str>) -> Result<u64, String> {
+    number_value::<i64>(keyword, token).map(|t| t.max(0) as u64)
+}
+
+fn parse_bench(args: &[&str]) -> Result<UciCommand, String> {
+    match args {
+        [millis] => millis
+            .parse()
+            .map(UciCommand::Bench)
+            .map_err(|_| "usage: bench <millis>".to_string()),
+        _ => Err("usage: bench <millis>".to_string()),
+    }
+}
+
+fn parse_mvm(args: &[&str]) -> Result<UciCommand, String> {
+    match args {
+        [millis, games] => match (millis.parse(), games.parse()) {
+            (Ok(millis), Ok(games)) => Ok(UciCommand::Mvm { millis, games }),
+            _ => Err("usage: mvm <millis> <games>".to_string()),
+        },
+        _ => Err("usage: mvm <millis> <games>".to_string()),
+    }
+}
+
+pub fn parse_datagen(args: &[&str]) -> Result<DatagenParams, String> {
+    let mut params = DatagenParams::default();
+    let mut tokens = args.iter();
+
+    while let Some(&keyword) = tokens.next() {
+        match keyword {
+            "games" => params.games = number_value(keyword, tokens.next())?,
+            "nodes" => params.nodes = number_value(keyword, tokens.next())?,
+            "threads" => params.threads = number_value(keyword, tokens.next())?,
+            "seed" => params.seed = number_value(keyword, tokens.next())?,
+            "randomplies" => params.random_plies = number_value(keyword, tokens.next())?,
+            "adjudicate" => params.adjudicate = number_value(keyword, tokens.next())?,
+            "file" => params.file = path_value(keyword, tokens.next())?,
+            _ => return Err(DATAGEN_USAGE.to_string()),
+        }
+    }
+    if params.threads == 0 || params.nodes == 0 || params.adjudicate <= 0 {
+        return Err("datagen threads, nodes and adjudicate must be at least 1".to_string());
+    }
+
+    Ok(params)
+}
+
+pub fn parse_annotate(args: &[&str]) -> Result<AnnotateParams, String> {
+    let mut params = AnnotateParams::default();
+    let mut tokens = args.iter();
+
+    while let Some(&keyword) = tokens.next() {
+        match keyword {
+            "file" => params.file = path_value(keyword, tokens.next())?,
+            "out" => params.out = Some(path_value(keyword, tokens.next())?),
+            "depth" => params.depth = Some(number_value(keyword, tokens.next())?),
+            "movetime" => params.move_time = Some(number_value(keyword, tokens.next())?),
+            "inaccuracy" => params.inaccuracy = number_value(keyword, tokens.next())?,
+            "mistake" => params.mistake = number_value(keyword, tokens.next())?,
+            "blunder" => params.blunder = number_value(keyword, tokens.next())?,
+            _ => return Err(ANNOTATE_USAGE.to_string()),
+        }
+    }
+    if params.file.is_empty() {
+        return Err(ANNOTATE_USAGE.to_string());
+    }
+    if !(0 < params.inaccuracy && params.inaccuracy <= params.mistake && params.mistake <= params.blunder) {
+        return Err("annotate thresholds must be positive with inaccuracy <= mistake <= blunder".to_string());
+    }
+
+    Ok(params)
+}
+
+pub fn parse_puzzles(args: &[&str]) -> Result<PuzzleParams, String> {
+    let mut params = PuzzleParams::default();
+    let mut tokens = args.iter();
+
+    while let Some(&keyword) = tokens.next() {
+        match keyword {
+            "file" => params.file = path_value(keyword, tokens.next())?,
+            "out" => params.out = Some(path_value(keyword, tokens.next())?),
+            "depth" => params.depth = Some(number_value(keyword, tokens.next())?),
+            "movetime" => params.move_time = Some(number_value(keyword, tokens.next())?),
+            "gap" => params.gap = number_value(keyword, tokens.next())?,
+            "decisive" => params.decisive = number_value(keyword, tokens.next())?,
+            "equal" => params.equal = number_value(keyword, tokens.next())?,
+            "plies" => params.plies = number_value(keyword, tokens.next())?,
+            _ => return Err(PUZZLES_USAGE.to_string()),
+        }
+    }
+    if params.file.is_empty() {
+        return Err(PUZZLES_USAGE.to_string());
+    }
+    if params.gap <= 0 || params.decisive <= params.equal || params.equal < 0 || params.plies == 0 {
+        return Err("puzzles gap and plies must be at least 1, with 0 <= equal < decisive".to_string());
+    }
+
+    Ok(params)
+}
+
+/// True if the token has the shape of a move in long algebraic notation, e.g. e2e4 or a7a8q
+pub fn is_move_token(token: &str) -> bool {
+    let b = token.as_bytes();
+    (b.len() == 4 || b.len() == 5)
+        && (b'a'..=b'h').contains(&b[0])
+        && (b'1'..=b'8').contains(&b[1])
+        && (b'a'..=b'h').contains(&b[2])
+        && (b'1'..=b'8').contains(&b[3])
+        && (b.len() == 4 || b"qrbn".contains(&b[4]))
+}
@@ -1,14 +1,18 @@
 use crate::bitboards::{bit, BLACK_PASSED_PAWN_MASK, C1_BIT, C8_BIT, E1_BIT, E8_BIT, G1_BIT, G8_BIT, WHITE_PASSED_PAWN_MASK};
-use crate::engine_constants::{BISHOP_VALUE_AVERAGE, KNIGHT_VALUE_AVERAGE, PAWN_VALUE_AVERAGE, QUEEN_VALUE_AVERAGE, ROOK_VALUE_AVERAGE};
-use crate::fen::{algebraic_path_from_path, get_fen, get_position, move_from_algebraic_move};
+use crate::engine_constants::{
+    BISHOP_VALUE_AVERAGE, CURRMOVE_INFO_MILLIS, KNIGHT_VALUE_AVERAGE, PAWN_VALUE_AVERAGE, QUEEN_VALUE_AVERAGE, ROOK_VALUE_AVERAGE,
+};
+use crate::fen::{algebraic_move_from_move, algebraic_path_from_path, get_fen, get_position, move_from_algebraic_move};
 use crate::move_constants::{
     BLACK_KING_CASTLE_MOVE_MASK, BLACK_QUEEN_CASTLE_MOVE_MASK, PIECE_MASK_BISHOP, PIECE_MASK_FULL, PIECE_MASK_KING, PIECE_MASK_KNIGHT,
     PIECE_MASK_PAWN, PIECE_MASK_QUEEN, PIECE_MASK_ROOK, PROMOTION_BISHOP_MOVE_MASK, PROMOTION_FULL_MOVE_MASK, PROMOTION_KNIGHT_MOVE_MASK,
     PROMOTION_QUEEN_MOVE_MASK, PROMOTION_ROOK_MOVE_MASK, WHITE_KING_CASTLE_MOVE_MASK, WHITE_QUEEN_CASTLE_MOVE_MASK,
 };
 use crate::opponent;
-use crate::types::{Bitboard, Move, Position, Score, SearchState, Square, BLACK, WHITE};
-use std::borrow::Borrow;
+use crate::search::mate_in_moves;
+use crate::types::{Bitboard, Move, PathScore, Position, Score, SearchState, Square, BLACK, WHITE};
+use crate::wdl::DEFAULT_WDL_MODEL;
+use std::cmp::max;
 
 #[inline(always)]
 pub const fn from_square_mask(square: Square) -> Move {
@@ -224,10 +228,18 @@ pub fn pawn_push(position: &Position, m: Move) -> bool {
     false
 }
 
-pub fn send_info(search_state: &mut SearchState, show_multi_pv: bool) {
+/// Format a score for a UCI info line, as `mate <moves>` for mate scores and `cp <centipawns>` otherwise
+pub fn uci_score(score: Score) -> String {
+    match mate_in_moves(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+pub fn send_info(search_state: &mut SearchState) {
     // Don't output info if no nodes have been searched yet - PV data would be stale
     // from a previous iteration or game
-    if !search_state.show_info || search_state.root_moves.is_empty() || search_state.nodes == 0 {
+    if !search_state.show_info || search_state.pv_lines.is_empty() || search_state.nodes == 0 {
         return;
     }
     // Skip duplicate output at the same node count
@@ -235,34 +247,64 @@ pub fn send_info(search_state: &mut SearchState, show_multi_pv: bool) {
         return;
     }
     search_state.last_info_nodes = search_state.nodes;
-    let multi_pv = if show_multi_pv { search_state.multi_pv } else { 1 };
-    // Limit multi_pv to actual number of root moves to avoid index out of bounds
-    let multi_pv = multi_pv.min(search_state.root_moves.len() as u8);
     if search_state.start_time.elapsed().as_millis() > 0 {
-        search_state.root_moves.sort_by(|(ma, _), (mb, _)| {
-            let sa = search_state.pv.get(ma).unwrap().1;
-            let sb = search_state.pv.get(mb).unwrap().1;
-            sb.cmp(&sa)
-        });
-        let nps = (search_state.nodes as f64 / search_state.start_time.elapsed().as_millis() as f64) * 1000.0;
-        for pv in 1..=multi_pv {
-            let multi_pv_move = search_state.root_moves[pv as usize - 1];
-            let pv_path_score = search_state.pv.get(multi_pv_move.0.borrow()).unwrap();
-            let s = "info score cp ".to_string()
-                + &*(pv_path_score.1 as i64).to_string()
-                + &*" depth ".to_string()
-                + &*search_state.iterative_depth.to_string()
-                + &*" time ".to_string()
-                + &*search_state.start_time.elapsed().as_millis().to_string()
-                + &*" nodes ".to_string()
-                + &*search_state.nodes.to_string()
-                + &*" nps ".to_string()
-                + &*(nps as u64).to_string()
-                + &*" multipv ".to_string()
-                + &*pv.to_string()
-                + &*" pv ".to_string()
-                + &*algebraic_path_from_path(&pv_path_score.0);
-            println!("{}", s);
+        for (line, path_score) in search_state.pv_lines.iter().enumerate() {
+            println!("{}", info_line(search_state, line as u8 + 1, path_score, None));
         }
     }
 }
+
+/// Report a single MultiPV line while it is being searched. The bound is `lowerbound` for a fail
+/// high and `upperbound` for a fail low, and `None` when the score is exact.
+pub fn send_line_info(search_state: &mut SearchState, line: u8, path_score: &PathScore, bound: Option<&str>) {
+    if !search_state.show_info || search_state.nodes == 0 || search_state.nodes == search_state.last_info_nodes {
+        return;
+    }
+    search_state.last_info_nodes = search_state.nodes;
+    if search_state.start_time.elapsed().as_millis() > 0 {
+        println!("{}", info_line(search_state, line, path_score, bound));
+    }
+}
+
+/// Announce the root move about to be searched, once the search has been running for a while
+pub fn send_currmove_info(search_state: &SearchState, mv: Move, move_number: usize) {
+    if search_state.show_info && search_state.start_time.elapsed().as_millis() >= CURRMOVE_INFO_MILLIS {
+        println!(
+            "info depth {} currmove {} currmovenumber {}",
+            search_state.iterative_depth,
+            algebraic_move_from_move(mv),
+            move_number
+        );
+    }
+}
+
+pub fn info_line(search_state: &SearchState, multi_pv: u8, path_score: &PathScore, bound: Option<&str>) -> String {
+    let millis = search_state.start_time.elapsed().as_millis();
+    let nps = if millis > 0 {
+        (search_state.nodes as f64 / millis as f64 * 1000.0) as u64
+    } else {
+        0
+    };
+    let bound = bound.map(|b| format!(" {}", b)).unwrap_or_default();
+    let wdl = if search_state.show_wdl {
+        let (win, draw, loss) = DEFAULT_WDL_MODEL.wdl(path_score.1, search_state.root_material);
+        format!(" wdl {} {} {}", win, draw, loss)
+    } else {
+        String::new()
+    };
+    format!(
+        "info depth {} seldepth {} multipv {} score {}{}{} nodes {} nps {} hashfull {} tbhits {} time {} pv {}",
+        search_state.iterative_depth,
+        max(search_state.seldepth, search_state.iterative_depth),
+        multi_pv,
+        uci_score(path_score.1),
+        bound,
+        wdl,
+        search_state.nodes,
+        nps,
+        search_state.hash_table.hashfull(search_state.hash_table_version),
+        search_state.tb_hits,
+        millis,
+        algebraic_path_from_path(&path_score.0)
+    )
+}
@@ -0,0 +1,139 @@
+//! Win/draw/loss estimates for the `UCI_ShowWDL` option
+//!
+//! The chance of winning from a score is modelled as a logistic curve, `1 / (1 + e^((a - score) / b))`,
+//! where `a` is the score at which the side to move wins half of its games and `b` controls how
+//! quickly that chance rises. Both depend linearly on the material left on the board, because the
+//! same advantage converts more reliably in an endgame. The loss chance is the win chance of the
+//! negated score, and the draw chance is whatever remains.
+//!
+//! The coefficients can be refitted from labelled positions with `fit_wdl_model`, which is what
+//! `cargo run --release --example fit_wdl` does.
+
+use crate::engine_constants::STARTING_MATERIAL;
+use crate::search::mate_in_moves;
+use crate::types::Score;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WdlModel {
+    /// Score at which the win chance reaches 50%, as (no material, starting material)
+    pub a: (f64, f64),
+    /// Spread of the win curve, as (no material, starting material)
+    pub b: (f64, f64),
+}
+
+pub const DEFAULT_WDL_MODEL: WdlModel = WdlModel {
+    a: (180.0, 400.0),
+    b: (70.0, 120.0),
+};
+
+/// Smallest spread allowed while fitting, to keep the curve from becoming a step
+const MIN_WDL_SPREAD: f64 = 1.0;
+
+/// A labelled position: the score from the side to move, the material on the board, and the
+/// game result from the side to move (1.0 win, 0.5 draw, 0.0 loss)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WdlSample {
+    pub score: Score,
+    pub material: Score,
+    pub result: f64,
+}
+
+impl WdlModel {
+    fn coefficients(&self, material: Score) -> (f64, f64) {
+        let phase = (material as f64 / STARTING_MATERIAL as f64).clamp(0.0, 1.0);
+        let a = self.a.0 + (self.a.1 - self.a.0) * phase;
+        let b = (self.b.0 + (self.b.1 - self.b.0) * phase).max(MIN_WDL_SPREAD);
+        (a, b)
+    }
+
+    fn probabilities(&self, score: Score, material: Score) -> (f64, f64, f64) {
+        let (a, b) = self.coefficients(material);
+        let win = 1.0 / (1.0 + ((a - score as f64) / b).exp());
+        let loss = 1.0 / (1.0 + ((a + score as f64) / b).exp());
+        (win, (1.0 - win - loss).max(0.0), loss)
+    }
+
+    /// Win, draw and loss chances in permille, always adding up to 1000
+    pub fn wdl(&self, score: Score, material: Score) -> (u32, u32, u32) {
+        match mate_in_moves(score) {
+            Some(moves) if moves > 0 => (1000, 0, 0),
+            Some(_) => (0, 0, 1000),
+            None => {
+                let (win, _, loss) = self.probabilities(score, material);
+                let win = (win * 1000.0).round() as u32;
+                let loss = ((loss * 1000.0).round() as u32).min(1000 - win);
+                (win, 1000 - win - loss, loss)
+            }
+        }
+    }
+
+    /// Mean negative log likelihood of the results, lower is better
+    pub fn loss(&self, samples: &[WdlSample]) -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = samples
+            .iter()
+            .map(|sample| {
+                let (win, draw, loss) = self.probabilities(sample.score, sample.material);
+                let p = if sample.result > 0.75 {
+                    win
+                } else if sample.result < 0.25 {
+                    loss
+                } else {
+                    draw
+                };
+                -p.max(1e-9).ln()
+            })
+            .sum();
+        total / samples.len() as f64
+    }
+}
+
+/// Fit the model to labelled positions with a local search: each coefficient is nudged up and down
+/// in turn, keeping any change that improves the fit, and the step is halved once nothing helps.
+pub fn fit_wdl_model(samples: &[WdlSample], start: WdlModel) -> WdlModel {
+    let mut model = start;
+    let mut best_loss = model.loss(samples);
+    let mut step = 64.0;
+
+    while step >= 0.25 {
+        let mut improved = false;
+        for coefficient in 0..4 {
+            for direction in [1.0, -1.0] {
+                let mut candidate = model;
+                match coefficient {
+                    0 => candidate.a.0 += step * direction,
+                    1 => candidate.a.1 += step * direction,
+                    2 => candidate.b.0 = (candidate.b.0 + step * direction).max(MIN_WDL_SPREAD),
+                    _ => candidate.b.1 = (candidate.b.1 + step * direction).max(MIN_WDL_SPREAD),
+                }
+                let candidate_loss = candidate.loss(samples);
+                if candidate_loss < best_loss {
+                    model = candidate;
+                    best_loss = candidate_loss;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        if !improved {
+            step /= 2.0;
+        }
+    }
+    model
+}
+
+/// Parse a game result label from a line of training data, either in the `[1.0]`, `[0.5]`, `[0.0]`
+/// style or as a PGN result such as `"1-0"`. The result is from white's point of view.
+pub fn parse_result_label(line: &str) -> Option<f64> {
+    if line.contains("[1.0]") || line.contains("1-0") {
+        Some(1.0)
+    } else if line.contains("[0.0]") || line.contains("0-1") {
+        Some(0.0)
+    } else if line.contains("[0.5]") || line.contains("1/2-1/2") {
+        Some(0.5)
+    } else {
+        None
+    }
+}
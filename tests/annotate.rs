@@ -0,0 +1,76 @@
+use rusty_rival::annotate::{annotate_game, annotated_pgn, format_score, move_suffix};
+use rusty_rival::pgn::parse_pgn;
+use rusty_rival::search::MATE_SCORE;
+use rusty_rival::types::default_search_state;
+use rusty_rival::uci_command::AnnotateParams;
+
+const SCHOLARS_MATE: &str = r#"[Event "Lesson"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+"#;
+
+fn params() -> AnnotateParams {
+    AnnotateParams {
+        file: "game.pgn".to_string(),
+        depth: Some(4),
+        ..AnnotateParams::default()
+    }
+}
+
+#[test]
+fn it_flags_a_blunder_with_the_best_line() {
+    let game = &parse_pgn(SCHOLARS_MATE)[0];
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+
+    let annotations = annotate_game(game, &params(), &mut search_state).unwrap();
+    assert_eq!(annotations.len(), 7);
+    let blunder = &annotations[5];
+    assert_eq!(blunder.san, "Nf6");
+    assert!(blunder.loss >= 300);
+    assert_eq!(format_score(blunder.score), "+M1");
+    assert_eq!(blunder.depth, 4);
+    assert!(!blunder.best_line.is_empty() && blunder.best_line[0] != "Nf6");
+    assert_eq!(annotations[6].san, "Qxf7#");
+    assert_eq!(annotations[6].loss, 0);
+
+    let pgn = annotated_pgn(game, &annotations, &params()).unwrap();
+    assert!(pgn.starts_with("[Event \"Lesson\"]\n[Annotator \"Rusty Rival "));
+    assert!(pgn.contains("3... Nf6?? {+M1/4} (3... "), "{}", pgn);
+    assert!(pgn.contains("4. Qxf7# {+M1/4} 1-0"), "{}", pgn);
+    assert!(pgn.lines().all(|l| l.len() <= 79));
+}
+
+#[test]
+fn it_formats_scores_and_suffixes() {
+    assert_eq!(format_score(35), "+0.35");
+    assert_eq!(format_score(-120), "-1.20");
+    assert_eq!(format_score(0), "+0.00");
+    assert_eq!(format_score(MATE_SCORE - 3), "+M2");
+    assert_eq!(format_score(-(MATE_SCORE - 2)), "-M1");
+
+    let params = params();
+    assert_eq!(move_suffix(20, &params), "");
+    assert_eq!(move_suffix(50, &params), "?!");
+    assert_eq!(move_suffix(150, &params), "?");
+    assert_eq!(move_suffix(300, &params), "??");
+}
+
+#[test]
+fn it_numbers_the_moves_of_a_game_that_starts_with_black() {
+    let game = &parse_pgn("[FEN \"8/8/8/4k3/8/8/4P3/4K3 b - - 0 40\"]\n\n40... Kd5 41. e4+ Kxe4 *\n")[0];
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    let params = AnnotateParams {
+        depth: Some(2),
+        ..params()
+    };
+
+    let annotations = annotate_game(game, &params, &mut search_state).unwrap();
+    let pgn = annotated_pgn(game, &annotations, &params).unwrap();
+    assert!(pgn.starts_with("[FEN \"8/8/8/4k3/8/8/4P3/4K3 b - - 0 40\"]\n"));
+    assert!(pgn.contains("\n\n40... Kd5 {"), "{}", pgn);
+    assert!(pgn.contains("} 41. e4+"), "{}", pgn);
+    assert!(pgn.contains(" 41... Kxe4"), "{}", pgn);
+    assert!(pgn.trim_end().ends_with(" *"));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use rusty_rival::bitboards::{
     bit, bitboard_for_mover, clear_bit, exactly_one_bit_set, north_fill, south_fill, test_bit, A1B1_BITS, A8B8_BITS, B1C1_BITS, B8C8_BITS,
     DARK_SQUARES_BITS, F1G1_BITS, F8G8_BITS, FILE_A_BITS, FILE_H_BITS, G1H1_BITS, G8H8_BITS, LIGHT_SQUARES_BITS, LOW_32_BITS,
@@ -132,6 +134,8 @@ fn it_returns_the_correct_bitboard_for_mover() {
         half_moves: 0,
         move_number: 1,
         zobrist_lock: 0,
+        pawn_zobrist_key: 0,
+        material_key: 0,
     };
 
     assert_eq!(1, bitboard_for_mover(&p1, Piece::Pawn));
@@ -168,6 +172,8 @@ fn it_returns_the_correct_bitboard_for_mover() {
         half_moves: 0,
         move_number: 1,
         zobrist_lock: 0,
+        pawn_zobrist_key: 0,
+        material_key: 0,
     };
 
     assert_eq!(7, bitboard_for_mover(&p2, Piece::Pawn));
@@ -0,0 +1,75 @@
+use rusty_rival::datagen::{game_result, play_game, run_datagen};
+use rusty_rival::fen::get_position;
+use rusty_rival::types::default_search_state;
+use rusty_rival::uci_command::DatagenParams;
+
+fn params(games: u32, threads: usize) -> DatagenParams {
+    DatagenParams {
+        games,
+        nodes: 300,
+        threads,
+        seed: 7,
+        adjudicate: 400,
+        ..DatagenParams::default()
+    }
+}
+
+fn datagen(params: &DatagenParams) -> String {
+    let mut out = vec![];
+    let positions = run_datagen(params, &default_search_state(), &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), positions);
+    text
+}
+
+#[test]
+fn it_writes_the_same_positions_for_the_same_seed_whatever_the_threads() {
+    let single = datagen(&params(2, 1));
+    assert!(!single.is_empty());
+    assert_eq!(datagen(&params(2, 2)), single);
+    assert_ne!(datagen(&DatagenParams { seed: 8, ..params(2, 1) }), single);
+
+    for line in single.lines() {
+        let fields: Vec<&str> = line.split(" | ").collect();
+        assert_eq!(fields.len(), 3, "{}", line);
+        get_position(fields[0]);
+        assert!(fields[1].parse::<i32>().unwrap().abs() < 9000);
+        assert!(["1.0", "0.5", "0.0"].contains(&fields[2]), "{}", line);
+    }
+}
+
+#[test]
+fn it_adjudicates_a_game_once_one_side_stays_clearly_ahead() {
+    let mut search_state = default_search_state();
+    search_state.deterministic = true;
+    search_state.show_info = false;
+    let game = play_game(&params(1, 1), 0, &mut search_state);
+
+    // Every position of a game shares its result, which matches the closing scores
+    assert!(!game.positions.is_empty());
+    let last_score = game.positions.last().unwrap().1;
+    if game.result == 1.0 {
+        assert!(last_score > 0);
+    } else if game.result == 0.0 {
+        assert!(last_score < 0);
+    }
+    assert!(game.lines().lines().all(|l| l.ends_with(&format!("{:.1}", game.result))));
+}
+
+#[test]
+fn it_knows_when_a_game_is_over() {
+    let mated = get_position("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1");
+    assert_eq!(game_result(&mated, &[]), Some(1.0));
+    let mated = get_position("8/8/8/8/8/6k1/6q1/7K w - - 0 1");
+    assert_eq!(game_result(&mated, &[]), Some(0.0));
+    let stalemate = get_position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+    assert_eq!(game_result(&stalemate, &[]), Some(0.5));
+    let fifty_moves = get_position("7k/8/6K1/8/8/8/8/R7 w - - 100 80");
+    assert_eq!(game_result(&fifty_moves, &[]), Some(0.5));
+    let bare_kings = get_position("7k/8/6K1/8/8/8/8/8 w - - 0 1");
+    assert_eq!(game_result(&bare_kings, &[]), Some(0.5));
+
+    let position = get_position("7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+    assert_eq!(game_result(&position, &[position.zobrist_lock; 2]), None);
+    assert_eq!(game_result(&position, &[position.zobrist_lock; 3]), Some(0.5));
+}
@@ -0,0 +1,79 @@
+use rusty_rival::endgame::{find_endgame, flip_material_key, material_key, material_key_from_code, EndgameEvaluation, KNOWN_WIN};
+use rusty_rival::evaluate::{evaluate, insufficient_material};
+use rusty_rival::fen::get_position;
+use rusty_rival::types::{BLACK, WHITE};
+
+fn endgame_code(fen: &str) -> Option<(&'static str, i8)> {
+    find_endgame(material_key(&get_position(fen))).map(|(endgame, strong)| (endgame.code, strong))
+}
+
+#[test]
+fn it_finds_endgames_by_material_for_either_colour() {
+    assert_eq!(
+        material_key(&get_position("8/8/8/3k4/3p4/8/8/R3K3 w - - 0 1")),
+        material_key_from_code("KRKP")
+    );
+    assert_eq!(
+        material_key(&get_position("r3k3/8/8/3P4/3K4/8/8/8 w - - 0 1")),
+        flip_material_key(material_key_from_code("KRKP"))
+    );
+    assert_eq!(endgame_code("8/8/8/3k4/3p4/8/8/R3K3 w - - 0 1"), Some(("KRKP", WHITE)));
+    assert_eq!(endgame_code("r3k3/8/8/3P4/3K4/8/8/8 w - - 0 1"), Some(("KRKP", BLACK)));
+    assert_eq!(endgame_code("8/8/8/3k4/8/8/1N6/B3K3 b - - 0 1"), Some(("KBNK", WHITE)));
+    assert_eq!(endgame_code("8/8/8/3k4/8/8/1N6/R3K3 b - - 0 1"), None);
+    // Opposite-coloured bishops are found whatever the pawns
+    assert_eq!(endgame_code("4k3/pp3b2/8/8/8/8/PPP5/2B1K3 w - - 0 1"), Some(("KBKB", WHITE)));
+    assert_eq!(endgame_code("4k3/pp3b2/8/8/8/8/PPP5/2BRK3 w - - 0 1"), None);
+}
+
+#[test]
+fn it_drives_the_king_to_the_bishops_corner_in_kbnk() {
+    // The bishop on a1 is dark, so the right corners are a1 and h8
+    let right_corner = evaluate(&get_position("7k/8/5K2/8/8/8/1N6/B7 w - - 0 1"));
+    let wrong_corner = evaluate(&get_position("k7/8/2K5/8/8/8/1N6/B7 w - - 0 1"));
+    assert!(right_corner > wrong_corner, "{} {}", right_corner, wrong_corner);
+    assert!(wrong_corner > KNOWN_WIN);
+    assert!(evaluate(&get_position("k7/8/2K5/8/8/8/1N6/B7 b - - 0 1")) < -KNOWN_WIN);
+    assert!(!insufficient_material(&get_position("k7/8/2K5/8/8/8/1N6/B7 b - - 0 1"), 4, true));
+    assert!(insufficient_material(&get_position("k7/8/2K5/8/8/8/1N6/N7 b - - 0 1"), 4, true));
+}
+
+#[test]
+fn it_adds_the_side_to_move_bonus_to_exact_endgame_scores() {
+    for fen in ["k7/8/2K5/8/8/8/1N6/B7 w - - 0 1", "k7/8/2K5/8/8/8/1N6/B7 b - - 0 1"] {
+        let position = get_position(fen);
+        let (endgame, strong) = find_endgame(material_key(&position)).unwrap();
+        match endgame.evaluate(&position, strong) {
+            EndgameEvaluation::Exact(score) => assert_eq!(evaluate(&position), 10 + score),
+            other => panic!("{:?}", other),
+        }
+    }
+}
+
+#[test]
+fn it_knows_which_rook_endgames_are_won() {
+    // Scores are from the side to move's point of view
+    assert!(evaluate(&get_position("8/8/8/3k4/8/8/8/q2RK3 b - - 0 1")) > 500);
+    assert!(evaluate(&get_position("8/8/8/3k4/8/8/8/Q2rK3 b - - 0 1")) < -500);
+    assert!(evaluate(&get_position("8/8/8/3k4/8/8/8/b2RK3 w - - 0 1")).abs() < 200);
+    assert!(evaluate(&get_position("8/8/8/3k4/8/8/8/n2RK3 w - - 0 1")).abs() < 200);
+
+    // The rook's king is in front of the pawn
+    let won = evaluate(&get_position("8/8/8/3p4/2k5/8/3K4/R7 w - - 0 1"));
+    // The pawn is on the third rank with its king beside it, and the rook's king is far away
+    let drawish = evaluate(&get_position("K7/8/8/8/8/2kp4/8/R7 w - - 0 1"));
+    assert!(won > 900, "{}", won);
+    assert!(drawish < 300, "{}", drawish);
+}
+
+#[test]
+fn it_scales_opposite_coloured_bishops() {
+    let scale = |fen: &str| {
+        let position = get_position(fen);
+        let (endgame, strong) = find_endgame(material_key(&position)).unwrap();
+        endgame.evaluate(&position, strong)
+    };
+    assert_eq!(scale("4k3/pp3b2/8/8/8/8/PPP5/2B1K3 w - - 0 1"), EndgameEvaluation::Scaled(28));
+    assert_eq!(scale("4k3/pp3b2/8/8/8/8/PPPP4/2B1K3 w - - 0 1"), EndgameEvaluation::Scaled(40));
+    assert_eq!(scale("4k3/pp4b1/8/8/8/8/PPP5/2B1K3 w - - 0 1"), EndgameEvaluation::Scaled(64));
+}
@@ -8,12 +8,13 @@ use rusty_rival::engine_constants::{
 };
 use rusty_rival::evaluate::{
     bad_bishop_penalty, black_king_early_safety, connected_passed_pawn_score, count_knight_fork_threats, doubled_and_isolated_pawn_score,
-    evaluate, fianchetto_bishop_bonus, insufficient_material, is_wrong_colored_bishop_draw, isolated_pawn_count, king_pawn_shield_score,
-    king_threat_score, knight_fork_threat_score, knight_on_rim_penalty, knight_outpost_scores, material_score, on_same_file_count,
-    passed_pawn_score, queenside_pawn_majority_score, rook_7th_king_8th_bonus, rook_file_score, white_king_early_safety,
+    evaluate, evaluate_with_pawn_hash, fianchetto_bishop_bonus, game_phase, insufficient_material, is_wrong_colored_bishop_draw,
+    isolated_pawn_count, king_pawn_shield_score, king_threat_score, knight_fork_threat_score, knight_on_rim_penalty, knight_outpost_scores,
+    material_hash_entry, material_score, on_same_file_count, passed_pawn_score, queenside_pawn_majority_score, rook_7th_king_8th_bonus,
+    rook_file_score, white_king_early_safety,
 };
 use rusty_rival::fen::get_position;
-use rusty_rival::types::{default_evaluate_cache, Score, BLACK, WHITE};
+use rusty_rival::types::{default_evaluate_cache, MaterialHashTable, PawnHashTable, Score, BLACK, WHITE};
 use rusty_rival::utils::{invert_fen, invert_pos};
 
 fn test_doubled_pawns(fen: &str, score: Score) {
@@ -73,6 +74,16 @@ fn it_knows_insufficient_material() {
 
     test_insufficient_material("6k1/8/8/4K3/8/4B3/5b2/8 b - - 0 1", true, false);
     test_insufficient_material("6k1/8/8/4K3/8/4B3/5b2/8 b - - 0 1", true, true);
+
+    // Bishop and knight, or bishops on both colours, force mate; two knights or bishops on one colour only help
+    test_insufficient_material("6k1/8/8/4K3/8/4B3/4N3/8 b - - 0 1", false, false);
+    test_insufficient_material("6k1/8/8/4K3/8/4B3/4N3/8 b - - 0 1", false, true);
+    test_insufficient_material("6k1/8/8/4K3/8/4BB2/8/8 b - - 0 1", false, false);
+    test_insufficient_material("6k1/8/8/4K3/8/4BB2/8/8 b - - 0 1", false, true);
+    test_insufficient_material("6k1/8/8/4K3/8/4B3/5B2/8 b - - 0 1", false, false);
+    test_insufficient_material("6k1/8/8/4K3/8/4B3/5B2/8 b - - 0 1", true, true);
+    test_insufficient_material("6k1/8/8/4K3/8/4NN2/8/8 b - - 0 1", false, false);
+    test_insufficient_material("6k1/8/8/4K3/8/4NN2/8/8 b - - 0 1", true, true);
 }
 
 #[test]
@@ -625,7 +636,7 @@ fn it_detects_kpk_draws() {
     test_kpk_draw("8/8/8/4k3/8/4K3/4P3/8 b - - 0 1", false);
 
     // White king on key square (d4) - winning, not drawn
-    test_kpk_draw("8/8/8/4k3/3K4/8/4P3/8 w - - 0 1", false);
+    test_kpk_draw("8/8/4k3/8/3K4/8/4P3/8 w - - 0 1", false);
 
     // Rook pawn - defending king in front on same file = draw
     test_kpk_draw("k7/8/K7/P7/8/8/8/8 w - - 0 1", true);
@@ -637,7 +648,14 @@ fn it_detects_kpk_draws() {
     test_kpk_draw("8/8/8/8/8/8/P6k/K7 w - - 0 1", false);
 
     // Black pawn version - opposition draw
-    test_kpk_draw("8/4p3/8/4k3/8/4K3/8/8 b - - 0 1", true);
+    test_kpk_draw("8/4p3/4k3/8/4K3/8/8/8 b - - 0 1", true);
+
+    // Black king two squares in front of its pawn wins whoever is to move
+    test_kpk_draw("8/4p3/8/4k3/8/4K3/8/8 b - - 0 1", false);
+
+    // Pawn on the sixth with the attacking king behind it - drawn either way
+    test_kpk_draw("8/4k3/4P3/4K3/8/8/8/8 w - - 0 1", true);
+    test_kpk_draw("8/4k3/4P3/4K3/8/8/8/8 b - - 0 1", true);
 
     // Not KP vs K (extra piece) - should not be detected as draw
     test_kpk_draw("8/8/8/4k3/8/4K3/4P3/4N3 w - - 0 1", false);
@@ -937,3 +955,35 @@ fn it_gives_fianchetto_bishop_bonus() {
     let position = get_position("4k3/5pbp/8/8/8/8/5PBP/4K3 w - - 0 1");
     assert_eq!(fianchetto_bishop_bonus(&position), 0);
 }
+
+#[test]
+fn it_evaluates_the_same_with_the_material_hash_table() {
+    let pawn_hash = PawnHashTable::new();
+    let material_hash = MaterialHashTable::new();
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 0 8",
+        // Queen against knight and pawns, with the knight attacking a pawn and then not
+        "1Q6/2k2Kpp/8/8/5Pn1/7P/8/8 w - - 0 3",
+        "1Q6/2k2Kpp/8/n7/5P2/7P/8/8 b - - 0 3",
+        // Endgames with their own evaluation or scale factor
+        "8/8/8/4k3/8/8/8/KBN5 w - - 0 1",
+        "8/8/3k4/8/2p5/8/8/R3K3 b - - 0 1",
+        "4k3/5b2/2p5/1p6/8/1P2B3/2P5/4K3 w - - 0 1",
+    ];
+    for fen in fens {
+        let position = get_position(fen);
+        // The second evaluation finds the material in the table
+        for _ in 0..2 {
+            assert_eq!(
+                evaluate_with_pawn_hash(&position, &pawn_hash, &material_hash),
+                evaluate(&position),
+                "{}",
+                fen
+            );
+        }
+        let entry = material_hash_entry(&position, &material_hash);
+        assert_eq!(entry.key, position.material_key);
+        assert_eq!(entry.phase, game_phase(&position));
+    }
+}
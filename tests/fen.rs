@@ -1,8 +1,10 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use rusty_rival::bitboards::{B2_BIT, C8_BIT, D8_BIT, E1_BIT, E2_BIT, F3_BIT, F4_BIT};
 use rusty_rival::fen::{
     algebraic_move_from_move, algebraic_squareref_from_bitref, bit_array_to_decimal, bitref_from_algebraic_squareref, board_bits,
     char_as_num, fen_board_part, get_fen, get_fen_ranks, get_piece_on_square, get_position, move_from_algebraic_move, piece_bitboard,
-    rank_bits, simple_algebraic_to_pretty_algebraic,
+    rank_bits, simple_algebraic_to_pretty_algebraic, try_get_position, FenError,
 };
 use rusty_rival::move_constants::{EN_PASSANT_NOT_AVAILABLE, START_POS};
 use rusty_rival::types::{is_bk_castle_available, is_bq_castle_available, is_wk_castle_available, is_wq_castle_available, BLACK, WHITE};
@@ -223,3 +225,68 @@ fn it_converts_a_simple_move_to_an_algebraic_move() {
         Some(String::from("exd4"))
     );
 }
+
+#[test]
+fn it_accepts_valid_fens_with_four_or_six_fields() {
+    assert_eq!(try_get_position(START_POS), Ok(get_position(START_POS)));
+    assert_eq!(
+        try_get_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"),
+        Ok(get_position(START_POS))
+    );
+    assert_eq!(
+        try_get_position("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"),
+        Ok(get_position("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"))
+    );
+    assert_eq!(
+        try_get_position("4k2r/8/8/8/8/8/8/4K3 w KQkq - 0 1"),
+        Ok(get_position("4k2r/8/8/8/8/8/8/4K3 w k - 0 1"))
+    );
+}
+
+#[test]
+fn it_rejects_invalid_fens() {
+    let cases = vec![
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+            FenError::WrongFieldCount(5),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::WrongRankCount(7),
+        ),
+        (
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::InvalidPiece('9'),
+        ),
+        (
+            "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::BadRank("ppppppp".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            FenError::InvalidSideToMove("x".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKq - 0 1",
+            FenError::InvalidCastling("KKq".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1",
+            FenError::InvalidEnPassant("e9".to_string()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+            FenError::InvalidHalfMoveClock("x".to_string()),
+        ),
+        ("8/8/8/8/8/8/8/4K3 w - - 0 1", FenError::WrongKingCount(BLACK, 0)),
+        ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", FenError::WrongKingCount(WHITE, 2)),
+        ("4k3/8/8/8/8/8/PPPPPPPP/P3K3 w - - 0 1", FenError::TooManyPawns(WHITE)),
+        ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank),
+        ("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", FenError::ImpossibleEnPassant("e6".to_string())),
+        ("4k3/8/8/8/8/8/8/4K3 b - e3 0 1", FenError::ImpossibleEnPassant("e3".to_string())),
+        ("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", FenError::SideNotToMoveInCheck),
+    ];
+    for (fen, error) in cases {
+        assert_eq!(try_get_position(fen), Err(error), "{}", fen);
+    }
+}
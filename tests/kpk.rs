@@ -0,0 +1,50 @@
+use rusty_rival::fen::get_position;
+use rusty_rival::kpk::probe_kpk;
+use rusty_rival::types::{BLACK, WHITE};
+
+fn kpk_win(fen: &str) -> bool {
+    let position = get_position(fen);
+    let (strong, weak) = if position.pieces[WHITE as usize].pawn_bitboard != 0 {
+        (WHITE, BLACK)
+    } else {
+        (BLACK, WHITE)
+    };
+    probe_kpk(
+        strong,
+        position.pieces[strong as usize].king_square,
+        position.pieces[weak as usize].king_square,
+        position.pieces[strong as usize].pawn_bitboard.trailing_zeros() as i8,
+        position.mover,
+    )
+}
+
+#[test]
+fn it_knows_the_key_squares() {
+    // Any of d4, e4 and f4 wins for a pawn on e2
+    assert!(kpk_win("8/8/4k3/8/3K4/8/4P3/8 b - - 0 1"));
+    assert!(kpk_win("8/8/4k3/8/4K3/8/4P3/8 b - - 0 1"));
+    assert!(kpk_win("8/8/4k3/8/5K2/8/4P3/8 b - - 0 1"));
+    // The king in front of the pawn on the sixth wins whoever is to move
+    assert!(kpk_win("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"));
+    assert!(kpk_win("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"));
+}
+
+#[test]
+fn it_knows_who_has_the_opposition() {
+    assert!(!kpk_win("8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"));
+    assert!(kpk_win("8/4k3/8/4K3/4P3/8/8/8 b - - 0 1"));
+    // The same for black, on the other wing
+    assert!(!kpk_win("8/8/8/3p4/3k4/8/3K4/8 b - - 0 1"));
+    assert!(kpk_win("8/8/8/3p4/3k4/8/3K4/8 w - - 0 1"));
+}
+
+#[test]
+fn it_knows_the_stalemates_and_rook_pawns() {
+    assert!(!kpk_win("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"));
+    assert!(!kpk_win("4k3/4P3/3K4/8/8/8/8/8 w - - 0 1"));
+    assert!(!kpk_win("7k/8/8/7P/8/8/8/6K1 w - - 0 1"));
+    assert!(!kpk_win("k7/8/1K6/P7/8/8/8/8 w - - 0 1"));
+    // The pawn outruns the king unless it can step into the square of the pawn
+    assert!(kpk_win("8/8/8/8/P7/4k3/8/1K6 w - - 0 1"));
+    assert!(!kpk_win("8/8/8/8/P7/4k3/8/1K6 b - - 0 1"));
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "move-validation")]
+
+use rusty_rival::fen::{algebraic_move_from_move, get_position};
+use rusty_rival::make_move::{make_move_in_place, unmake_move};
+use rusty_rival::make_move_validation::{check_position, is_move_validation_enabled, set_move_validation};
+use rusty_rival::moves::generate_legal_moves;
+use rusty_rival::perft::perft_count;
+use rusty_rival::search::iterative_deepening;
+use rusty_rival::types::{default_search_state, Move, Position};
+use std::panic;
+use std::thread;
+
+fn find_move(position: &Position, algebraic: &str) -> Move {
+    *generate_legal_moves(position)
+        .iter()
+        .find(|m| algebraic_move_from_move(**m) == algebraic)
+        .unwrap_or_else(|| panic!("{} is not a move here", algebraic))
+}
+
+fn panic_message(result: std::thread::Result<()>) -> String {
+    match result {
+        Ok(()) => panic!("Expected the validation to fail"),
+        Err(e) => e
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| e.downcast_ref::<&str>().unwrap_or(&"").to_string()),
+    }
+}
+
+#[test]
+fn it_validates_every_make_and_unmake_in_perft_and_search() {
+    set_move_validation(true);
+
+    // Castling, en passant, promotions and captures of castling rooks
+    let mut position = get_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    assert_eq!(perft_count(&mut position, 2), 97862);
+    let mut position = get_position("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+    assert_eq!(perft_count(&mut position, 2), 9467);
+
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    let fen = "rnb1kbnr/pppppppp/8/2q4R/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1";
+    let mut position = get_position(fen);
+    iterative_deepening(&mut position, 4, &mut search_state);
+    assert_eq!(position, get_position(fen));
+    assert_eq!(check_position(&position), Ok(()));
+}
+
+#[test]
+fn it_reports_the_moves_that_led_to_a_corrupt_position() {
+    set_move_validation(true);
+
+    let mut position = get_position("4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1");
+    let first = find_move(&position, "e2e4");
+    let first_unmake = make_move_in_place(&mut position, first);
+    let second = find_move(&position, "d5e4");
+
+    // A pawn that vanishes without the lock being updated
+    position.pieces[1].pawn_bitboard = 0;
+    position.pieces[1].all_pieces_bitboard = 1 << position.pieces[1].king_square;
+    let message = panic_message(panic::catch_unwind(move || {
+        let mut corrupt = position;
+        make_move_in_place(&mut corrupt, second);
+    }));
+    assert!(message.contains("zobrist lock"), "{}", message);
+
+    let mut position = get_position("4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1");
+    let first_again = make_move_in_place(&mut position, first);
+    assert_eq!(first_again.zobrist_lock, first_unmake.zobrist_lock);
+    let before_second = position;
+    let second_unmake = make_move_in_place(&mut position, second);
+
+    // An unmake that forgets to restore the captured pawn
+    let message = panic_message(panic::catch_unwind(move || {
+        let mut position = position;
+        let mut unmake = second_unmake;
+        unmake.captured_piece = 0;
+        unmake_move(&mut position, second, &unmake);
+    }));
+    assert!(message.contains("after unmaking d5e4"), "{}", message);
+    assert!(message.contains("after moves [e2e4"), "{}", message);
+
+    let mut position = before_second;
+    unmake_move(&mut position, first, &first_again);
+    assert_eq!(position, get_position("4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1"));
+}
+
+#[test]
+fn it_catches_a_pawn_key_the_make_did_not_update() {
+    set_move_validation(true);
+
+    let mut position = get_position("4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1");
+    let mv = find_move(&position, "e2e4");
+
+    // The bitboards and zobrist lock are right, but the pawn key is stale
+    position.pawn_zobrist_key ^= 1;
+    assert!(check_position(&position).unwrap_err().contains("pawn key"));
+    let message = panic_message(panic::catch_unwind(move || {
+        let mut corrupt = position;
+        make_move_in_place(&mut corrupt, mv);
+    }));
+    assert!(message.contains("pawn key"), "{}", message);
+}
+
+#[test]
+fn it_switches_validation_on_for_the_current_thread_only() {
+    set_move_validation(true);
+    assert!(is_move_validation_enabled());
+    assert!(!thread::spawn(is_move_validation_enabled).join().unwrap());
+
+    set_move_validation(false);
+    assert!(!is_move_validation_enabled());
+}
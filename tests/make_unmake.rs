@@ -1,4 +1,6 @@
+use rusty_rival::endgame::material_key;
 use rusty_rival::fen::{get_fen, get_position};
+use rusty_rival::hash::pawn_zobrist_key;
 use rusty_rival::make_move::{make_move_in_place, unmake_move};
 use rusty_rival::utils::hydrate_move_from_algebraic_move;
 
@@ -16,6 +18,18 @@ fn test_make_unmake(fen: &str, move_str: &str, expected_fen_after: &str) {
         "After move {}: expected {} but got {}",
         move_str, expected_fen_after, after_fen
     );
+    assert_eq!(
+        pawn_zobrist_key(&position),
+        position.pawn_zobrist_key,
+        "After move {}: pawn key not updated",
+        move_str
+    );
+    assert_eq!(
+        material_key(&position),
+        position.material_key,
+        "After move {}: material key not updated",
+        move_str
+    );
 
     unmake_move(&mut position, mv, &unmake_info);
 
@@ -25,6 +39,11 @@ fn test_make_unmake(fen: &str, move_str: &str, expected_fen_after: &str) {
         "After unmake {}: expected {} but got {}",
         move_str, fen, restored_fen
     );
+    assert_eq!(
+        original.pawn_zobrist_key, position.pawn_zobrist_key,
+        "Pawn key mismatch after unmake"
+    );
+    assert_eq!(original.material_key, position.material_key, "Material key mismatch after unmake");
 
     // Also verify bitboards match
     assert_eq!(
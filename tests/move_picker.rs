@@ -0,0 +1,117 @@
+use rusty_rival::fen::{algebraic_move_from_move, get_position};
+use rusty_rival::make_move::make_move;
+use rusty_rival::move_constants::{PROMOTION_FULL_MOVE_MASK, PROMOTION_QUEEN_MOVE_MASK};
+use rusty_rival::move_picker::{MovePicker, Stage};
+use rusty_rival::moves::{generate_moves, is_check};
+use rusty_rival::mvm_test_fens::get_test_fens;
+use rusty_rival::types::{default_search_state, Move, Position, SearchState};
+use rusty_rival::utils::is_capture;
+
+fn find_move(position: &Position, algebraic: &str) -> Move {
+    *generate_moves(position)
+        .iter()
+        .find(|m| algebraic_move_from_move(**m) == algebraic)
+        .unwrap_or_else(|| panic!("{} is not a move here", algebraic))
+}
+
+fn is_legal(position: &Position, m: Move) -> bool {
+    let mut new_position = *position;
+    make_move(position, m, &mut new_position);
+    !is_check(&new_position, position.mover)
+}
+
+fn picked_moves(position: &Position, search_state: &SearchState, hash_move: Move, ply: u8) -> Vec<Move> {
+    let mut picker = MovePicker::new(hash_move, ply, is_check(position, position.mover));
+    let mut moves = vec![];
+    while let Some(m) = picker.next_move(position, search_state) {
+        moves.push(m);
+    }
+    moves
+}
+
+fn sorted(mut moves: Vec<Move>) -> Vec<Move> {
+    moves.sort();
+    moves
+}
+
+#[test]
+fn it_yields_the_same_moves_as_generate_moves() {
+    let mut search_state = default_search_state();
+    let rook_lift = find_move(&get_position("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), "a1a4");
+    let mut fens: Vec<&str> = get_test_fens().iter().map(|t| t.0).collect();
+    fens.extend([
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        "1n2k3/P7/8/8/8/8/6p1/4K2R w K - 0 1",
+        "1n2k3/P7/8/8/8/8/6p1/4K2R b K - 0 1",
+        "4k3/8/8/8/8/8/4r3/4K3 w - - 0 1",
+        "4k3/8/8/8/1b6/8/3P4/4K3 w - - 0 1",
+    ]);
+
+    for fen in fens {
+        let position = get_position(fen);
+        let all_moves = generate_moves(&position);
+        let in_check = is_check(&position, position.mover);
+        let quiets: Vec<Move> = all_moves.iter().copied().filter(|m| !is_capture(&position, *m)).collect();
+
+        // Killers that may or may not be playable here
+        search_state.killer_moves[1] = [quiets.first().copied().unwrap_or(0), quiets.last().copied().unwrap_or(0)];
+        search_state.mate_killer[1] = rook_lift;
+
+        for hash_move in [0, all_moves[0], *all_moves.last().unwrap()] {
+            let picked = picked_moves(&position, &search_state, hash_move, 1);
+            let mut expected = all_moves.to_vec();
+            if in_check {
+                // Evasions skip moves that obviously leave the king in check, so compare the legal moves
+                let legal = |moves: Vec<Move>| sorted(moves.into_iter().filter(|m| is_legal(&position, *m)).collect());
+                assert_eq!(legal(picked.clone()), legal(expected), "{}", fen);
+                assert_eq!(sorted(picked.clone()).windows(2).filter(|w| w[0] == w[1]).count(), 0, "{}", fen);
+            } else {
+                // The quiet stage only promotes to a queen, so a quiet underpromotion only comes from the hash move
+                let queen_only = |m: &Move| [0, PROMOTION_QUEEN_MOVE_MASK].contains(&(m & PROMOTION_FULL_MOVE_MASK));
+                expected.retain(|m| is_capture(&position, *m) || queen_only(m) || *m == hash_move);
+                assert_eq!(sorted(picked), sorted(expected), "{}", fen);
+            }
+        }
+    }
+}
+
+#[test]
+fn it_yields_moves_in_stages() {
+    let mut search_state = default_search_state();
+    // The rook can win a free pawn on a7, but the queen loses itself for the pawn on c5
+    let position = get_position("4k3/p7/3p4/2p5/1Q6/8/8/R3K3 w - - 0 1");
+    let hash_move = find_move(&position, "b4b5");
+    let good_capture = find_move(&position, "a1a7");
+    let bad_capture = find_move(&position, "b4c5");
+    let killer = find_move(&position, "e1d1");
+    search_state.killer_moves[1] = [killer, 0];
+
+    let mut picker = MovePicker::new(hash_move, 1, false);
+    assert_eq!(picker.next_move(&position, &search_state), Some(hash_move));
+    assert_eq!(picker.next_move(&position, &search_state), Some(good_capture));
+    assert_eq!(picker.next_move(&position, &search_state), Some(killer));
+    // A cutoff here would have been found before any quiet moves were generated
+    assert_eq!(picker.stage(), Stage::Killers);
+
+    let mut rest = vec![];
+    while let Some(m) = picker.next_move(&position, &search_state) {
+        rest.push(m);
+    }
+    assert_eq!(rest.last(), Some(&bad_capture));
+    assert!(!rest.contains(&hash_move) && !rest.contains(&killer) && !rest.contains(&good_capture));
+    assert_eq!(rest.len() + 3, generate_moves(&position).len());
+    assert_eq!(picker.stage(), Stage::Done);
+
+    // A picker for a node that has already searched the hash move leaves it out
+    let mut picker = MovePicker::after_hash_move(hash_move, 1, false);
+    let mut picked = vec![];
+    while let Some(m) = picker.next_move(&position, &search_state) {
+        picked.push(m);
+    }
+    assert_eq!(picked[0], good_capture);
+    assert!(!picked.contains(&hash_move));
+    assert_eq!(picked.len() + 1, generate_moves(&position).len());
+}
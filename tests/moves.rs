@@ -1,10 +1,12 @@
+#![allow(clippy::bool_assert_comparison, clippy::iter_nth)]
+
 use rusty_rival::bitboards::{bit, EMPTY_CASTLE_SQUARES_WHITE_QUEEN, RANK_4_BITS, WHITE_PAWN_MOVES_CAPTURE, WHITE_PAWN_MOVES_FORWARD};
 use rusty_rival::fen::{algebraic_move_from_move, bitref_from_algebraic_squareref, get_position, move_from_algebraic_move};
 use rusty_rival::make_move::make_move;
 use rusty_rival::move_constants::{EN_PASSANT_NOT_AVAILABLE, PIECE_MASK_FULL, PIECE_MASK_KNIGHT, PIECE_MASK_PAWN, PIECE_MASK_QUEEN};
 use rusty_rival::moves::{
-    any_squares_in_bitboard_attacked, generate_diagonal_slider_moves, generate_moves, generate_straight_slider_moves, is_check,
-    is_square_attacked, verify_move,
+    any_squares_in_bitboard_attacked, generate_diagonal_slider_moves, generate_legal_moves, generate_moves, generate_straight_slider_moves,
+    is_check, is_square_attacked, pinned_pieces, verify_move,
 };
 use rusty_rival::quiesce::quiesce_moves;
 use rusty_rival::types::{MoveList, Square, BLACK, WHITE};
@@ -468,3 +470,43 @@ fn it_can_verify_a_move() {
         assert!(verify_move(&position, m));
     }
 }
+
+#[test]
+fn it_generates_only_legal_moves() {
+    let legal_moves = |fen: &str| {
+        let mut moves: Vec<String> = generate_legal_moves(&get_position(fen))
+            .iter()
+            .map(|m| algebraic_move_from_move(*m))
+            .collect();
+        moves.sort();
+        moves
+    };
+
+    // The pinned rook can only move along the file, towards or onto its pinner
+    assert_eq!(
+        legal_moves("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1"),
+        vec!["e1d1", "e1d2", "e1f1", "e1f2", "e2e3", "e2e4", "e2e5", "e2e6", "e2e7"]
+    );
+    // A pinned knight can't move at all
+    assert_eq!(
+        legal_moves("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1"),
+        vec!["e1d1", "e1e2", "e1f1", "e1f2"]
+    );
+    // Taking en passant would remove both pawns from the rank and expose the king to the rook
+    assert_eq!(legal_moves("8/8/8/KPp4r/8/8/8/7k w - c6 0 1"), vec!["a5a4", "a5a6", "a5b6", "b5b6"]);
+    // In check from the rook, the king can't step back along the line of the check
+    assert_eq!(legal_moves("4k3/8/8/8/8/8/8/r3K3 w - - 0 1"), vec!["e1d2", "e1e2", "e1f2"]);
+    // Double check leaves only king moves, and castling through an attacked square is not allowed
+    assert_eq!(legal_moves("4k3/8/8/8/1b6/8/4r3/R3K2R w KQ - 0 1"), vec!["e1d1", "e1e2", "e1f1"]);
+    assert!(!legal_moves("r3k2r/8/8/8/8/8/8/R3K1R1 b Qkq - 0 1").contains(&"e8g8".to_string()));
+
+    let position = get_position("4k3/4r3/8/8/1b6/8/3NR3/4K3 w - - 0 1");
+    assert_eq!(
+        pinned_pieces(
+            &position,
+            WHITE,
+            position.pieces[WHITE as usize].all_pieces_bitboard | position.pieces[BLACK as usize].all_pieces_bitboard
+        ),
+        bit(bitref_from_algebraic_squareref("d2".to_string())) | bit(bitref_from_algebraic_squareref("e2".to_string()))
+    );
+}
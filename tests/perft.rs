@@ -1,5 +1,9 @@
+use rusty_rival::fen::algebraic_move_from_move;
 use rusty_rival::fen::get_position;
-use rusty_rival::perft::perft;
+use rusty_rival::perft::{
+    parse_perft_suite_line, perft, perft_count, perft_divide, perft_divide_parallel, perft_hashed, perft_pseudo_legal, run_perft_suite,
+    PerftHashTable, PerftSuiteEntry,
+};
 
 #[test]
 fn it_returns_the_total_number_of_moves_in_a_full_move_tree_of_a_given_depth_with_a_given_position_as_its_head() {
@@ -234,3 +238,68 @@ fn it_returns_the_total_number_of_moves_in_a_full_move_tree_of_a_given_depth_wit
         11139762
     );
 }
+
+#[test]
+fn it_counts_the_same_nodes_with_legal_and_pseudo_legal_move_generation() {
+    for (fen, depth) in [
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 2),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 2),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 2),
+        ("8/8/8/KPp4r/8/8/8/7k w - c6 0 1", 3),
+        ("8/8/3k4/8/2pP4/8/8/B3K3 b - d3 0 1", 3),
+        ("r3k2r/p6p/8/B7/1pp1p3/3b4/P6P/R3K2R w KQkq - 0 1", 3),
+    ] {
+        assert_eq!(
+            perft(&mut get_position(fen), depth),
+            perft_pseudo_legal(&mut get_position(fen), depth),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn it_divides_perft_between_threads_with_a_hash_table() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let hash_table = PerftHashTable::new(1 << 16);
+
+    let divided = perft_divide(&mut get_position(fen), 2);
+    assert_eq!(divided.len(), 48);
+    assert_eq!(divided.iter().map(|(_, n)| n).sum::<u64>(), 97862);
+    assert_eq!(perft_divide_parallel(&get_position(fen), 2, &hash_table), divided);
+    let (m, count) = divided[0];
+    assert_eq!(format!("{}: {}", algebraic_move_from_move(m), count), "e1g1: 2059");
+
+    // A second count, answered from the hash table, and a count at another depth give the same results
+    assert_eq!(perft_hashed(&mut get_position(fen), 2, &hash_table), 97862);
+    assert_eq!(perft_hashed(&mut get_position(fen), 2, &hash_table), 97862);
+    assert_eq!(
+        perft_hashed(&mut get_position(fen), 1, &hash_table),
+        perft_count(&mut get_position(fen), 1)
+    );
+}
+
+#[test]
+fn it_runs_a_perft_suite() {
+    assert_eq!(
+        parse_perft_suite_line("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 191 ;D3 2812"),
+        Ok(PerftSuiteEntry {
+            fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -".to_string(),
+            expected: vec![(1, 14), (2, 191), (3, 2812)],
+        })
+    );
+    assert!(parse_perft_suite_line("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;X1 14").is_err());
+    assert!(parse_perft_suite_line("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 many").is_err());
+
+    let suite = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400 ;D3 8902 ;D4 197281\n\
+                 8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - ;D1 14 ;D2 190\n";
+    let hash_table = PerftHashTable::new(1 << 16);
+    assert_eq!(
+        run_perft_suite(suite, 3, &hash_table),
+        Ok((
+            5,
+            vec!["8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - D2: expected 190 got 191".to_string()]
+        ))
+    );
+}
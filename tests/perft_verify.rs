@@ -0,0 +1,45 @@
+use rusty_rival::fen::get_position;
+use rusty_rival::perft_verify::{verify_perft, verify_perft_position, PerftDivergence, PerftError, PERFT_VERIFY_FENS};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, PositionError};
+
+#[test]
+fn it_agrees_with_shakmaty_on_tricky_positions() {
+    for fen in PERFT_VERIFY_FENS {
+        assert!(verify_perft(fen, 3).is_ok(), "{}", fen);
+    }
+    assert_eq!(verify_perft(PERFT_VERIFY_FENS[1], 3), Ok(97862));
+    assert!(matches!(verify_perft("not a fen", 1), Err(PerftError::InvalidFen(_))));
+}
+
+#[test]
+fn it_reports_the_first_diverging_path() {
+    // Without the checks in try_get_position, a castling right with no rook is enough for our
+    // generator to castle, while shakmaty has dropped the right
+    let verify = |fen: &str, depth: u8| {
+        let chess: Chess = fen
+            .parse::<Fen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .or_else(PositionError::ignore_invalid_castling_rights)
+            .unwrap();
+        verify_perft_position(&mut get_position(fen), &chess, depth)
+    };
+
+    assert_eq!(
+        verify("4k3/8/8/8/8/8/8/4K3 w K - 0 1", 2),
+        Err(PerftDivergence {
+            path: vec![],
+            fen: "4k3/8/8/8/8/8/8/4K3 w K - 0 1".to_string(),
+            missing: vec![],
+            extra: vec!["e1g1".to_string()],
+        })
+    );
+
+    let divergence = verify("4k3/8/8/8/8/8/8/4K3 b K - 0 1", 3).unwrap_err();
+    assert_eq!(divergence.path.len(), 1);
+    assert_eq!(divergence.extra, vec!["e1g1"]);
+    assert!(divergence
+        .to_string()
+        .starts_with(&format!("moves differ after [{}]", divergence.path[0])));
+}
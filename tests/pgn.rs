@@ -0,0 +1,70 @@
+use rusty_rival::move_constants::START_POS;
+use rusty_rival::pgn::{parse_pgn, san_to_uci, uci_to_san};
+
+const TWO_GAMES: &str = r#"[Event "Club \"Open\""]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 {gambit}) 2... Nc6 $1 3. Bb5!? a6
+; a line comment 4. d4
+4. Ba4 Nf6 5. 0-0 1-0
+
+[Event "Second"]
+[FEN "8/8/8/4k3/8/8/4P3/4K3 b - - 0 40"]
+
+40... Kd5 41.e4+ Kxe4 *
+"#;
+
+#[test]
+fn it_reads_the_main_line_and_tags_of_each_game() {
+    let games = parse_pgn(TWO_GAMES);
+    assert_eq!(games.len(), 2);
+
+    assert_eq!(games[0].tag("Event"), Some("Club \"Open\""));
+    assert_eq!(games[0].tag("White"), Some("A"));
+    assert_eq!(games[0].start_fen(), START_POS);
+    assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O"]);
+    assert_eq!(games[0].result, "1-0");
+    assert_eq!(
+        games[0].uci_moves(),
+        Ok(vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1"]
+            .into_iter()
+            .map(String::from)
+            .collect())
+    );
+
+    assert_eq!(games[1].start_fen(), "8/8/8/4k3/8/8/4P3/4K3 b - - 0 40");
+    assert_eq!(games[1].moves, vec!["Kd5", "e4+", "Kxe4"]);
+    assert_eq!(games[1].result, "*");
+}
+
+#[test]
+fn it_reads_castling_written_with_zeros() {
+    let pgn = r#"[FEN "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"]
+
+1. 0-0 0-0-0 2. Ra2 Rd7 *
+"#;
+    let games = parse_pgn(pgn);
+    assert_eq!(games[0].moves, vec!["O-O", "O-O-O", "Ra2", "Rd7"]);
+    assert_eq!(
+        games[0].uci_moves(),
+        Ok(vec!["e1g1", "e8c8", "a1a2", "d8d7"].into_iter().map(String::from).collect())
+    );
+}
+
+#[test]
+fn it_converts_between_san_and_long_algebraic_moves() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let san: Vec<String> = ["O-O-O", "Bxe2", "Nxe2", "hxg2"].iter().map(|m| m.to_string()).collect();
+    let uci = san_to_uci(fen, &san).unwrap();
+    assert_eq!(uci, vec!["e1c1", "a6e2", "c3e2", "h3g2"]);
+    assert_eq!(uci_to_san(fen, &uci), san);
+
+    // A line is cut short at the first move that isn't legal
+    let broken: Vec<String> = ["e1c1", "a6e2", "e1g1"].iter().map(|m| m.to_string()).collect();
+    assert_eq!(uci_to_san(fen, &broken), vec!["O-O-O", "Bxe2"]);
+
+    assert_eq!(san_to_uci(START_POS, &["e5".to_string()]), Err("Illegal move e5".to_string()));
+    assert_eq!(san_to_uci(START_POS, &["Zz9".to_string()]), Err("Invalid move Zz9".to_string()));
+}
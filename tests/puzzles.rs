@@ -0,0 +1,70 @@
+use rusty_rival::pgn::parse_pgn;
+use rusty_rival::puzzles::{game_puzzles, is_unique, Puzzle};
+use rusty_rival::types::default_search_state;
+use rusty_rival::uci_command::PuzzleParams;
+
+fn params(plies: u8) -> PuzzleParams {
+    PuzzleParams {
+        file: "games.pgn".to_string(),
+        depth: Some(5),
+        plies,
+        ..PuzzleParams::default()
+    }
+}
+
+fn puzzles(pgn: &str, params: &PuzzleParams) -> Vec<Puzzle> {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    game_puzzles(&parse_pgn(pgn)[0], 1, params, &mut search_state).unwrap()
+}
+
+fn position_puzzles(fen: &str, params: &PuzzleParams) -> Vec<Puzzle> {
+    puzzles(&format!("[FEN \"{}\"]\n\n*\n", fen), params)
+}
+
+#[test]
+fn it_finds_the_only_winning_move_in_a_game() {
+    let found = puzzles("[Event \"Lesson\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n", &params(5));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].line, vec!["Qxf7#"]);
+    assert!(found[0].gap >= 200);
+    assert!(found[0]
+        .epd()
+        .starts_with("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; id \"Lesson 1, move 4.\"; pv Qxf7#; ce "));
+}
+
+#[test]
+fn it_finds_the_only_move_that_keeps_equality() {
+    // Black has a knight as well, since rook against a lone knight is drawish even without the fork
+    let found = position_puzzles("r3k3/8/7n/3N4/8/8/8/4K3 w - - 0 1", &params(3));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].line.len(), 3);
+    assert_eq!(found[0].line[0], "Nc7+");
+    assert_eq!(found[0].line[2], "Nxa8");
+    assert!(found[0].score.abs() <= 50);
+    assert_eq!(found[0].id, "Game 1, move 1.");
+}
+
+#[test]
+fn it_needs_the_solution_to_be_unique_along_the_line() {
+    // Two rooks mate on the back rank
+    assert!(position_puzzles("k7/8/1K6/8/8/8/8/6RR w - - 0 1", &params(5)).is_empty());
+
+    // The fork is the only winning move, but once the rook is taken several moves win
+    let fen = "r3k3/8/8/3N4/8/8/4P3/4K3 w - - 0 1";
+    assert!(position_puzzles(fen, &params(5)).is_empty());
+    let found = position_puzzles(fen, &params(3));
+    assert_eq!(found.len(), 1);
+    assert!(found[0].epd().contains(" bm Nc7+; "));
+    assert!(found[0].score >= 300);
+}
+
+#[test]
+fn it_decides_whether_the_best_move_is_unique() {
+    let params = params(5);
+    assert!(is_unique(500, 100, &params));
+    assert!(!is_unique(800, 400, &params));
+    assert!(!is_unique(500, 350, &params));
+    assert!(is_unique(0, -250, &params));
+    assert!(!is_unique(0, -150, &params));
+}
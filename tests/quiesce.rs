@@ -0,0 +1,51 @@
+use rusty_rival::evaluate::evaluate;
+use rusty_rival::fen::{algebraic_move_from_move, get_position};
+use rusty_rival::quiesce::quiesce;
+use rusty_rival::search::{MATE_SCORE, MAX_WINDOW};
+use rusty_rival::types::BoundType::Exact;
+use rusty_rival::types::{default_search_state, Position, SearchState};
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+fn quiesce_position(fen: &str) -> (Position, SearchState, (String, i32)) {
+    let mut search_state = default_search_state();
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    let mut position = get_position(fen);
+    let path_score = quiesce(&mut position, 100, 1, (-MAX_WINDOW, MAX_WINDOW), &mut search_state);
+    (position, search_state, (algebraic_move_from_move(path_score.0[0]), path_score.1))
+}
+
+#[test]
+fn it_recognises_checkmate() {
+    let (_, _, (_, score)) = quiesce_position("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+    assert_eq!(score, -MATE_SCORE + 1);
+}
+
+#[test]
+fn it_does_not_stand_pat_when_in_check() {
+    // White is well ahead on material, but the knight check forks the king and the queen
+    let fen = "4k3/8/8/8/8/Q7/2n5/R3K3 w - - 0 1";
+    let (_, _, (_, score)) = quiesce_position(fen);
+    assert!(score < evaluate(&get_position(fen)) - 500, "{}", score);
+}
+
+#[test]
+fn it_finds_a_quiet_promotion() {
+    let fen = "8/P7/8/8/8/8/k7/7K w - - 0 1";
+    let (_, _, (mv, score)) = quiesce_position(fen);
+    assert_eq!(mv, "a7a8q");
+    assert!(score > evaluate(&get_position(fen)) + 500, "{}", score);
+}
+
+#[test]
+fn it_stores_its_result_in_the_hash_table() {
+    let (position, search_state, (mv, _)) = quiesce_position("4k3/7p/8/3p4/4P3/8/7P/4K3 w - - 0 1");
+    assert_eq!(mv, "e4d5");
+
+    let index = (position.zobrist_lock % search_state.hash_table.len() as u128) as usize;
+    let entry = search_state.hash_table.get(index);
+    assert_eq!(entry.lock, position.zobrist_lock);
+    assert_eq!(entry.height, 0);
+    assert_eq!(entry.bound, Exact);
+    assert_eq!(algebraic_move_from_move(entry.mv), "e4d5");
+}
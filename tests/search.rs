@@ -1,6 +1,12 @@
+use rusty_rival::engine_constants::MAX_DEPTH;
 use rusty_rival::fen::{algebraic_move_from_move, get_position};
-use rusty_rival::search::{is_draw, is_passed_pawn_push, iterative_deepening, null_move_reduced_depth, piece_index_12};
+use rusty_rival::search::{
+    is_draw, is_passed_pawn_push, iterative_deepening, mate_in_moves, mate_score_threshold, null_move_reduced_depth, piece_index_12,
+    MATE_SCORE,
+};
+use rusty_rival::search_limits::{SearchLimits, DETERMINISTIC_NODES_PER_MILLI};
 use rusty_rival::types::default_search_state;
+use rusty_rival::utils::uci_score;
 use rusty_rival::utils::{hydrate_move_from_algebraic_move, pawn_push};
 use std::ops::Add;
 use std::time::{Duration, Instant};
@@ -132,6 +138,84 @@ fn it_finds_a_mate_in_6() {
     assert_move("8/8/8/1K6/4Q3/2P5/5k2/8 w - - 0 1", 13, 10000000, "b5c5,b5c4,e4g4");
 }
 
+#[test]
+fn it_converts_between_mate_scores_and_moves() {
+    assert_eq!(mate_in_moves(MATE_SCORE - 1), Some(1));
+    assert_eq!(mate_in_moves(MATE_SCORE - 3), Some(2));
+    assert_eq!(mate_in_moves(-MATE_SCORE + 2), Some(-1));
+    assert_eq!(mate_in_moves(-MATE_SCORE + 4), Some(-2));
+    assert_eq!(mate_in_moves(350), None);
+    assert_eq!(mate_score_threshold(2), MATE_SCORE - 3);
+    assert_eq!(uci_score(MATE_SCORE - 3), "mate 2");
+    assert_eq!(uci_score(-MATE_SCORE + 4), "mate -2");
+    assert_eq!(uci_score(-35), "cp -35");
+}
+
+#[test]
+fn it_stops_a_mate_search_as_soon_as_the_mate_is_proven() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.mate_search = Some(2);
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    let mut position = get_position("8/8/8/8/4Q3/2P4k/8/5K2 w - - 0 1");
+
+    let mv = iterative_deepening(&mut position, 4, &mut search_state);
+
+    assert_eq!(mate_in_moves(search_state.current_best.1), Some(2));
+    assert_eq!(search_state.current_best.0[0], mv);
+    assert!(search_state.iterative_depth <= 4);
+}
+
+#[test]
+fn it_searches_each_multi_pv_line_with_an_exact_score() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.multi_pv = 3;
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    // Taking the queen is clearly best, with the rook and the knight also hanging
+    let mut position = get_position("6k1/8/1q6/4r3/7n/8/8/KR2R2R w - - 0 1");
+
+    let mv = iterative_deepening(&mut position, 4, &mut search_state);
+
+    let lines: Vec<String> = search_state
+        .pv_lines
+        .iter()
+        .map(|line| algebraic_move_from_move(line.0[0]))
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "b1b6");
+    assert!(lines[1] != lines[2] && !lines[1..].contains(&lines[0]));
+    assert_eq!(search_state.pv_lines[0].0[0], mv);
+    assert!(search_state.pv_lines.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    // The next iteration starts with the lines that were found
+    for (root_move, line) in search_state.root_moves.iter().zip(&search_state.pv_lines) {
+        assert_eq!(root_move.0, line.0[0]);
+    }
+}
+
+#[test]
+fn it_leaves_no_result_from_an_earlier_search_when_returning_without_searching() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    let mut position = get_position("6k1/8/1q6/4r3/7n/8/8/KR2R2R w - - 0 1");
+    iterative_deepening(&mut position, 3, &mut search_state);
+    assert!(search_state.current_best.1 > 500);
+    assert!(!search_state.pv_lines.is_empty());
+
+    // Checkmated
+    let mut position = get_position("6k1/8/8/8/8/8/5PPP/3q2K1 w - - 0 1");
+    assert_eq!(iterative_deepening(&mut position, 3, &mut search_state), 0);
+    assert_eq!(search_state.current_best.1, -MATE_SCORE);
+    assert!(search_state.pv_lines.is_empty());
+
+    // Stalemated
+    let mut position = get_position("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+    assert_eq!(iterative_deepening(&mut position, 3, &mut search_state), 0);
+    assert_eq!(search_state.current_best.1, 0);
+    assert!(search_state.pv_lines.is_empty());
+}
+
 #[test]
 fn it_returns_the_best_move_when_time_runs_out() {
     assert_move("rnb1kbnr/pppppppp/8/2q4R/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1", 20, 100, "h5c5");
@@ -261,3 +345,52 @@ fn test_is_passed_pawn_push() {
         "e3e4 should NOT trigger extension (only 4th rank)"
     );
 }
+
+#[test]
+fn it_counts_search_statistics() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.end_time = Instant::now().add(Duration::from_secs(60));
+    let mut position = get_position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    iterative_deepening(&mut position, 6, &mut search_state);
+
+    let stats = search_state.stats;
+    assert_eq!(*search_state.last_search_stats.lock().unwrap(), stats);
+    assert_eq!(stats.nodes + stats.qsearch_nodes, search_state.nodes);
+    assert!(stats.qsearch_nodes > 0 && stats.expanded_nodes > 0);
+    assert!(stats.moves_searched >= stats.expanded_nodes);
+    assert!(stats.hash_hits_exact + stats.hash_hits_lower + stats.hash_hits_upper <= stats.hash_probes);
+    assert!(stats.first_move_cutoffs > 0 && stats.first_move_cutoffs <= stats.beta_cutoffs);
+    assert!(stats.null_move_cutoffs > 0 && stats.null_move_cutoffs <= stats.null_move_attempts);
+    assert!(stats.lmr_researches <= stats.lmr_searches && stats.lmr_searches > 0);
+
+    // Each search starts counting again
+    iterative_deepening(&mut position, 1, &mut search_state);
+    assert!(search_state.stats.nodes < stats.nodes);
+}
+
+#[test]
+fn it_repeats_a_timed_search_exactly_in_deterministic_mode() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.deterministic = true;
+    let limits = SearchLimits {
+        hard_millis: Some(30),
+        ..SearchLimits::default()
+    };
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    let mut results = vec![];
+    for _ in 0..3 {
+        let mut position = get_position(fen);
+        search_state.nodes = 0;
+        limits.apply(&mut search_state);
+        assert_eq!(search_state.nodes_limit, 30 * DETERMINISTIC_NODES_PER_MILLI);
+        let mv = iterative_deepening(&mut position, MAX_DEPTH, &mut search_state);
+        results.push((mv, search_state.current_best.clone(), search_state.nodes, search_state.stats));
+    }
+
+    assert!(results[0].2 >= 30 * DETERMINISTIC_NODES_PER_MILLI);
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[1], results[2]);
+}
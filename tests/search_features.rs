@@ -0,0 +1,63 @@
+use rusty_rival::fen::{algebraic_move_from_move, get_position};
+use rusty_rival::search::iterative_deepening;
+use rusty_rival::search_features::{SearchFeatures, ALL_SEARCH_FEATURES, LATE_MOVE_PRUNING, MULTICUT, NULL_MOVE, PROBCUT, SEE_PRUNING};
+use rusty_rival::types::{default_search_state, SearchState};
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+fn search(fen: &str, depth: u8, features: SearchFeatures) -> (String, SearchState) {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.search_features = features;
+    search_state.end_time = Instant::now().add(Duration::from_secs(60));
+    let mv = iterative_deepening(&mut get_position(fen), depth, &mut search_state);
+    (algebraic_move_from_move(mv), search_state)
+}
+
+#[test]
+fn it_parses_search_features() {
+    assert_eq!(SearchFeatures::default().bits, ALL_SEARCH_FEATURES);
+    assert_eq!(SearchFeatures::default().to_string(), "all");
+    assert_eq!(SearchFeatures::parse("0"), Ok(SearchFeatures { bits: 0 }));
+    assert_eq!(SearchFeatures::parse("none").unwrap().to_string(), "none");
+    assert_eq!(
+        SearchFeatures::parse(&ALL_SEARCH_FEATURES.to_string()),
+        Ok(SearchFeatures::default())
+    );
+    assert!(SearchFeatures::parse(&(ALL_SEARCH_FEATURES + 1).to_string()).is_err());
+
+    let features = SearchFeatures::parse("-multicut,-probcut").unwrap();
+    assert_eq!(features.bits, ALL_SEARCH_FEATURES & !MULTICUT & !PROBCUT);
+    assert_eq!(SearchFeatures::parse("none lmr nullmove").unwrap().to_string(), "nullmove lmr");
+    assert_eq!(SearchFeatures::parse("-all NullMove").unwrap().bits, NULL_MOVE);
+    assert_eq!(SearchFeatures::parse("-lmp").unwrap().to_string().split(' ').count(), 10);
+    assert_eq!(SearchFeatures::parse("-xyzzy"), Err("Unknown search feature xyzzy".to_string()));
+
+    let mut features = SearchFeatures::default();
+    features.set(SEE_PRUNING, false);
+    assert!(!features.enabled(SEE_PRUNING) && features.enabled(LATE_MOVE_PRUNING));
+    features.set(SEE_PRUNING, true);
+    assert_eq!(features, SearchFeatures::default());
+}
+
+#[test]
+fn it_does_not_use_switched_off_search_features() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    let (_, search_state) = search(fen, 6, SearchFeatures::default());
+    assert!(search_state.stats.null_move_attempts > 0 && search_state.stats.lmr_searches > 0 && search_state.stats.lmp_prunes > 0);
+
+    let (_, search_state) = search(fen, 6, SearchFeatures::parse("-nullmove -lmr -lmp").unwrap());
+    assert_eq!(search_state.stats.null_move_attempts, 0);
+    assert_eq!(search_state.stats.lmr_searches, 0);
+    assert_eq!(search_state.stats.lmp_prunes, 0);
+    assert!(search_state.stats.see_prunes > 0);
+
+    let (_, search_state) = search(fen, 5, SearchFeatures::parse("none").unwrap());
+    assert_eq!(search_state.stats.see_prunes + search_state.stats.null_move_attempts, 0);
+    assert_eq!(search_state.stats.probcut_cutoffs + search_state.stats.multicut_cutoffs, 0);
+
+    // A mate is still found with every feature off
+    let (mv, _) = search("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 3, SearchFeatures { bits: 0 });
+    assert_eq!(mv, "d1d8");
+}
@@ -0,0 +1,60 @@
+use rusty_rival::engine_constants::MAX_DEPTH;
+use rusty_rival::search_limits::SearchLimits;
+use rusty_rival::time_management::allocate_time;
+use rusty_rival::types::{BLACK, WHITE};
+use rusty_rival::uci_command::{parse_command, GoParams, UciCommand};
+
+fn go_params(command: &str) -> GoParams {
+    match parse_command(command) {
+        Ok(UciCommand::Go(params)) => params,
+        other => panic!("{} parsed as {:?}", command, other),
+    }
+}
+
+#[test]
+fn it_has_no_limits_for_a_plain_go() {
+    assert_eq!(SearchLimits::from_go(&go_params("go"), WHITE, 10), SearchLimits::default());
+    assert_eq!(SearchLimits::default().depth, MAX_DEPTH);
+    assert_eq!(SearchLimits::default().nodes, u64::MAX);
+}
+
+#[test]
+fn it_keeps_every_limit_given_together() {
+    let limits = SearchLimits::from_go(&go_params("go depth 8 nodes 5000 movetime 300"), WHITE, 10);
+    assert_eq!(limits.depth, 8);
+    assert_eq!(limits.nodes, 5000);
+    assert_eq!(limits.hard_millis, Some(290));
+    assert_eq!(limits.soft_millis, None);
+    assert!(limits.limit_strength());
+
+    let limits = SearchLimits::from_go(&go_params("go wtime 100000 btime 1000 movestogo 9 movetime 5000"), BLACK, 10);
+    assert_eq!(limits.hard_millis, Some(allocate_time(1000, 0, 9, 10).hard_millis));
+    assert_eq!(limits.soft_millis, None);
+
+    let limits = SearchLimits::from_go(&go_params("go wtime 31010 btime 1000"), WHITE, 10);
+    assert_eq!(limits.hard_millis, Some(4000));
+    assert_eq!(limits.soft_millis, Some(1000));
+}
+
+#[test]
+fn it_limits_a_mate_search_by_depth_nodes_and_time() {
+    let limits = SearchLimits::from_go(&go_params("go mate 3 movetime 1000 nodes 100000"), WHITE, 10);
+    assert_eq!(limits.mate, Some(3));
+    assert_eq!(limits.depth, 6);
+    assert_eq!(limits.nodes, 100000);
+    assert_eq!(limits.hard_millis, Some(990));
+    assert!(!limits.limit_strength());
+
+    assert_eq!(SearchLimits::from_go(&go_params("go mate 5 depth 4"), WHITE, 10).depth, 4);
+}
+
+#[test]
+fn it_drops_the_time_limits_of_an_infinite_search() {
+    let limits = SearchLimits::from_go(&go_params("go infinite depth 5 nodes 2000 wtime 1000 movetime 100"), WHITE, 10);
+    assert!(limits.infinite);
+    assert_eq!(limits.depth, 5);
+    assert_eq!(limits.nodes, 2000);
+    assert_eq!(limits.hard_millis, None);
+    assert_eq!(limits.soft_millis, None);
+    assert!(!limits.limit_strength());
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "search-trace")]
+
+use rusty_rival::fen::get_position;
+use rusty_rival::search::iterative_deepening;
+use rusty_rival::search_trace::{finish_trace, start_trace, trace_to_json, trace_to_text, TraceEvent, TraceNode};
+use rusty_rival::types::default_search_state;
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+fn trace_search(fen: &str, depth: u8, max_ply: u8) -> Vec<TraceNode> {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.end_time = Instant::now().add(Duration::from_secs(60));
+    let mut position = get_position(fen);
+    start_trace(max_ply);
+    iterative_deepening(&mut position, depth, &mut search_state);
+    finish_trace()
+}
+
+fn all_nodes(nodes: &[TraceNode]) -> Vec<&TraceNode> {
+    nodes
+        .iter()
+        .flat_map(|n| std::iter::once(n).chain(all_nodes(&n.children)))
+        .collect()
+}
+
+#[test]
+fn it_records_the_nodes_searched_below_each_root_move() {
+    let trace = trace_search("6k1/3q1pp1/5b1p/1Pp1pP2/2Pp4/3Q4/5PPP/r1B2RK1 w - - 0 32", 4, 2);
+
+    assert!(trace.iter().any(|n| n.path == vec!["d3f3"] && n.depth == 3));
+    for node in all_nodes(&trace) {
+        assert!(node.ply <= 2);
+        assert_eq!(node.path.len(), node.ply as usize);
+        for child in &node.children {
+            assert_eq!(child.path[..node.path.len()], node.path[..]);
+        }
+    }
+    assert!(all_nodes(&trace)
+        .iter()
+        .flat_map(|n| n.events.iter())
+        .any(|e| matches!(e, TraceEvent::BetaCutoff { .. })));
+
+    let text = trace_to_text(&trace, &["d3f3"]);
+    assert!(!text.is_empty());
+    assert!(text.lines().all(|l| l.trim_start().starts_with("d3f3")));
+    assert_eq!(trace_to_text(&trace, &[]).lines().count(), all_nodes(&trace).len());
+
+    let json = trace_to_json(&trace);
+    assert!(json.starts_with("[{\"path\":[\"") && json.ends_with("}]"));
+    assert_eq!(json.matches('{').count(), json.matches('}').count());
+
+    assert_eq!(finish_trace(), vec![]);
+}
+
+#[test]
+fn it_records_pruning_decisions_by_rule() {
+    let trace = trace_search("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 6, 3);
+    let rules: Vec<&str> = all_nodes(&trace).iter().flat_map(|n| n.events.iter().map(|e| e.rule())).collect();
+
+    assert!(rules.contains(&"lmr"));
+    assert!(rules.contains(&"null move cutoff"));
+    assert!(rules.contains(&"beta cutoff"));
+    let reduction = all_nodes(&trace)
+        .iter()
+        .flat_map(|n| n.events.iter())
+        .find(|e| e.rule() == "lmr")
+        .unwrap()
+        .to_string();
+    assert!(reduction.starts_with("lmr ") && reduction.contains(" reduction "), "{}", reduction);
+}
@@ -0,0 +1,98 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rusty_rival::fen::{algebraic_move_from_move, get_position, move_from_algebraic_move};
+use rusty_rival::move_constants::START_POS;
+use rusty_rival::strength::{elo_to_skill_level, pick_weakened_move, search_with_strength, Strength, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+use rusty_rival::types::{default_search_state, pv_single, Move, Score, SearchState};
+use rusty_rival::uci::is_legal_move;
+use std::ops::Add;
+use std::time::{Duration, Instant};
+
+fn search_state_with_root_scores(scores: &[(&str, Score)]) -> (SearchState, Vec<Move>) {
+    let mut search_state = default_search_state();
+    let moves: Vec<Move> = scores.iter().map(|(m, _)| move_from_algebraic_move(m.to_string(), 0)).collect();
+    for (m, (_, score)) in moves.iter().zip(scores) {
+        search_state.root_moves.push((*m, *score));
+        search_state.pv_lines.push((pv_single(*m), *score));
+    }
+    (search_state, moves)
+}
+
+#[test]
+fn it_maps_elo_to_skill_levels() {
+    assert_eq!(elo_to_skill_level(MIN_ELO), 0);
+    assert_eq!(elo_to_skill_level(0), 0);
+    assert_eq!(elo_to_skill_level(MAX_ELO), MAX_SKILL_LEVEL);
+    assert_eq!(elo_to_skill_level((MIN_ELO + MAX_ELO) / 2), MAX_SKILL_LEVEL / 2);
+}
+
+#[test]
+fn it_only_limits_strength_when_asked() {
+    let strength = Strength::default();
+    assert!(!strength.is_limited());
+
+    let strength = Strength {
+        limit_strength: true,
+        elo: MIN_ELO,
+        ..Strength::default()
+    };
+    assert!(strength.is_limited());
+    assert_eq!(strength.effective_skill_level(), 0);
+    assert_eq!(strength.depth_limit(), 1);
+
+    let strength = Strength {
+        skill_level: 10,
+        ..Strength::default()
+    };
+    assert!(strength.is_limited());
+    assert_eq!(strength.depth_limit(), 6);
+    assert!(
+        strength.nodes_limit()
+            > Strength {
+                skill_level: 2,
+                ..strength
+            }
+            .nodes_limit()
+    );
+}
+
+#[test]
+fn it_never_plays_a_blunder_at_a_high_skill_level() {
+    let mut rng = StdRng::seed_from_u64(28);
+    let (mut search_state, moves) = search_state_with_root_scores(&[("e2e4", 50), ("d2d4", 45), ("g2g4", -900)]);
+    for _ in 0..100 {
+        let chosen = pick_weakened_move(&mut search_state, 19, moves[0], &mut rng);
+        assert_ne!(chosen, moves[2]);
+        assert_eq!(search_state.current_best.0[0], chosen);
+    }
+}
+
+#[test]
+fn it_varies_its_choice_at_a_low_skill_level() {
+    let mut rng = StdRng::seed_from_u64(28);
+    let mut chosen_moves = vec![];
+    let (mut search_state, moves) = search_state_with_root_scores(&[("e2e4", 30), ("d2d4", 20), ("c2c4", 10), ("g1f3", 0)]);
+    for _ in 0..100 {
+        let chosen = pick_weakened_move(&mut search_state, 0, moves[0], &mut rng);
+        assert!(moves.contains(&chosen));
+        if !chosen_moves.contains(&chosen) {
+            chosen_moves.push(chosen);
+        }
+    }
+    assert!(chosen_moves.len() > 1);
+}
+
+#[test]
+fn it_plays_a_legal_move_when_strength_is_limited() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.strength.skill_level = 0;
+    search_state.end_time = Instant::now().add(Duration::from_secs(10));
+    let mut position = get_position(START_POS);
+
+    let mv = search_with_strength(&mut position, 100, &mut search_state);
+
+    assert!(is_legal_move(&get_position(START_POS), &algebraic_move_from_move(mv)));
+    assert_eq!(search_state.iterative_depth, 1);
+    assert_eq!(search_state.multi_pv, 1);
+}
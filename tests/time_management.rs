@@ -0,0 +1,131 @@
+use rusty_rival::time_management::{allocate_time, TimeAllocation, TimeManager, DOMINANT_MOVE_MARGIN};
+use std::time::Duration;
+
+#[test]
+fn it_allocates_soft_and_hard_limits_from_the_clock() {
+    assert_eq!(
+        allocate_time(1000, 0, 9, 10),
+        TimeAllocation {
+            soft_millis: 99,
+            hard_millis: 396
+        }
+    );
+    // With no movestogo, 30 moves are assumed
+    assert_eq!(
+        allocate_time(31010, 0, 0, 10),
+        TimeAllocation {
+            soft_millis: 1000,
+            hard_millis: 4000
+        }
+    );
+    // Three quarters of the increment is added to the soft limit
+    assert_eq!(
+        allocate_time(31010, 400, 0, 10),
+        TimeAllocation {
+            soft_millis: 1300,
+            hard_millis: 5200
+        }
+    );
+    // The hard limit never exceeds half of the clock unless this is the last move of the time control
+    assert_eq!(allocate_time(250, 200, 0, 10).hard_millis, 120);
+    assert_eq!(allocate_time(1000, 0, 1, 10).hard_millis, 891);
+    // A clock shorter than the move overhead still allows a minimal search
+    assert_eq!(
+        allocate_time(5, 0, 0, 10),
+        TimeAllocation {
+            soft_millis: 1,
+            hard_millis: 1
+        }
+    );
+}
+
+#[test]
+fn it_always_starts_an_iteration_without_a_soft_limit() {
+    let mut time_manager = TimeManager::new(None);
+    time_manager.start_search(1);
+    assert!(time_manager.should_start_iteration(Duration::from_secs(3600)));
+}
+
+#[test]
+fn it_stops_after_one_iteration_with_a_single_legal_move() {
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(1);
+    time_manager.update(1, 0);
+    assert!(!time_manager.should_start_iteration(Duration::from_millis(1)));
+}
+
+#[test]
+fn it_shortens_the_soft_limit_when_the_best_move_is_stable() {
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(20);
+    for _ in 0..8 {
+        time_manager.update(1, 50);
+    }
+    assert!(time_manager.scale() < 1.0);
+    assert!(time_manager.should_start_iteration(Duration::from_millis(400)));
+    assert!(!time_manager.should_start_iteration(Duration::from_millis(900)));
+}
+
+#[test]
+fn it_extends_the_soft_limit_when_the_best_move_changes_or_the_score_drops() {
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(20);
+    time_manager.update(1, 50);
+    time_manager.update(2, 50);
+    assert!(time_manager.scale() > 1.0);
+    assert!(time_manager.should_start_iteration(Duration::from_millis(1200)));
+
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(20);
+    time_manager.update(1, 50);
+    time_manager.update(1, -50);
+    assert!(time_manager.scale() > 1.0);
+
+    // A new search forgets the previous one but keeps the soft limit
+    time_manager.start_search(20);
+    assert_eq!(time_manager.scale(), 1.0);
+    assert_eq!(time_manager.soft_limit, Some(Duration::from_millis(1000)));
+}
+
+#[test]
+fn it_stops_early_when_the_best_move_dominates() {
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(20);
+    for _ in 0..4 {
+        time_manager.update(1, 400);
+    }
+
+    // Not asked for until a quarter of the soft limit has been used
+    assert!(!time_manager.wants_second_best(Duration::from_millis(200)));
+    assert!(time_manager.wants_second_best(Duration::from_millis(250)));
+
+    // A close second best move keeps the search going
+    time_manager.update_second_best(400 - DOMINANT_MOVE_MARGIN + 1);
+    assert!(!time_manager.is_dominant());
+    assert!(time_manager.should_start_iteration(Duration::from_millis(300)));
+
+    time_manager.update_second_best(400 - DOMINANT_MOVE_MARGIN);
+    assert!(time_manager.is_dominant());
+    assert!(!time_manager.should_start_iteration(Duration::from_millis(300)));
+
+    // Each iteration has to show the margin again
+    time_manager.update(1, 400);
+    assert!(!time_manager.is_dominant());
+    assert!(time_manager.should_start_iteration(Duration::from_millis(300)));
+}
+
+#[test]
+fn it_only_checks_for_a_dominant_move_once_the_best_move_is_stable() {
+    let mut time_manager = TimeManager::new(Some(Duration::from_millis(1000)));
+    time_manager.start_search(20);
+    time_manager.update(1, 400);
+    time_manager.update(2, 400);
+    assert!(!time_manager.wants_second_best(Duration::from_millis(500)));
+
+    let mut time_manager = TimeManager::new(None);
+    time_manager.start_search(20);
+    for _ in 0..4 {
+        time_manager.update(1, 400);
+    }
+    assert!(!time_manager.wants_second_best(Duration::from_millis(500)));
+}
@@ -1,11 +1,18 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use either::{Either, Left, Right};
-use rusty_rival::engine_constants::UCI_MILLIS_REDUCTION;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rusty_rival::engine_constants::{DEFAULT_MOVE_OVERHEAD_MILLIS, HASH_ENTRY_BYTES};
 use rusty_rival::fen::get_position;
 use rusty_rival::move_constants::START_POS;
-use rusty_rival::types::{default_search_state, default_uci_state, BoundType, HashEntry, SearchState, UciState};
-use rusty_rival::uci::{extract_go_param, is_legal_move, run_command_test};
-use std::cmp::max;
-use std::time::Instant;
+use rusty_rival::perft::PERFT_HASH_ENTRY_BYTES;
+use rusty_rival::search_features::{SearchFeatures, LATE_MOVE_REDUCTIONS, MULTICUT, NULL_MOVE};
+use rusty_rival::types::{default_search_state, default_uci_state, BoundType, HashEntry, SearchHandle, SearchState, UciState};
+use rusty_rival::uci::{extract_go_param, is_legal_move, run_command, run_command_test};
+use rusty_rival::uci_command::parse_command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[test]
 pub fn it_sets_a_fen() {
@@ -53,7 +60,40 @@ pub fn it_runs_a_perft_test() {
         ),
         Right(None)
     );
-    assert_eq!(run_command_test(&mut uci_state, &mut search_state, "go perft 2"), Right(None))
+    assert_eq!(run_command_test(&mut uci_state, &mut search_state, "go perft 2"), Right(None));
+    assert_eq!(
+        run_command_test(&mut uci_state, &mut search_state, "go perft 2 divide"),
+        Right(None)
+    )
+}
+
+#[test]
+pub fn it_keeps_the_perft_hash_table_between_perft_commands() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+    assert!(search_state.perft_hash_table.is_none());
+
+    assert_eq!(
+        run_command_test(&mut uci_state, &mut search_state, "setoption name Hash value 4"),
+        Right(None)
+    );
+    assert_eq!(run_command_test(&mut uci_state, &mut search_state, "go perft 3"), Right(None));
+    let hash_table = search_state.perft_hash_table.clone().unwrap();
+    let entries = |search_state: &SearchState| search_state.hash_table.len() * HASH_ENTRY_BYTES as usize / PERFT_HASH_ENTRY_BYTES;
+    assert_eq!(hash_table.len(), entries(&search_state));
+
+    assert_eq!(run_command_test(&mut uci_state, &mut search_state, "go perft 3"), Right(None));
+    assert!(Arc::ptr_eq(&hash_table, search_state.perft_hash_table.as_ref().unwrap()));
+
+    // A new Hash size is picked up by the next perft
+    assert_eq!(
+        run_command_test(&mut uci_state, &mut search_state, "setoption name Hash value 1"),
+        Right(None)
+    );
+    assert!(search_state.perft_hash_table.is_none());
+    assert_eq!(run_command_test(&mut uci_state, &mut search_state, "go perft 3"), Right(None));
+    assert_eq!(search_state.perft_hash_table.as_ref().unwrap().len(), entries(&search_state));
+    assert!(entries(&search_state) < hash_table.len());
 }
 
 #[test]
@@ -114,6 +154,17 @@ pub fn it_takes_a_threefold_repetition_from_a_lost_position() {
     );
     let result = run_command_test(&mut uci_state, &mut search_state, "go depth 7");
     assert_success_message(result, |message| message.contains("bestmove d4f2"));
+
+    let nodes = search_state.nodes;
+    let result = run_command_test(&mut uci_state, &mut search_state, "state");
+    assert_success_message(result, |message| {
+        message.starts_with("Nodes ") && message.contains("Null move attempts") && message.contains("Branching factor")
+    });
+    let result = run_command_test(&mut uci_state, &mut search_state, "state");
+    assert_eq!(
+        result.right().flatten().unwrap().lines().next(),
+        Some(format!("Nodes {}", nodes).as_str())
+    );
 }
 
 #[test]
@@ -162,7 +213,7 @@ pub fn it_handles_a_bad_fen() {
     let command = "position fen rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0";
     assert_eq!(
         run_command_test(&mut uci_state, &mut search_state, command),
-        Left("Invalid FEN".to_string())
+        Left("Invalid FEN: expected 4 or 6 fields, found 5".to_string())
     );
 }
 
@@ -239,7 +290,48 @@ pub fn it_returns_a_best_move() {
     });
 }
 
-fn test_wtime_btime(fen: &str, cmd: &str, max_millis: u128) {
+#[test]
+pub fn it_handles_go_mate() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+
+    assert_eq!(
+        run_command_test(
+            &mut uci_state,
+            &mut search_state,
+            "position fen 4r1k1/5bpp/2p5/3pr3/8/1B3pPq/PPR2P2/2R2QK1 b - - 0 1"
+        ),
+        Right(None)
+    );
+    let result = run_command_test(&mut uci_state, &mut search_state, "go mate 3");
+    assert_success_message(result, |message| message.starts_with("bestmove e5e1"));
+    assert_eq!(search_state.mate_search, None);
+}
+
+#[test]
+pub fn it_stops_an_infinite_or_mate_search_at_the_other_limits_given() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+
+    let start = Instant::now();
+    let result = run_command_test(&mut uci_state, &mut search_state, "go infinite depth 3");
+    assert_success_message(result, |message| message.starts_with("bestmove"));
+    assert_eq!(search_state.iterative_depth, 3);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "go infinite nodes 2000");
+    assert_success_message(result, |message| message.starts_with("bestmove"));
+    assert!(search_state.nodes < 4000);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "go mate 20 movetime 200");
+    assert_success_message(result, |message| message.starts_with("bestmove"));
+    assert!(uci_state.mate);
+    assert_eq!(uci_state.move_time, 200 - DEFAULT_MOVE_OVERHEAD_MILLIS);
+    assert_eq!(search_state.mate_search, None);
+    assert!(Instant::now() - start < Duration::from_secs(30));
+}
+
+fn test_wtime_btime(fen: &str, cmd: &str, soft_millis: u64, hard_millis: u64) {
     let mut uci_state = default_uci_state();
     let mut search_state = default_search_state();
 
@@ -249,66 +341,78 @@ fn test_wtime_btime(fen: &str, cmd: &str, max_millis: u128) {
     );
     let start = Instant::now();
     let result = run_command_test(&mut uci_state, &mut search_state, cmd);
-    let millis = (Instant::now() - start).as_millis();
-    let adjusted_max_millis: u128 = max(10, max_millis - UCI_MILLIS_REDUCTION);
-    println!("{}", millis);
-    assert!(millis as f64 > adjusted_max_millis as f64 * 0.9 && millis <= max_millis);
+    let millis = (Instant::now() - start).as_millis() as u64;
+    println!("{} {} {}", millis, soft_millis, hard_millis);
+    assert_eq!(uci_state.move_time, hard_millis);
+    assert_eq!(search_state.time_manager.soft_limit, Some(Duration::from_millis(soft_millis)));
+    // No iteration is cut short before a quarter of the soft limit has been used
+    assert!(millis >= soft_millis / 4);
+    // The clock is only checked every 1000 nodes, which can take a while in a debug build
+    assert!(millis <= hard_millis + 50);
     assert_success_message(result, |message| message.contains("bestmove"));
 }
 
 #[test]
 pub fn it_handles_wtime_and_btime() {
-    // Time calculation: (time / (movestogo + 1)) * 0.95 + increment - UCI_MILLIS_REDUCTION
-    // When movestogo=0, defaults to 30 moves remaining
-
-    // wtime=1000, movestogo=9: 1000/10 * 0.95 - 5 = 90ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0 1",
-        "go wtime 1000 btime 1000 movestogo 9",
-        100,
-    );
-    // wtime=5000, movestogo=24: 5000/25 * 0.95 - 5 = 185ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0 1",
-        "go wtime 5000 btime 10000 movestogo 24",
-        200,
-    );
-    // btime=1000, movestogo=1: 1000/2 * 0.95 - 5 = 470ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR b KQkq - 0 1",
-        "go wtime 500 btime 1000 movestogo 1",
-        500,
-    );
-    // btime=250, movestogo=0 (→30): 250/31 * 0.95 - 5 = ~3ms → min 10ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR b KQkq - 0 1",
-        "go wtime 500 btime 250 movestogo 0",
-        15,
-    );
-    // wtime=1000, movestogo=9, winc=100: 1000/10 * 0.95 + 100 - 5 = 190ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0 1",
-        "go wtime 1000 btime 1000 movestogo 9 winc 100 binc 0",
-        200,
-    );
-    // wtime=5000, movestogo=24, winc=100: 5000/25 * 0.95 + 100 - 5 = 285ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0 1",
-        "go wtime 5000 btime 10000 movestogo 24 winc 100 binc 100",
-        300,
-    );
-    // btime=1000, movestogo=1, binc=200: 1000/2 * 0.95 + 200 - 5 = 670ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR b KQkq - 0 1",
-        "go wtime 500 btime 1000 movestogo 1 winc 200 binc 200",
-        700,
-    );
-    // btime=250, movestogo=0 (→30), binc=200: 250/31 * 0.95 + 200 - 5 = ~203ms
-    test_wtime_btime(
-        "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR b KQkq - 0 1",
-        "go wtime 500 btime 250 movestogo 0 winc 50 binc 200",
-        220,
+    let white_to_move = "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR w KQkq - 0 1";
+    let black_to_move = "rnbqkbnr/pppppppp/8/8/PPPPPPPP/8/8/RNBQKBNR b KQkq - 0 1";
+
+    // With the 10ms move overhead taken off the clock, soft = clock / (movestogo + 1) + 3/4 increment
+    // and hard = 4 * soft, both capped at half the clock, or 90% of it when movestogo is 1.
+    // A movestogo of 0 counts as 30.
+
+    // 990 / 10 = 99, 4 * 99 = 396
+    test_wtime_btime(white_to_move, "go wtime 1000 btime 1000 movestogo 9", 99, 396);
+    // 4990 / 25 = 199, 4 * 199 = 796
+    test_wtime_btime(white_to_move, "go wtime 5000 btime 10000 movestogo 24", 199, 796);
+    // 990 / 2 = 495, 4 * 495 capped at 891
+    test_wtime_btime(black_to_move, "go wtime 500 btime 1000 movestogo 1", 495, 891);
+    // 240 / 31 = 7, 4 * 7 = 28
+    test_wtime_btime(black_to_move, "go wtime 500 btime 250 movestogo 0", 7, 28);
+    // 99 + 75 = 174, 4 * 174 capped at 495
+    test_wtime_btime(white_to_move, "go wtime 1000 btime 1000 movestogo 9 winc 100 binc 0", 174, 495);
+    // 199 + 75 = 274, 4 * 274 = 1096
+    test_wtime_btime(white_to_move, "go wtime 5000 btime 10000 movestogo 24 winc 100 binc 100", 274, 1096);
+    // 495 + 150 = 645, 4 * 645 capped at 891
+    test_wtime_btime(black_to_move, "go wtime 500 btime 1000 movestogo 1 winc 200 binc 200", 645, 891);
+    // 7 + 150 capped at 120, 4 * 157 capped at 120
+    test_wtime_btime(black_to_move, "go wtime 500 btime 250 movestogo 0 winc 50 binc 200", 120, 120);
+}
+
+#[test]
+pub fn it_stops_early_when_one_move_dominates() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+
+    // Rxd5 wins an undefended queen, and no other move comes close
+    let fen = "r3k2r/ppp2ppp/8/3q4/8/8/PPPR1PPP/4K2R w Kk - 0 1";
+    assert_eq!(
+        run_command_test(&mut uci_state, &mut search_state, &format!("position fen {}", fen)),
+        Right(None)
     );
+    let start = Instant::now();
+    let result = run_command_test(&mut uci_state, &mut search_state, "go wtime 20000 btime 20000");
+    let millis = (Instant::now() - start).as_millis() as u64;
+    assert_success_message(result, |message| message.contains("bestmove d2d5"));
+    assert!(search_state.time_manager.is_dominant());
+    assert!(millis < uci_state.move_time);
+}
+
+#[test]
+pub fn it_applies_the_move_overhead_to_movetime() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Move Overhead value 50");
+    assert_eq!(result, Right(None));
+    assert_eq!(search_state.move_overhead, 50);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "go movetime 200");
+    assert_success_message(result, |message| message.contains("bestmove"));
+    assert_eq!(uci_state.move_time, 150);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Move Overhead value 6000");
+    assert_error_message(result, |message| message == "Move Overhead must be between 0 and 5000");
 }
 
 #[test]
@@ -405,6 +509,161 @@ pub fn it_handles_the_setoption_multipv_command() {
     assert_eq!(result, Right(None));
 }
 
+#[test]
+pub fn it_handles_the_setoption_search_features_command() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+
+    // Names as well as the bitmask are accepted, so the option is advertised as a string
+    let result = run_command_test(&mut uci_state, &mut search_state, "uci");
+    assert_success_message(result, |message| {
+        message.contains("\noption name SearchFeatures type string default all\n")
+    });
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        "setoption name SearchFeatures value -multicut -lmr",
+    );
+    assert_eq!(result, Right(None));
+    assert!(!search_state.search_features.enabled(MULTICUT));
+    assert!(!search_state.search_features.enabled(LATE_MOVE_REDUCTIONS));
+    assert!(search_state.search_features.enabled(NULL_MOVE));
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name SearchFeatures value 4");
+    assert_eq!(result, Right(None));
+    assert_eq!(search_state.search_features.to_string(), "nullmove");
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name SearchFeatures value -singular");
+    assert_eq!(result, Left("Unknown search feature singular".to_string()));
+    assert_eq!(search_state.search_features.to_string(), "nullmove");
+
+    // The advertised default restores every feature
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name SearchFeatures value all");
+    assert_eq!(result, Right(None));
+    assert_eq!(search_state.search_features, SearchFeatures::default());
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "go depth 3");
+    assert_success_message(result, |message| message.contains("bestmove"));
+}
+
+#[test]
+pub fn it_gives_the_same_answer_to_every_timed_go_in_deterministic_mode() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+    search_state.show_info = false;
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Deterministic value true");
+    assert_eq!(result, Right(None));
+    assert!(search_state.deterministic);
+    run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        "position fen r1bqk2r/1ppp1ppp/p1n2n2/2b1p3/B3P3/2N2N2/PPPP1PPP/R1BQ1RK1 w kq - 0 1",
+    );
+
+    for go in ["go movetime 20", "go wtime 1000 btime 1000 winc 10 binc 10"] {
+        let answers: Vec<(Option<String>, Option<String>)> = (0..2)
+            .map(|_| {
+                let bestmove = run_command_test(&mut uci_state, &mut search_state, go).right().flatten();
+                let state = run_command_test(&mut uci_state, &mut search_state, "state").right().flatten();
+                (bestmove, state)
+            })
+            .collect();
+        assert!(answers[0].0.as_ref().unwrap().starts_with("bestmove"));
+        assert_eq!(answers[0], answers[1], "{}", go);
+    }
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Deterministic value maybe");
+    assert_eq!(result, Left("usage: setoption name Deterministic value [true|false]".to_string()));
+}
+
+#[test]
+pub fn it_writes_self_play_positions_with_datagen() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+    let file = std::env::temp_dir().join("rusty_rival_datagen_test.txt");
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        &format!("datagen games 1 nodes 200 adjudicate 300 file {}", file.display()),
+    );
+    let written = std::fs::read_to_string(&file).unwrap();
+    let _ = std::fs::remove_file(&file);
+    assert_eq!(
+        result,
+        Right(Some(format!(
+            "Wrote {} positions from 1 games to {}",
+            written.lines().count(),
+            file.display()
+        )))
+    );
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "datagen games 1 file /nonexistent/dir/out.txt");
+    assert!(result.left().unwrap().starts_with("Cannot create /nonexistent/dir/out.txt"));
+}
+
+#[test]
+pub fn it_annotates_the_games_in_a_pgn() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+    let file = std::env::temp_dir().join("rusty_rival_annotate_test.pgn");
+    let out = std::env::temp_dir().join("rusty_rival_annotate_test_out.pgn");
+    std::fs::write(&file, "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n\n1. d4 d5 *\n").unwrap();
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        &format!("annotate file {} depth 3", file.display()),
+    );
+    assert_success_message(result, |pgn| pgn.contains("3... Nf6??") && pgn.matches("[Annotator").count() == 2);
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        &format!("annotate file {} depth 2 out {}", file.display(), out.display()),
+    );
+    let written = std::fs::read_to_string(&out).unwrap();
+    let _ = std::fs::remove_file(&file);
+    let _ = std::fs::remove_file(&out);
+    assert_eq!(result, Right(Some(format!("Annotated 2 games to {}", out.display()))));
+    assert!(written.contains("1. d4 {"));
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "annotate file /nonexistent/games.pgn");
+    assert!(result.left().unwrap().starts_with("Cannot read /nonexistent/games.pgn"));
+}
+
+#[test]
+pub fn it_writes_the_puzzles_in_a_pgn_as_epd() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+    let file = std::env::temp_dir().join("rusty_rival_puzzles_test.pgn");
+    let out = std::env::temp_dir().join("rusty_rival_puzzles_test_out.epd");
+    std::fs::write(&file, "1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n\n1. d4 d5 *\n").unwrap();
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        &format!("puzzles file {} depth 4", file.display()),
+    );
+    assert_success_message(result, |epd| epd.contains(" bm Qxf7#; id \"Game 1, move 4.\"; pv Qxf7#; "));
+
+    let result = run_command_test(
+        &mut uci_state,
+        &mut search_state,
+        &format!("puzzles file {} depth 4 out {}", file.display(), out.display()),
+    );
+    let written = std::fs::read_to_string(&out).unwrap();
+    let _ = std::fs::remove_file(&file);
+    let _ = std::fs::remove_file(&out);
+    assert_eq!(result, Right(Some(format!("Wrote 1 puzzles from 2 games to {}", out.display()))));
+    assert_eq!(written.lines().count(), 1);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "puzzles file /nonexistent/games.pgn");
+    assert!(result.left().unwrap().starts_with("Cannot read /nonexistent/games.pgn"));
+}
+
 #[test]
 pub fn it_handles_the_setoption_contempt_command() {
     let mut search_state = default_search_state();
@@ -417,6 +676,48 @@ pub fn it_handles_the_setoption_contempt_command() {
     assert_eq!(result, Right(None));
 }
 
+#[test]
+pub fn it_handles_the_setoption_show_wdl_command() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name UCI_ShowWDL value true");
+    assert!(search_state.show_wdl);
+    assert_eq!(result, Right(None));
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name UCI_ShowWDL value maybe");
+    assert!(result.is_left());
+    assert!(search_state.show_wdl);
+}
+
+#[test]
+pub fn it_handles_the_strength_setoption_commands() {
+    let mut search_state = default_search_state();
+    let mut uci_state = default_uci_state();
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Skill Level value 5");
+    assert_eq!(5, search_state.strength.skill_level);
+    assert_eq!(result, Right(None));
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name UCI_LimitStrength value true");
+    assert!(search_state.strength.limit_strength);
+    assert_eq!(result, Right(None));
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name UCI_Elo value 1800");
+    assert_eq!(1800, search_state.strength.elo);
+    assert_eq!(
+        result,
+        Right(Some(
+            "info string UCI_Elo is nominal and uncalibrated, 1800 plays at Skill Level 8".to_string()
+        ))
+    );
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name Skill Level value 21");
+    assert_error_message(result, |message| message == "Skill Level must be between 0 and 20");
+    let result = run_command_test(&mut uci_state, &mut search_state, "setoption name UCI_Elo value 100");
+    assert_error_message(result, |message| message == "UCI_Elo must be between 1000 and 2800");
+}
+
 #[test]
 pub fn it_handles_a_bad_setoption_name() {
     let mut uci_state = default_uci_state();
@@ -476,3 +777,118 @@ pub fn it_parses_params_from_a_go_command() {
 pub fn it_extracts_a_u64_param() {
     assert_eq!(456, extract_go_param("cat", "dog 123 cat 456 fox 789", 0))
 }
+
+#[test]
+pub fn it_reports_malformed_commands_as_errors() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+
+    for command in [
+        "go depth",
+        "go wtime abc",
+        "go perft",
+        "go perft 0",
+        "go mate x",
+        "position",
+        "position fen",
+        "position startpos moves e2e5",
+        "setoption name Hash value abc",
+        "setoption name MultiPV value many",
+        "setoption name Contempt",
+        "bench",
+        "mvm 10",
+    ] {
+        assert!(
+            run_command_test(&mut uci_state, &mut search_state, command).is_left(),
+            "{}",
+            command
+        );
+    }
+
+    assert_eq!(uci_state.fen, START_POS);
+    assert_eq!(search_state.multi_pv, 1);
+}
+
+#[test]
+pub fn it_keeps_the_previous_position_after_a_bad_fen() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "position fen not a fen");
+    assert_eq!(result, Left("Invalid FEN: expected 4 or 6 fields, found 3".to_string()));
+    assert_eq!(uci_state.fen, START_POS);
+
+    let result = run_command_test(&mut uci_state, &mut search_state, "position fen 8/8/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(result, Left("Invalid FEN: black has 0 kings".to_string()));
+    assert_eq!(uci_state.fen, START_POS);
+}
+
+#[test]
+pub fn it_never_panics_on_random_input() {
+    let mut uci_state = default_uci_state();
+    let mut search_state = default_search_state();
+    let mut search_handle: Option<SearchHandle> = None;
+    let mut rng = StdRng::seed_from_u64(26);
+
+    // Commands that search for a long time or exit the process are left out
+    let commands = [
+        "uci",
+        "isready",
+        "debug",
+        "setoption",
+        "register",
+        "ucinewgame",
+        "position",
+        "stop",
+        "state",
+        "",
+        "xyzzy",
+    ];
+    let tokens = [
+        "name",
+        "value",
+        "Hash",
+        "Clear",
+        "MultiPV",
+        "Contempt",
+        "SyzygyPath",
+        "on",
+        "off",
+        "startpos",
+        "fen",
+        "moves",
+        "e2e4",
+        "e7e5",
+        "g1f3",
+        "a7a8q",
+        "e2e9",
+        "8/8/8/8/4Q3/2P4k/8/5K2",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+        "w",
+        "b",
+        "KQkq",
+        "-",
+        "e3",
+        "0",
+        "1",
+        "-1",
+        "99999999999999999999",
+        "abc",
+        "\t",
+        "",
+    ];
+
+    for _ in 0..2000 {
+        let mut line = commands[rng.gen_range(0..commands.len())].to_string();
+        for _ in 0..rng.gen_range(0..10) {
+            line.push(' ');
+            line.push_str(tokens[rng.gen_range(0..tokens.len())]);
+        }
+        let _ = run_command(&mut uci_state, &mut search_state, &mut search_handle, &line);
+    }
+
+    for _ in 0..2000 {
+        let line: String = (0..rng.gen_range(0..40)).map(|_| char::from(rng.gen_range(0x20u8..0x7f))).collect();
+        let _ = parse_command(&line);
+    }
+}
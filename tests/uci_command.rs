@@ -0,0 +1,245 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rusty_rival::move_constants::START_POS;
+use rusty_rival::uci_command::{
+    is_move_token, parse_command, parse_go, tokenize, AnnotateParams, DatagenParams, GoParams, PuzzleParams, UciCommand, ANNOTATE_USAGE,
+    DATAGEN_USAGE, PUZZLES_USAGE,
+};
+
+#[test]
+fn it_tokenizes_on_any_whitespace() {
+    assert_eq!(tokenize("  go   depth\t5 \n"), vec!["go", "depth", "5"]);
+    assert!(tokenize("   ").is_empty());
+}
+
+#[test]
+fn it_parses_simple_commands() {
+    assert_eq!(parse_command(""), Ok(UciCommand::Empty));
+    assert_eq!(parse_command("uci"), Ok(UciCommand::Uci));
+    assert_eq!(parse_command("isready"), Ok(UciCommand::IsReady));
+    assert_eq!(parse_command("debug on"), Ok(UciCommand::Debug(true)));
+    assert_eq!(parse_command("debug off"), Ok(UciCommand::Debug(false)));
+    assert_eq!(parse_command("bench 500"), Ok(UciCommand::Bench(500)));
+    assert_eq!(parse_command("mvm 100 4"), Ok(UciCommand::Mvm { millis: 100, games: 4 }));
+    assert_eq!(parse_command("blah"), Err("Unknown command".to_string()));
+}
+
+#[test]
+fn it_rejects_malformed_arguments() {
+    assert_eq!(parse_command("debug"), Err("usage: debug [on|off]".to_string()));
+    assert_eq!(parse_command("bench"), Err("usage: bench <millis>".to_string()));
+    assert_eq!(parse_command("bench abc"), Err("usage: bench <millis>".to_string()));
+    assert_eq!(parse_command("mvm 100"), Err("usage: mvm <millis> <games>".to_string()));
+    assert_eq!(parse_command("go depth"), Err("Missing value for depth".to_string()));
+    assert_eq!(parse_command("go depth abc"), Err("Invalid value for depth: abc".to_string()));
+    assert_eq!(parse_command("go perft 0"), Err("perft depth must be at least 1".to_string()));
+    assert_eq!(parse_command("go mate 0"), Err("mate depth must be at least 1".to_string()));
+    assert!(parse_command("position").is_err());
+    assert!(parse_command("position fen").is_err());
+    assert!(parse_command("position startpos e2e4").is_err());
+    assert!(parse_command("setoption").is_err());
+    assert!(parse_command("setoption name value 5").is_err());
+}
+
+#[test]
+fn it_parses_setoption_names_with_spaces() {
+    assert_eq!(
+        parse_command("setoption name Clear Hash"),
+        Ok(UciCommand::SetOption {
+            name: "Clear Hash".to_string(),
+            value: None
+        })
+    );
+    assert_eq!(
+        parse_command("setoption name SyzygyPath value /my tables/syzygy"),
+        Ok(UciCommand::SetOption {
+            name: "SyzygyPath".to_string(),
+            value: Some("/my tables/syzygy".to_string())
+        })
+    );
+}
+
+#[test]
+fn it_parses_position_commands() {
+    assert_eq!(
+        parse_command("position startpos moves e2e4 e7e5"),
+        Ok(UciCommand::Position {
+            fen: START_POS.to_string(),
+            moves: vec!["e2e4".to_string(), "e7e5".to_string()]
+        })
+    );
+    assert_eq!(
+        parse_command("position fen 8/8/8/8/4Q3/2P4k/8/5K2 w - - 0 1"),
+        Ok(UciCommand::Position {
+            fen: "8/8/8/8/4Q3/2P4k/8/5K2 w - - 0 1".to_string(),
+            moves: vec![]
+        })
+    );
+}
+
+#[test]
+fn it_parses_go_parameters() {
+    let params = parse_go(&tokenize("wtime 1000 btime -20 winc 10 movestogo 5 searchmoves e2e4 d2d4 depth 7")).unwrap();
+    assert_eq!(
+        params,
+        GoParams {
+            wtime: Some(1000),
+            btime: Some(0),
+            winc: Some(10),
+            moves_to_go: Some(5),
+            depth: Some(7),
+            search_moves: vec!["e2e4".to_string(), "d2d4".to_string()],
+            ..GoParams::default()
+        }
+    );
+    assert_eq!(parse_go(&[]), Ok(GoParams::default()));
+    assert!(parse_go(&tokenize("infinite")).unwrap().infinite);
+    assert_eq!(parse_go(&tokenize("mate 3")).unwrap().mate, Some(3));
+    assert_eq!(parse_go(&tokenize("perft 4")).unwrap().perft, Some(4));
+    assert_eq!(
+        parse_go(&tokenize("perft 4 divide")),
+        Ok(GoParams {
+            perft: Some(4),
+            perft_divide: true,
+            ..GoParams::default()
+        })
+    );
+}
+
+#[test]
+fn it_recognises_move_tokens() {
+    assert!(is_move_token("e2e4"));
+    assert!(is_move_token("a7a8q"));
+    assert!(!is_move_token("a7a8k"));
+    assert!(!is_move_token("e2e9"));
+    assert!(!is_move_token("depth"));
+    assert!(!is_move_token(""));
+}
+
+#[test]
+fn it_parses_datagen() {
+    assert_eq!(parse_command("datagen"), Ok(UciCommand::Datagen(DatagenParams::default())));
+    assert_eq!(
+        parse_command("datagen games 10 nodes 2000 threads 4 seed 99 randomplies 6 adjudicate 800 file out.txt"),
+        Ok(UciCommand::Datagen(DatagenParams {
+            games: 10,
+            nodes: 2000,
+            threads: 4,
+            seed: 99,
+            random_plies: 6,
+            adjudicate: 800,
+            file: "out.txt".to_string(),
+        }))
+    );
+    assert_eq!(parse_command("datagen games x"), Err("Invalid value for games: x".to_string()));
+    assert_eq!(parse_command("datagen file"), Err("Missing value for file".to_string()));
+    assert_eq!(parse_command("datagen speed 3"), Err(DATAGEN_USAGE.to_string()));
+    assert_eq!(
+        parse_command("datagen threads 0"),
+        Err("datagen threads, nodes and adjudicate must be at least 1".to_string())
+    );
+}
+
+#[test]
+fn it_parses_annotate() {
+    assert_eq!(
+        parse_command("annotate file games.pgn"),
+        Ok(UciCommand::Annotate(AnnotateParams {
+            file: "games.pgn".to_string(),
+            ..AnnotateParams::default()
+        }))
+    );
+    assert_eq!(
+        parse_command("annotate file games.pgn out notes.pgn movetime 500 inaccuracy 30 mistake 90 blunder 250"),
+        Ok(UciCommand::Annotate(AnnotateParams {
+            file: "games.pgn".to_string(),
+            out: Some("notes.pgn".to_string()),
+            depth: None,
+            move_time: Some(500),
+            inaccuracy: 30,
+            mistake: 90,
+            blunder: 250,
+        }))
+    );
+    assert_eq!(parse_command("annotate depth 10"), Err(ANNOTATE_USAGE.to_string()));
+    assert_eq!(
+        parse_command("annotate file games.pgn mistake 20"),
+        Err("annotate thresholds must be positive with inaccuracy <= mistake <= blunder".to_string())
+    );
+}
+
+#[test]
+fn it_parses_puzzles() {
+    assert_eq!(
+        parse_command("puzzles file games.pgn"),
+        Ok(UciCommand::Puzzles(PuzzleParams {
+            file: "games.pgn".to_string(),
+            ..PuzzleParams::default()
+        }))
+    );
+    assert_eq!(
+        parse_command("puzzles file games.pgn out puzzles.epd depth 8 gap 150 decisive 400 equal 30 plies 7"),
+        Ok(UciCommand::Puzzles(PuzzleParams {
+            file: "games.pgn".to_string(),
+            out: Some("puzzles.epd".to_string()),
+            depth: Some(8),
+            move_time: None,
+            gap: 150,
+            decisive: 400,
+            equal: 30,
+            plies: 7,
+        }))
+    );
+    assert_eq!(parse_command("puzzles out puzzles.epd"), Err(PUZZLES_USAGE.to_string()));
+    assert_eq!(
+        parse_command("puzzles file games.pgn decisive 40 equal 50"),
+        Err("puzzles gap and plies must be at least 1, with 0 <= equal < decisive".to_string())
+    );
+}
+
+#[test]
+fn it_never_panics_parsing_random_go_commands() {
+    let mut rng = StdRng::seed_from_u64(26);
+    let tokens = [
+        "wtime",
+        "btime",
+        "winc",
+        "binc",
+        "movetime",
+        "movestogo",
+        "depth",
+        "nodes",
+        "mate",
+        "perft",
+        "divide",
+        "infinite",
+        "ponder",
+        "searchmoves",
+        "e2e4",
+        "a7a8q",
+        "e2e9",
+        "0",
+        "1",
+        "-1",
+        "-99999999999999999999",
+        "99999999999999999999",
+        "abc",
+        "",
+    ];
+
+    for _ in 0..5000 {
+        let mut line = "go".to_string();
+        for _ in 0..rng.gen_range(0..12) {
+            line.push(' ');
+            line.push_str(tokens[rng.gen_range(0..tokens.len())]);
+        }
+        match parse_command(&line) {
+            Ok(UciCommand::Go(params)) => {
+                assert!(params.mate.is_none_or(|m| m >= 1), "{}", line);
+                assert!(params.perft.is_none_or(|p| p >= 1), "{}", line);
+            }
+            Ok(other) => panic!("{} parsed as {:?}", line, other),
+            Err(_) => {}
+        }
+    }
+}
@@ -5,11 +5,15 @@ use rusty_rival::move_constants::{
     BLACK_KING_CASTLE_MOVE_MASK, BLACK_QUEEN_CASTLE_MOVE_MASK, PIECE_MASK_KING, PIECE_MASK_KNIGHT, PIECE_MASK_PAWN, PIECE_MASK_ROOK,
     START_POS, WHITE_KING_CASTLE_MOVE, WHITE_KING_CASTLE_MOVE_MASK, WHITE_QUEEN_CASTLE_MOVE_MASK,
 };
-use rusty_rival::types::Move;
+use rusty_rival::search::iterative_deepening;
+use rusty_rival::types::{default_search_state, Move, SharedHashTable};
 use rusty_rival::utils::{
-    captured_piece_value, castle_mask, from_square_mask, from_square_part, hydrate_move_from_algebraic_move, invert_fen, moving_piece_mask,
-    to_square_part,
+    captured_piece_value, castle_mask, from_square_mask, from_square_part, hydrate_move_from_algebraic_move, info_line, invert_fen,
+    moving_piece_mask, to_square_part,
 };
+use std::ops::Add;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[test]
 fn it_creates_a_move_with_the_from_part_only() {
@@ -126,3 +130,42 @@ fn it_inverts_a_fen() {
         "r1q1k3/1R2n2p/5b2/5r2/p1Pp4/7P/1p2p3/6K1 b q - 0 1"
     );
 }
+
+#[test]
+fn it_builds_a_complete_info_line() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    let mut position = get_position("8/8/8/8/4Q3/2P4k/8/5K2 w - - 0 1");
+    iterative_deepening(&mut position, 5, &mut search_state);
+
+    let best = search_state.current_best.clone();
+    let line = info_line(&search_state, 1, &best, None);
+    assert!(line.starts_with("info depth 5 seldepth "), "{}", line);
+    assert!(line.contains(" multipv 1 score mate 2 nodes "), "{}", line);
+    assert!(line.contains(" hashfull ") && line.contains(" tbhits 0 time "), "{}", line);
+    assert!(search_state.seldepth >= 5);
+
+    let line = info_line(&search_state, 1, &(best.0.clone(), 25), Some("lowerbound"));
+    assert!(line.contains(" score cp 25 lowerbound nodes "), "{}", line);
+
+    search_state.show_wdl = true;
+    let line = info_line(&search_state, 1, &best, None);
+    assert!(line.contains(" score mate 2 wdl 1000 0 0 nodes "), "{}", line);
+}
+
+#[test]
+fn it_estimates_how_full_the_hash_table_is() {
+    let mut search_state = default_search_state();
+    search_state.show_info = false;
+    search_state.hash_table = Arc::new(SharedHashTable::new_with_entries(1000));
+    assert_eq!(search_state.hash_table.hashfull(search_state.hash_table_version), 0);
+
+    search_state.end_time = Instant::now().add(Duration::from_secs(100));
+    iterative_deepening(&mut get_position(START_POS), 6, &mut search_state);
+    let hashfull = search_state.hash_table.hashfull(search_state.hash_table_version);
+    assert!(hashfull > 500 && hashfull <= 1000, "{}", hashfull);
+
+    // Entries from earlier searches don't count
+    assert_eq!(search_state.hash_table.hashfull(search_state.hash_table_version + 1), 0);
+}
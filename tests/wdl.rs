@@ -0,0 +1,61 @@
+use rusty_rival::search::MATE_SCORE;
+use rusty_rival::wdl::{fit_wdl_model, parse_result_label, WdlModel, WdlSample, DEFAULT_WDL_MODEL};
+
+#[test]
+fn it_gives_win_draw_loss_chances_that_add_up() {
+    for score in [-3000, -400, -50, 0, 50, 400, 3000] {
+        for material in [0, 3000, 8000] {
+            let (win, draw, loss) = DEFAULT_WDL_MODEL.wdl(score, material);
+            assert_eq!(win + draw + loss, 1000);
+            assert_eq!(DEFAULT_WDL_MODEL.wdl(-score, material), (loss, draw, win));
+        }
+    }
+    let (win, draw, loss) = DEFAULT_WDL_MODEL.wdl(0, 8000);
+    assert!(draw > win && win == loss);
+}
+
+#[test]
+fn it_converts_an_advantage_more_reliably_with_less_material() {
+    assert!(DEFAULT_WDL_MODEL.wdl(300, 1000).0 > DEFAULT_WDL_MODEL.wdl(300, 8000).0);
+}
+
+#[test]
+fn it_knows_mate_scores_decide_the_game() {
+    assert_eq!(DEFAULT_WDL_MODEL.wdl(MATE_SCORE - 5, 8000), (1000, 0, 0));
+    assert_eq!(DEFAULT_WDL_MODEL.wdl(-MATE_SCORE + 4, 8000), (0, 0, 1000));
+}
+
+#[test]
+fn it_recovers_a_model_from_labelled_results() {
+    let target = WdlModel {
+        a: (250.0, 350.0),
+        b: (90.0, 110.0),
+    };
+    let mut samples = vec![];
+    for score in (-800..=800).step_by(40) {
+        for material in [1000, 7000] {
+            let (win, draw, loss) = target.wdl(score, material);
+            for (count, result) in [(win, 1.0), (draw, 0.5), (loss, 0.0)] {
+                for _ in 0..count / 10 {
+                    samples.push(WdlSample { score, material, result });
+                }
+            }
+        }
+    }
+
+    let model = fit_wdl_model(&samples, DEFAULT_WDL_MODEL);
+
+    assert!(model.loss(&samples) < DEFAULT_WDL_MODEL.loss(&samples));
+    for (fitted, expected) in [(model.a.0, 250.0), (model.a.1, 350.0), (model.b.0, 90.0), (model.b.1, 110.0)] {
+        assert!((fitted - expected).abs() < 15.0, "{:?}", model);
+    }
+}
+
+#[test]
+fn it_parses_result_labels() {
+    assert_eq!(parse_result_label("[1.0]"), Some(1.0));
+    assert_eq!(parse_result_label("[0.5]"), Some(0.5));
+    assert_eq!(parse_result_label("c9 \"0-1\";"), Some(0.0));
+    assert_eq!(parse_result_label("c9 \"1/2-1/2\";"), Some(0.5));
+    assert_eq!(parse_result_label(""), None);
+}
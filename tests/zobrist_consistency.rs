@@ -1,5 +1,6 @@
+use rusty_rival::endgame::material_key;
 use rusty_rival::fen::get_position;
-use rusty_rival::hash::zobrist_lock;
+use rusty_rival::hash::{pawn_zobrist_key, zobrist_lock};
 use rusty_rival::make_move::{make_move, make_move_in_place, unmake_move};
 use rusty_rival::moves::generate_moves;
 
@@ -51,6 +52,24 @@ fn test_zobrist_consistency_between_make_and_make_in_place() {
                 fen, m, zobrist_after_inplace_make, recalc_inplace
             );
 
+            // The pawn and material keys are kept up to date the same way
+            assert_eq!(
+                copy_pos.pawn_zobrist_key,
+                pawn_zobrist_key(&copy_pos),
+                "FEN: {}, Move: {:x}",
+                fen,
+                m
+            );
+            assert_eq!(
+                inplace_pos.pawn_zobrist_key,
+                pawn_zobrist_key(&inplace_pos),
+                "FEN: {}, Move: {:x}",
+                fen,
+                m
+            );
+            assert_eq!(copy_pos.material_key, material_key(&copy_pos), "FEN: {}, Move: {:x}", fen, m);
+            assert_eq!(inplace_pos.material_key, material_key(&inplace_pos), "FEN: {}, Move: {:x}", fen, m);
+
             // After unmake, zobrist should match original
             unmake_move(&mut inplace_pos, m, &unmake);
             assert_eq!(
@@ -58,6 +77,7 @@ fn test_zobrist_consistency_between_make_and_make_in_place() {
                 "FEN: {}, Move: {:x}\nafter unmake zobrist {:x} != original {:x}",
                 fen, m, inplace_pos.zobrist_lock, original.zobrist_lock
             );
+            assert_eq!(original.material_key, inplace_pos.material_key, "FEN: {}, Move: {:x}", fen, m);
         }
     }
 }